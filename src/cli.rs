@@ -1,25 +1,36 @@
-use crate::data::{DataFormat, DataStore, WorkingDir};
-use crate::effects::StackEffect;
-use crate::output::{NoiseLevel, OutputFormat};
-use clap::{Args, Parser, Subcommand, ValueEnum};
+use crate::data::{
+    create_project_dir, find_project_dir, DataFormat, DataStore, StackMode, Timestamp, WorkingDir,
+};
+use crate::effects::{ItemFilter, ItemRef, RetentionPolicy, SearchField, SearchQuery, StackEffect};
+use crate::error::SigiError;
+use crate::output::{parse_locale, DisplayZone, NoiseLevel, OutputFormat, TimeDisplay, TimeStyle};
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+use std::collections::BTreeSet;
+use std::path::Path;
 use std::str::FromStr;
 use std::{error, fmt};
 
+mod config;
+use config::Config;
+
 mod interact;
 use interact::*;
 
+mod when;
+use when::*;
+
 /// The current version of the CLI. (As defined in Cargo.toml)
 pub const SIGI_VERSION: &str = std::env!("CARGO_PKG_VERSION");
 
 const DEFAULT_STACK_NAME: &str = "sigi";
 const DEFAULT_FORMAT: OutputFormat = OutputFormat::Human(NoiseLevel::Normal);
-const DEFAULT_DATA_STORE: DataStore = DataStore {
-    working_dir: WorkingDir::HomeDir,
-    data_format: DataFormat::SigiJson,
-};
+const DEFAULT_DATA_FORMAT: DataFormat = DataFormat::SigiJson;
 const DEFAULT_SHORT_LIST_LIMIT: usize = 10;
 
 // === Glossary ===
+const COMPACT_TERMS: [&str; 1] = ["compact"];
 const COMPLETE_TERMS: [&str; 4] = ["complete", "done", "finish", "fulfill"];
 const COUNT_TERMS: [&str; 3] = ["count", "size", "length"];
 const DELETE_TERMS: [&str; 5] = ["delete", "pop", "remove", "cancel", "drop"];
@@ -31,51 +42,219 @@ const DELETE_ALL_TERMS: [&str; 6] = [
     "cancel-all",
     "drop-all",
 ];
+const DUE_TERMS: [&str; 1] = ["due"];
 const EDIT_TERMS: [&str; 1] = ["edit"];
 const HEAD_TERMS: [&str; 3] = ["head", "top", "first"];
 const IS_EMPTY_TERMS: [&str; 2] = ["is-empty", "empty"];
 const LIST_TERMS: [&str; 4] = ["list", "ls", "snoop", "all"];
 const LIST_STACKS_TERMS: [&str; 2] = ["list-stacks", "stacks"];
+const MODE_TERMS: [&str; 1] = ["mode"];
 const MOVE_TERMS: [&str; 1] = ["move"];
 const MOVE_ALL_TERMS: [&str; 1] = ["move-all"];
 const NEXT_TERMS: [&str; 4] = ["next", "later", "cycle", "bury"];
-const PEEK_TERMS: [&str; 2] = ["peek", "show"];
+const OVERDUE_TERMS: [&str; 2] = ["overdue", "late"];
+const PEEK_TERMS: [&str; 1] = ["peek"];
 const PICK_TERMS: [&str; 1] = ["pick"];
+const PRIORITY_TERMS: [&str; 2] = ["priority", "prioritize"];
 const PUSH_TERMS: [&str; 6] = ["push", "create", "add", "do", "start", "new"];
+const RESTORE_TERMS: [&str; 2] = ["restore", "undelete"];
 const ROT_TERMS: [&str; 2] = ["rot", "rotate"];
+const SEARCH_TERMS: [&str; 3] = ["search", "find", "grep"];
+const SHOW_TERMS: [&str; 2] = ["show", "details"];
+const SNOOZE_TERMS: [&str; 2] = ["snooze", "defer"];
+const STATS_TERMS: [&str; 2] = ["stats", "statistics"];
 const SWAP_TERMS: [&str; 1] = ["swap"];
+const TAG_TERMS: [&str; 2] = ["tag", "label"];
 const TAIL_TERMS: [&str; 3] = ["tail", "bottom", "last"];
+const UNDO_TERMS: [&str; 1] = ["undo"];
+const UNTAG_TERMS: [&str; 2] = ["untag", "unlabel"];
+const REDO_TERMS: [&str; 1] = ["redo"];
+const REPORT_TERMS: [&str; 3] = ["report", "standup", "journal"];
+const RENAME_STACK_TERMS: [&str; 1] = ["rename-stack"];
+const COPY_STACK_TERMS: [&str; 1] = ["copy-stack"];
+const DELETE_STACK_TERMS: [&str; 1] = ["delete-stack"];
+const ARCHIVE_STACK_TERMS: [&str; 1] = ["archive-stack"];
+
+/// How many days back `list-stacks --summary` counts completed items.
+const DEFAULT_SUMMARY_DAYS: &str = "7";
+/// Where `report` starts when no --since is given.
+const DEFAULT_REPORT_SINCE: &str = "yesterday";
+/// How many weeks back `stats` counts completed items for throughput.
+const DEFAULT_STATS_WEEKS: &str = "4";
+const DEFAULT_STATS_OLDEST: &str = "3";
 // === /glossary ===
 
+// Settings are resolved with flags first, then environment variables, then the
+// config file, then the built-in defaults.
 pub fn run() {
-    let args = Cli::parse();
-
-    let stack = args.stack.unwrap_or_else(|| DEFAULT_STACK_NAME.into());
-    let store = args
-        .data_store
-        .map(|dir| DataStore {
-            working_dir: WorkingDir::Dir(dir),
-            data_format: DataFormat::SigiJson,
-        })
-        .unwrap_or(DEFAULT_DATA_STORE);
+    let config = Config::load().unwrap_or_else(|err| {
+        report_error(&err);
+        // A broken config file shouldn't stop --help or --version, which may
+        // be how to find out what's wrong with it.
+        if let Err(help) = Cli::try_parse_from(std::env::args()) {
+            if matches!(
+                help.kind(),
+                ErrorKind::DisplayHelp | ErrorKind::DisplayVersion
+            ) {
+                help.exit();
+            }
+        }
+        std::process::exit(err.exit_code());
+    });
+    let argv = expand_alias(std::env::args().collect(), &config);
+    let args = Cli::parse_from(&argv);
+
+    exit_on_error(choose_time_display(&args.tc, &config));
+    // Times given in flags are read in the zone just chosen, which --tz can
+    // choose, so read them again now that it's known.
+    let args = Cli::parse_from(argv);
+
+    let stack = args
+        .stack
+        .or_else(|| config.stack.clone())
+        .unwrap_or_else(|| DEFAULT_STACK_NAME.into());
+    let working_dir = match (args.data_store, std::env::var("SIGI_HOME")) {
+        (Some(dir), _) => WorkingDir::Dir(dir),
+        (None, Ok(_)) => WorkingDir::HomeDir,
+        (None, Err(_)) => std::env::current_dir()
+            .ok()
+            .and_then(|cwd| find_project_dir(&cwd))
+            .map(WorkingDir::Project)
+            .or_else(|| config.data_store().map(WorkingDir::Dir))
+            .unwrap_or(WorkingDir::HomeDir),
+    };
+    let store = DataStore {
+        working_dir,
+        data_format: args
+            .data_format
+            .or_else(|| config.data_format.clone())
+            .map(StoreFormat::into_data_format)
+            .unwrap_or(DEFAULT_DATA_FORMAT),
+    };
+    let default_format = config.format.unwrap_or(DEFAULT_FORMAT);
 
     match args.mode {
         None => {
-            let output = args.fc.into_output_format().unwrap_or(DEFAULT_FORMAT);
+            let output = args.fc.into_output_format().unwrap_or(default_format);
             let peek = StackEffect::Peek { stack };
-            peek.run(&store, &output);
+            exit_on_error(peek.run(&store, &output));
         }
         Some(Mode::Command(command)) => {
-            let (effect, effect_fc) = command.into_effect_and_fc(stack);
-            let output = args.fc.into_fallback_for(effect_fc);
-            effect.run(&store, &output);
+            let (effect, effect_fc) = command.into_effect_and_fc(stack, &config);
+            let output = args.fc.into_fallback_for(effect_fc, default_format);
+            exit_on_error(effect.run(&store, &output));
         }
         Some(Mode::Interactive { fc }) => {
-            let output = args.fc.into_fallback_for(fc);
-            interact(stack, store, output);
+            let output = args.fc.into_fallback_for(fc, default_format);
+            interact(stack, store, output, &config);
         }
-        Some(Mode::ReadStdin) => interact(stack, store, OutputFormat::TerseText),
+        Some(Mode::ReadStdin) => interact(stack, store, OutputFormat::TerseText, &config),
+        Some(Mode::Init { dir, fc }) => {
+            let output = args.fc.into_fallback_for(fc, default_format);
+            exit_on_error(init_project(dir, &output));
+        }
+    };
+}
+
+fn init_project(dir: Option<String>, output: &OutputFormat) -> Result<(), SigiError> {
+    let parent = dir.unwrap_or_else(|| ".".to_string());
+    let (store, created) = create_project_dir(Path::new(&parent))?;
+    let action = if created {
+        "Initialized"
+    } else {
+        "Already initialized"
     };
+    output.log(vec!["action", "store"], vec![vec![action, &store]]);
+    Ok(())
+}
+
+/// Replace an alias from the config file, given as the command, with what it
+/// stands for. Commands sigi already knows can't be replaced.
+fn expand_alias(mut args: Vec<String>, config: &Config) -> Vec<String> {
+    let cli = Cli::command();
+    let takes_value = |arg: &str| {
+        cli.get_arguments().any(|known| {
+            let takes_value = known.get_action().takes_values();
+            let matches = match arg.strip_prefix("--") {
+                Some(long) => known
+                    .get_long_and_visible_aliases()
+                    .is_some_and(|longs| longs.contains(&long)),
+                None => arg.len() == 2 && known.get_short().is_some_and(|c| arg.ends_with(c)),
+            };
+            takes_value && matches
+        })
+    };
+
+    // Skip past the options (and their values) to the command.
+    let mut i = 1;
+    while let Some(arg) = args.get(i) {
+        if !arg.starts_with('-') || arg == "-" {
+            break;
+        }
+        if arg == "--" {
+            return args;
+        }
+        i += if takes_value(arg) { 2 } else { 1 };
+    }
+
+    let expansion = args
+        .get(i)
+        .filter(|name| cli.find_subcommand(name).is_none())
+        .and_then(|name| config.alias(name));
+    if let Some(expansion) = expansion {
+        args.splice(i..=i, expansion);
+    }
+    args
+}
+
+/// Choose how times are shown.
+fn choose_time_display(tc: &TimeConfig, config: &Config) -> Result<(), SigiError> {
+    let invalid = |setting: &str| {
+        let setting = setting.to_string();
+        |details| SigiError::InvalidSetting { setting, details }
+    };
+
+    let zone = match (tc.tz, std::env::var("SIGI_TZ")) {
+        (Some(zone), _) => zone,
+        (None, Ok(zone)) => zone.parse().map_err(invalid("SIGI_TZ"))?,
+        (None, Err(_)) => config.tz.unwrap_or_default(),
+    };
+    // The locale environment is often set to things sigi doesn't know (like
+    // C.UTF-8), which shouldn't stop sigi from working.
+    let locale = tc
+        .locale
+        .or_else(|| {
+            ["LC_ALL", "LC_TIME", "LANG"]
+                .iter()
+                .find_map(|var| std::env::var(var).ok().filter(|locale| !locale.is_empty()))
+                .and_then(|locale| parse_locale(&locale).ok())
+        })
+        .or(config.locale)
+        .unwrap_or_default();
+
+    TimeDisplay {
+        zone,
+        style: tc
+            .time_format
+            .clone()
+            .or_else(|| config.time_format.clone())
+            .unwrap_or_default(),
+        locale,
+    }
+    .use_for_display();
+    Ok(())
+}
+
+/// Print an error for humans, and exit with a code scripts can tell apart.
+fn exit_on_error(result: Result<(), SigiError>) {
+    if let Err(err) = result {
+        report_error(&err);
+        std::process::exit(err.exit_code());
+    }
+}
+
+fn report_error(err: &SigiError) {
+    eprintln!("Error: {}", err);
 }
 
 #[derive(Parser)]
@@ -85,14 +264,21 @@ struct Cli {
     #[command(flatten)]
     fc: FormatConfig,
 
-    /// Manage items in a specific stack
+    /// Manage items in a specific stack. The default is the stack in the config file, or "sigi"
     #[arg(short='t', long, visible_aliases = &["topic", "about", "namespace"])]
     stack: Option<String>,
 
-    /// (Advanced) Manage sigi stacks in a specific directory. The default is either the value of a SIGI_HOME environment variable or your OS-specific home directory
+    /// (Advanced) Manage sigi stacks in a specific directory. The default is the value of a SIGI_HOME environment variable, the nearest .sigi directory (see init), the data-store in the config file, or your OS-specific home directory
     #[arg(short = 'd', long, visible_aliases = &["dir", "directory", "store"])]
     data_store: Option<String>,
 
+    /// (Advanced) How sigi stacks are persisted. The default is json, one file per stack. Use sqlite for stacks with thousands of items
+    #[arg(long, value_enum)]
+    data_format: Option<StoreFormat>,
+
+    #[command(flatten)]
+    tc: TimeConfig,
+
     #[command(subcommand)]
     mode: Option<Mode>,
 }
@@ -112,17 +298,72 @@ enum Mode {
     #[command(name = "-")]
     ReadStdin,
 
+    /// Create a .sigi directory for a project's own stacks. Sigi uses the nearest .sigi directory
+    /// in or above the current directory, unless told otherwise by --data-store or SIGI_HOME
+    Init {
+        /// Where to create the .sigi directory. Default is the current directory
+        dir: Option<String>,
+
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
     #[command(flatten)]
     Command(Command),
 }
 
 #[derive(Subcommand)]
 enum Command {
+    /// Hide the stack (and "<STACK>_history") from list-stacks, without deleting it
+    #[command(visible_aliases = &ARCHIVE_STACK_TERMS[1..])]
+    ArchiveStack {
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
+    /// Trim "<STACK>_history" down to what the retention policy keeps, archiving or dropping older items
+    #[command(visible_aliases = &COMPACT_TERMS[1..])]
+    Compact {
+        /// Compact every history stack that isn't archived
+        #[arg(long)]
+        all: bool,
+
+        /// Keep the N most recently finished items. Default is keep under [retention] in the config file
+        #[arg(long, value_name = "N")]
+        keep: Option<usize>,
+
+        /// Keep items completed or deleted in the last N days. Default is keep-days under [retention] in the config file
+        #[arg(long, value_name = "N")]
+        keep_days: Option<u32>,
+
+        /// Archive older items in a stack for each month, like "_archive/<STACK>_history/2024-06". This is the default
+        #[arg(long)]
+        archive: bool,
+
+        /// Drop older items instead of archiving them
+        #[arg(long, conflicts_with = "archive")]
+        drop: bool,
+
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
     /// Move the current item to "<STACK>_history" and mark as completed
     #[command(visible_aliases = &COMPLETE_TERMS[1..])]
     Complete {
-        /// The number of the item to complete. Default is the most recent item (0 index)
-        n: Option<usize>,
+        /// The number or @ID of the item to complete. Default is the most recent item (0 index)
+        n: Option<ItemRef>,
+
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
+    /// Copy the stack's items to a new stack
+    #[command(arg_required_else_help = true, visible_aliases = &COPY_STACK_TERMS[1..])]
+    CopyStack {
+        #[arg(name = "destination")]
+        /// The name of the new stack
+        dest: String,
 
         #[command(flatten)]
         fc: FormatConfig,
@@ -131,6 +372,13 @@ enum Command {
     /// Print the total number of items in the stack
     #[command(visible_aliases = &COUNT_TERMS[1..])]
     Count {
+        /// Include the stacks in this stack's namespace, like "<STACK>/bugs"
+        #[arg(short, long)]
+        recursive: bool,
+
+        #[command(flatten)]
+        filter: FilterConfig,
+
         #[command(flatten)]
         fc: FormatConfig,
     },
@@ -138,8 +386,8 @@ enum Command {
     /// Move the current item to "<STACK>_history" and mark as deleted
     #[command(visible_aliases = &DELETE_TERMS[1..])]
     Delete {
-        /// The number of the item to delete. Default is the most recent item (0 index)
-        n: Option<usize>,
+        /// The number or @ID of the item to delete. Default is the most recent item (0 index)
+        n: Option<ItemRef>,
 
         #[command(flatten)]
         fc: FormatConfig,
@@ -152,27 +400,59 @@ enum Command {
         fc: FormatConfig,
     },
 
+    /// Delete the stack and "<STACK>_history" entirely
+    #[command(visible_aliases = &DELETE_STACK_TERMS[1..])]
+    DeleteStack {
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
+    /// Set or clear when an item is due
+    #[command(arg_required_else_help = true, visible_aliases = &DUE_TERMS[1..])]
+    Due {
+        /// The number or @ID of the item. Default is the most recent item (0 index)
+        n: Option<ItemRef>,
+
+        /// When the item is due, e.g. tomorrow, 3d, 2024-06-01 or "2024-06-01 17:00"
+        #[arg(long, value_parser = parse_end_of, required_unless_present = "never")]
+        by: Option<Timestamp>,
+
+        /// Clear the due date
+        #[arg(long, conflicts_with = "by")]
+        never: bool,
+
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
     /// Edit the content of an item. Other metadata like creation date is left unchanged.
     #[command(visible_aliases = &EDIT_TERMS[1..])]
     Edit {
         /// The editor to execute. If unspecified, the editor launched will be the value of
-        /// VISUAL, EDITOR, or if both env variables are unset, nano.
+        /// VISUAL, EDITOR, the editor in the config file, or if none of those are set, nano.
         #[arg(short, long)]
         editor: Option<String>,
 
-        /// The number of the item to edit. Default is the most recent item (0 index)
-        n: Option<usize>,
+        /// The number or @ID of the item to edit. Default is the most recent item (0 index)
+        n: Option<ItemRef>,
+
+        /// Edit the item's body (longer notes) instead of its headline
+        #[arg(short, long)]
+        body: bool,
 
         #[command(flatten)]
         fc: FormatConfig,
     },
 
-    /// Print the first N items (default is 10)
+    /// Print the first N items (default is 10, or the list-limit in the config file)
     #[command(visible_aliases = &HEAD_TERMS[1..])]
     Head {
         /// The number of items to display
         n: Option<usize>,
 
+        #[command(flatten)]
+        filter: FilterConfig,
+
         #[command(flatten)]
         fc: FormatConfig,
     },
@@ -188,6 +468,17 @@ enum Command {
     /// Print all items
     #[command(visible_aliases = &LIST_TERMS[1..])]
     List {
+        /// Include the stacks in this stack's namespace, like "<STACK>/bugs"
+        #[arg(short, long)]
+        recursive: bool,
+
+        /// Print the stack as it was at a past time, e.g. yesterday, "3d ago" or 2024-06-01 (the end of that day)
+        #[arg(long, value_name = "WHEN", value_parser = parse_end_of)]
+        as_of: Option<Timestamp>,
+
+        #[command(flatten)]
+        filter: FilterConfig,
+
         #[command(flatten)]
         fc: FormatConfig,
     },
@@ -195,6 +486,18 @@ enum Command {
     /// Print all stacks
     #[command(visible_aliases = &LIST_STACKS_TERMS[1..])]
     ListStacks {
+        /// Print archived stacks instead (see archive-stack)
+        #[arg(long)]
+        archived: bool,
+
+        /// Also print history stacks, each under the stack it belongs to
+        #[arg(long)]
+        history: bool,
+
+        /// Print each stack's item count, top item, newest and oldest items, and how many items were completed in the last DAYS days (default 7)
+        #[arg(long, value_name = "DAYS", num_args = 0..=1, default_missing_value = DEFAULT_SUMMARY_DAYS)]
+        summary: Option<u32>,
+
         #[command(flatten)]
         fc: FormatConfig,
     },
@@ -206,6 +509,19 @@ enum Command {
         /// The stack that will get the source stack's current item
         dest: String,
 
+        /// The number or @ID of the item to move. Default is the most recent item (0 index)
+        n: Option<ItemRef>,
+
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
+    /// Print the stack's mode, or change it: stack (last in, first out), queue (first in, first out) or priority (lowest priority number first)
+    #[command(visible_aliases = &MODE_TERMS[1..])]
+    Mode {
+        /// The mode to change to. Items are put in its order, except for stack, which leaves them as they are
+        mode: Option<StackMode>,
+
         #[command(flatten)]
         fc: FormatConfig,
     },
@@ -228,6 +544,13 @@ enum Command {
         fc: FormatConfig,
     },
 
+    /// Print items that are past due, across all stacks
+    #[command(visible_aliases = &OVERDUE_TERMS[1..])]
+    Overdue {
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
     /// Print the first item. This is the default CLI behavior when no command is given
     #[command(visible_aliases = &PEEK_TERMS[1..])]
     Peek {
@@ -238,18 +561,118 @@ enum Command {
     /// Move items to the top of stack by their number
     #[command(visible_aliases = &PICK_TERMS[1..])]
     Pick {
-        ns: Vec<usize>,
+        /// The numbers or @IDs of the items to pick
+        ns: Vec<ItemRef>,
 
         #[command(flatten)]
         fc: FormatConfig,
     },
 
-    /// Create a new item
+    /// Set the priority of an item, for stacks in priority mode. Lower numbers come first
+    #[command(visible_aliases = &PRIORITY_TERMS[1..])]
+    Priority {
+        /// The number or @ID of the item. Default is the most recent item (0 index)
+        n: Option<ItemRef>,
+
+        /// The item's priority, like 1 for the most urgent
+        #[arg(long, required_unless_present = "none")]
+        to: Option<u32>,
+
+        /// Clear the priority
+        #[arg(long, conflicts_with = "to")]
+        none: bool,
+
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
+    /// Create a new item. Leading words like +work are tags instead of content
     #[command(visible_aliases = &PUSH_TERMS[1..])]
     Push {
         // The content to add as an item. Multiple arguments will be interpreted as a single string
         content: Vec<String>,
 
+        /// When the item is due, e.g. tomorrow, 3d, 2024-06-01 or "2024-06-01 17:00"
+        #[arg(long, value_parser = parse_end_of)]
+        due: Option<Timestamp>,
+
+        /// The item's priority, for stacks in priority mode. Lower numbers come first
+        #[arg(long)]
+        priority: Option<u32>,
+
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
+    /// Redo the most recently undone change
+    #[command(visible_aliases = &REDO_TERMS[1..])]
+    Redo {
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
+    /// Give the stack (and "<STACK>_history") a new name
+    #[command(arg_required_else_help = true, visible_aliases = &RENAME_STACK_TERMS[1..])]
+    RenameStack {
+        #[arg(name = "destination")]
+        /// The stack's new name
+        dest: String,
+
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
+    /// Print what was created, completed and deleted, by day and stack
+    #[command(visible_aliases = &REPORT_TERMS[1..])]
+    Report {
+        /// Where the report starts, e.g. yesterday, "3d ago" or 2024-06-01 (the start of that day)
+        #[arg(long, value_name = "WHEN", value_parser = parse_start_of, default_value = DEFAULT_REPORT_SINCE)]
+        since: Timestamp,
+
+        /// Only report on these stacks, and the stacks in their namespaces. The default is every stack that isn't archived
+        #[arg(long, value_name = "STACK", num_args = 1.., value_delimiter = ',')]
+        stacks: Vec<String>,
+
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
+    /// Find items across stacks by their headlines and bodies
+    #[command(arg_required_else_help = true, visible_aliases = &SEARCH_TERMS[1..])]
+    Search {
+        /// The text to look for. Multiple arguments will be interpreted as a single string
+        pattern: Vec<String>,
+
+        /// Read the pattern as a regular expression
+        #[arg(short = 'E', long)]
+        regex: bool,
+
+        /// Match upper and lower case alike
+        #[arg(short, long)]
+        ignore_case: bool,
+
+        /// Search "<STACK>_history" stacks too
+        #[arg(long)]
+        history: bool,
+
+        /// Only search this stack, and the stacks in its namespace. Can be given more than once
+        #[arg(long = "in", value_name = "STACK")]
+        stacks: Vec<String>,
+
+        /// Only search these parts of items: headline, body or tags. Can be given more than once
+        #[arg(long = "field", value_name = "FIELD")]
+        fields: Vec<SearchField>,
+
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
+    /// Move an item from "<STACK>_history" back to the stack and mark as restored
+    #[command(visible_aliases = &RESTORE_TERMS[1..])]
+    Restore {
+        /// The number or @ID of the item in "<STACK>_history" to restore. Default is the most recent item (0 index)
+        n: Option<ItemRef>,
+
         #[command(flatten)]
         fc: FormatConfig,
     },
@@ -261,6 +684,49 @@ enum Command {
         fc: FormatConfig,
     },
 
+    /// Print an item along with its body
+    #[command(visible_aliases = &SHOW_TERMS[1..])]
+    Show {
+        /// The number or @ID of the item to show. Default is the most recent item (0 index)
+        n: Option<ItemRef>,
+
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
+    /// Hide an item from peek and list until a later time
+    #[command(visible_aliases = &SNOOZE_TERMS[1..])]
+    Snooze {
+        /// The number or @ID of the item to snooze. Default is the most recent item (0 index)
+        n: Option<ItemRef>,
+
+        /// When the item comes back, e.g. tomorrow, 3d, 2024-06-01 or "2024-06-01 09:00"
+        #[arg(long, value_parser = parse_start_of, default_value = "tomorrow")]
+        until: Timestamp,
+
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
+    /// Print how many items are open, completed and deleted, how many get done each week, how long they take, and the oldest open items, by stack
+    #[command(visible_aliases = &STATS_TERMS[1..])]
+    Stats {
+        /// Only count these stacks, and the stacks in their namespaces. The default is every stack that isn't archived
+        #[arg(long, value_name = "STACK", num_args = 1.., value_delimiter = ',')]
+        stacks: Vec<String>,
+
+        /// How many weeks back to count completed items, week by week
+        #[arg(long, value_parser = clap::value_parser!(u32).range(1..), default_value = DEFAULT_STATS_WEEKS)]
+        weeks: u32,
+
+        /// How many of the oldest open items to show
+        #[arg(long, value_name = "N", default_value = DEFAULT_STATS_OLDEST)]
+        oldest: usize,
+
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
     /// Swap the two most-current items
     #[command(visible_aliases = &SWAP_TERMS[1..])]
     Swap {
@@ -268,78 +734,365 @@ enum Command {
         fc: FormatConfig,
     },
 
-    /// Print the last N items (default is 10)
+    /// Add tags to an item
+    #[command(arg_required_else_help = true, visible_aliases = &TAG_TERMS[1..])]
+    Tag {
+        /// The number or @ID of the item to tag (optional, default is the most recent item),
+        /// followed by the tags to add. A leading + on a tag is optional
+        #[arg(value_name = "TAGS", required = true)]
+        args: Vec<String>,
+
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
+    /// Print the last N items (default is 10, or the list-limit in the config file)
     #[command(visible_aliases = &TAIL_TERMS[1..])]
     Tail {
         /// The number of items to display
         n: Option<usize>,
 
+        #[command(flatten)]
+        filter: FilterConfig,
+
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
+    /// Undo the most recent change to any stack
+    #[command(visible_aliases = &UNDO_TERMS[1..])]
+    Undo {
+        #[command(flatten)]
+        fc: FormatConfig,
+    },
+
+    /// Remove tags from an item
+    #[command(arg_required_else_help = true, visible_aliases = &UNTAG_TERMS[1..])]
+    Untag {
+        /// The number or @ID of the item to untag (optional, default is the most recent item),
+        /// followed by the tags to remove. A leading + on a tag is optional
+        #[arg(value_name = "TAGS", required = true)]
+        args: Vec<String>,
+
         #[command(flatten)]
         fc: FormatConfig,
     },
 }
 
 impl Command {
-    fn into_effect_and_fc(self, stack: String) -> (StackEffect, FormatConfig) {
+    fn into_effect_and_fc(self, stack: String, config: &Config) -> (StackEffect, FormatConfig) {
         use StackEffect::*;
         match self {
+            Command::ArchiveStack { fc } => (ArchiveStack { stack }, fc),
+            Command::Compact {
+                all,
+                keep,
+                keep_days,
+                archive,
+                drop,
+                fc,
+            } => {
+                let archive = (archive || drop).then_some(archive);
+                let policy = retention_policy(keep, keep_days, archive, config);
+                (Compact { stack, all, policy }, fc)
+            }
             Command::Complete { n, fc } => (
                 Complete {
                     stack,
-                    index: n.unwrap_or(0),
+                    index: n.unwrap_or(ItemRef::Position(0)),
+                    retention: automatic_retention(config),
                 },
                 fc,
             ),
-            Command::Count { fc } => (Count { stack }, fc),
+            Command::CopyStack { dest, fc } => (CopyStack { stack, dest }, fc),
+            Command::Count {
+                recursive,
+                filter,
+                fc,
+            } => {
+                let filter = filter.into_filter();
+                let effect = Count {
+                    stack,
+                    filter,
+                    recursive,
+                };
+                (effect, fc)
+            }
             Command::Delete { n, fc } => (
                 Delete {
                     stack,
-                    index: n.unwrap_or(0),
+                    index: n.unwrap_or(ItemRef::Position(0)),
+                    retention: automatic_retention(config),
                 },
                 fc,
             ),
-            Command::DeleteAll { fc } => (DeleteAll { stack }, fc),
-            Command::Edit { editor, n, fc } => (
+            Command::DeleteAll { fc } => {
+                let retention = automatic_retention(config);
+                (DeleteAll { stack, retention }, fc)
+            }
+            Command::DeleteStack { fc } => (DeleteStack { stack }, fc),
+            Command::Due { n, by, never, fc } => {
+                let index = n.unwrap_or(ItemRef::Position(0));
+                let due = if never { None } else { by };
+                (Due { stack, index, due }, fc)
+            }
+            Command::Edit {
+                editor,
+                n,
+                body,
+                fc,
+            } => (
                 Edit {
                     stack,
-                    editor: resolve_editor(editor),
-                    index: n.unwrap_or(0),
+                    editor: resolve_editor(editor, config),
+                    index: n.unwrap_or(ItemRef::Position(0)),
+                    body,
                 },
                 fc,
             ),
-            Command::Head { n, fc } => {
-                let n = n.unwrap_or(DEFAULT_SHORT_LIST_LIMIT);
-                (Head { n, stack }, fc)
+            Command::Head { n, filter, fc } => {
+                let n = n.unwrap_or_else(|| list_limit(config));
+                let filter = filter.into_filter();
+                (Head { n, stack, filter }, fc)
             }
             Command::IsEmpty { fc } => (IsEmpty { stack }, fc),
-            Command::List { fc } => (ListAll { stack }, fc),
-            Command::ListStacks { fc } => (ListStacks, fc),
-            Command::Move { dest, fc } => (Move { stack, dest }, fc),
+            Command::List {
+                recursive,
+                as_of,
+                filter,
+                fc,
+            } => {
+                let filter = filter.into_filter();
+                let effect = ListAll {
+                    stack,
+                    filter,
+                    recursive,
+                    as_of,
+                };
+                (effect, fc)
+            }
+            Command::ListStacks {
+                archived,
+                history,
+                summary,
+                fc,
+            } => {
+                let effect = ListStacks {
+                    archived,
+                    history,
+                    summary,
+                };
+                (effect, fc)
+            }
+            Command::Mode { mode, fc } => (Mode { stack, mode }, fc),
+            Command::Move { dest, n, fc } => {
+                let index = n.unwrap_or(ItemRef::Position(0));
+                (Move { stack, dest, index }, fc)
+            }
             Command::MoveAll { dest, fc } => (MoveAll { stack, dest }, fc),
             Command::Next { fc } => (Next { stack }, fc),
+            Command::Overdue { fc } => (Overdue, fc),
+            Command::Search {
+                pattern,
+                regex,
+                ignore_case,
+                history,
+                stacks,
+                fields,
+                fc,
+            } => {
+                let query = SearchQuery {
+                    pattern: pattern.join(" "),
+                    regex,
+                    ignore_case,
+                    history,
+                    stacks,
+                    fields: fields.into_iter().collect(),
+                };
+                (Search { query }, fc)
+            }
             Command::Peek { fc } => (Peek { stack }, fc),
             Command::Pick { ns, fc } => (Pick { stack, indices: ns }, fc),
-            Command::Push { content, fc } => {
-                let content = content.join(" ");
-                (Push { stack, content }, fc)
+            Command::Priority { n, to, none, fc } => {
+                let index = n.unwrap_or(ItemRef::Position(0));
+                let priority = if none { None } else { to };
+                let effect = Prioritize {
+                    stack,
+                    index,
+                    priority,
+                };
+                (effect, fc)
+            }
+            Command::Push {
+                content,
+                due,
+                priority,
+                fc,
+            } => {
+                let (tags, content) = split_tags(&content);
+                let push = Push {
+                    stack,
+                    content,
+                    tags,
+                    due,
+                    priority,
+                };
+                (push, fc)
             }
+            Command::Redo { fc } => (Redo, fc),
+            Command::RenameStack { dest, fc } => (RenameStack { stack, dest }, fc),
+            Command::Report { since, stacks, fc } => (Report { since, stacks }, fc),
+            Command::Restore { n, fc } => (
+                Restore {
+                    stack,
+                    index: n.unwrap_or(ItemRef::Position(0)),
+                },
+                fc,
+            ),
             Command::Rot { fc } => (Rot { stack }, fc),
+            Command::Show { n, fc } => {
+                let index = n.unwrap_or(ItemRef::Position(0));
+                (Show { stack, index }, fc)
+            }
+            Command::Snooze { n, until, fc } => {
+                let index = n.unwrap_or(ItemRef::Position(0));
+                (
+                    Snooze {
+                        stack,
+                        index,
+                        until,
+                    },
+                    fc,
+                )
+            }
+            Command::Stats {
+                stacks,
+                weeks,
+                oldest,
+                fc,
+            } => (
+                Stats {
+                    stacks,
+                    weeks,
+                    oldest,
+                },
+                fc,
+            ),
             Command::Swap { fc } => (Swap { stack }, fc),
-            Command::Tail { n, fc } => {
-                let n = n.unwrap_or(DEFAULT_SHORT_LIST_LIMIT);
-                (Tail { n, stack }, fc)
+            Command::Tag { args, fc } => {
+                let (index, tags) = split_item_ref(&args);
+                (Tag { stack, index, tags }, fc)
+            }
+            Command::Tail { n, filter, fc } => {
+                let n = n.unwrap_or_else(|| list_limit(config));
+                let filter = filter.into_filter();
+                (Tail { n, stack, filter }, fc)
+            }
+            Command::Undo { fc } => (Undo, fc),
+            Command::Untag { args, fc } => {
+                let (index, tags) = split_item_ref(&args);
+                (Untag { stack, index, tags }, fc)
             }
         }
     }
 }
 
-pub fn resolve_editor(editor: Option<String>) -> String {
+fn resolve_editor(editor: Option<String>, config: &Config) -> String {
     editor
         .or_else(|| std::env::var("VISUAL").ok())
         .or_else(|| std::env::var("EDITOR").ok())
+        .or_else(|| config.editor.clone())
         .unwrap_or("nano".into())
 }
 
+/// How many items head and tail print when not told.
+fn list_limit(config: &Config) -> usize {
+    config.list_limit.unwrap_or(DEFAULT_SHORT_LIST_LIMIT)
+}
+
+/// The retention policy for compact. Limits given here replace the config
+/// file's, and older items are archived unless told otherwise.
+fn retention_policy(
+    keep: Option<usize>,
+    keep_days: Option<u32>,
+    archive: Option<bool>,
+    config: &Config,
+) -> RetentionPolicy {
+    let retention = &config.retention;
+    let (keep, keep_days) = match keep.is_some() || keep_days.is_some() {
+        true => (keep, keep_days),
+        false => (retention.keep, retention.keep_days),
+    };
+    RetentionPolicy {
+        keep,
+        keep_days,
+        archive: archive.or(retention.archive).unwrap_or(true),
+    }
+}
+
+/// The retention policy history stacks are kept to as items go to them. It's
+/// the config file's, if that says what to keep.
+fn automatic_retention(config: &Config) -> Option<RetentionPolicy> {
+    let retention = &config.retention;
+    let configured = retention.keep.is_some() || retention.keep_days.is_some();
+    configured.then(|| retention_policy(None, None, None, config))
+}
+
+/// Split leading "+tag" words off of the words of an item's content.
+fn split_tags<S: AsRef<str>>(words: &[S]) -> (BTreeSet<String>, String) {
+    let is_tag = |word: &str| word.len() > 1 && word.starts_with('+');
+    let n_tags = words.iter().take_while(|w| is_tag(w.as_ref())).count();
+    let tags = words[..n_tags]
+        .iter()
+        .map(|w| parse_tag(w.as_ref()))
+        .collect();
+    let content = words[n_tags..]
+        .iter()
+        .map(|w| w.as_ref())
+        .collect::<Vec<_>>()
+        .join(" ");
+    (tags, content)
+}
+
+/// Split an optional leading item number or @ID off of a list of tags.
+fn split_item_ref<S: AsRef<str>>(args: &[S]) -> (ItemRef, BTreeSet<String>) {
+    let first = args.first().map(|arg| arg.as_ref()).unwrap_or_default();
+    let (index, tags) = match ItemRef::from_str(first) {
+        Ok(index) if !first.starts_with('+') => (index, &args[1..]),
+        _ => (ItemRef::Position(0), args),
+    };
+    let tags = tags
+        .iter()
+        .map(|tag| parse_tag(tag.as_ref()))
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    (index, tags)
+}
+
+fn parse_tag(tag: &str) -> String {
+    tag.strip_prefix('+').unwrap_or(tag).to_string()
+}
+
+#[derive(Args)]
+struct FilterConfig {
+    /// Only include items with this tag. Can be given more than once, to only include items with all the tags
+    #[arg(long = "tag", value_name = "TAG")]
+    tags: Vec<String>,
+
+    /// Only include deferred (snoozed) items, instead of leaving them out
+    #[arg(long)]
+    deferred: bool,
+}
+
+impl FilterConfig {
+    fn into_filter(self) -> ItemFilter {
+        ItemFilter {
+            tags: self.tags.iter().map(|tag| parse_tag(tag)).collect(),
+            deferred: self.deferred,
+        }
+    }
+}
+
 #[derive(Args)]
 struct FormatConfig {
     #[arg(short, long)]
@@ -355,7 +1108,7 @@ struct FormatConfig {
     verbose: bool,
 
     #[arg(short, long)]
-    /// Use a programmatic format. Options include [csv, json, json-compact, tsv]. Not compatible with quiet/silent/verbose.
+    /// Use a programmatic format. Options include [csv, json, json-compact, markdown, tsv]. Not compatible with quiet/silent/verbose.
     format: Option<ProgrammaticFormat>,
 }
 
@@ -376,6 +1129,7 @@ impl FormatConfig {
                 ProgrammaticFormat::Csv => Csv,
                 ProgrammaticFormat::Json => Json,
                 ProgrammaticFormat::JsonCompact => JsonCompact,
+                ProgrammaticFormat::Markdown => Markdown,
                 ProgrammaticFormat::Tsv => Tsv,
             })
             .or(if verbose {
@@ -389,10 +1143,41 @@ impl FormatConfig {
             })
     }
 
-    fn into_fallback_for(self, fc: FormatConfig) -> OutputFormat {
+    fn into_fallback_for(self, fc: FormatConfig, default: OutputFormat) -> OutputFormat {
         fc.into_output_format()
             .or_else(|| self.into_output_format())
-            .unwrap_or(DEFAULT_FORMAT)
+            .unwrap_or(default)
+    }
+}
+
+#[derive(Args)]
+struct TimeConfig {
+    /// Show times in this time zone: local, original (as each time was recorded), utc, an offset like +05:30, or a name like America/Chicago. The default is either the value of a SIGI_TZ environment variable or local
+    #[arg(long)]
+    tz: Option<DisplayZone>,
+
+    /// How to show times to people: rfc2822 (the default), iso, relative (like "3 days ago"), locale, or a strftime-style format like "%Y-%m-%d %H:%M". Programmatic formats other than markdown always use ISO 8601
+    #[arg(long)]
+    time_format: Option<TimeStyle>,
+
+    /// The locale for names of days and months, like fr_FR. The default is from the LC_ALL, LC_TIME or LANG environment variables
+    #[arg(long, value_parser = parse_locale)]
+    locale: Option<chrono::Locale>,
+}
+
+#[derive(ValueEnum, Clone, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum StoreFormat {
+    Json,
+    Sqlite,
+}
+
+impl StoreFormat {
+    fn into_data_format(self) -> DataFormat {
+        match self {
+            StoreFormat::Json => DataFormat::SigiJson,
+            StoreFormat::Sqlite => DataFormat::Sqlite,
+        }
     }
 }
 
@@ -401,6 +1186,8 @@ enum ProgrammaticFormat {
     Csv,
     Json,
     JsonCompact,
+    #[value(alias = "md")]
+    Markdown,
     Tsv,
 }
 
@@ -416,6 +1203,7 @@ impl FromStr for ProgrammaticFormat {
             "csv" => Ok(Csv),
             "json" => Ok(Json),
             "json-compact" => Ok(JsonCompact),
+            "markdown" | "md" => Ok(Markdown),
             "tsv" => Ok(Tsv),
             _ => Err(UnknownFormat { format }),
         }
@@ -0,0 +1,146 @@
+//! Defaults from a config file, so they don't have to be given every time.
+//!
+//! ```toml
+//! stack = "work"
+//! format = "verbose"
+//! list-limit = 5
+//!
+//! [retention]
+//! keep-days = 365
+//!
+//! [aliases]
+//! tomorrow = "snooze --until tomorrow"
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::{env, fs};
+
+use chrono::Locale;
+use directories::{BaseDirs, ProjectDirs};
+use serde::{Deserialize, Deserializer};
+
+use super::StoreFormat;
+use crate::data::days_ago;
+use crate::error::SigiError;
+use crate::output::{parse_locale, DisplayZone, OutputFormat, TimeStyle};
+
+const CONFIG_FILE: &str = "config.toml";
+
+/// Settings from a config file. Flags and environment variables win over
+/// these, and these win over sigi's built-in defaults.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub(super) struct Config {
+    /// The stack to use when none is given.
+    pub(super) stack: Option<String>,
+    /// The output format to use when none is given, like "verbose" or "json".
+    #[serde(deserialize_with = "parsed")]
+    pub(super) format: Option<OutputFormat>,
+    pub(super) editor: Option<String>,
+    pub(super) data_store: Option<String>,
+    pub(super) data_format: Option<StoreFormat>,
+    /// How many items head and tail print when not told.
+    pub(super) list_limit: Option<usize>,
+    #[serde(deserialize_with = "parsed")]
+    pub(super) time_format: Option<TimeStyle>,
+    #[serde(deserialize_with = "parsed")]
+    pub(super) tz: Option<DisplayZone>,
+    #[serde(deserialize_with = "locale")]
+    pub(super) locale: Option<Locale>,
+    /// Other names for commands, like `tomorrow = "snooze --until tomorrow"`.
+    pub(super) aliases: BTreeMap<String, String>,
+    pub(super) retention: RetentionConfig,
+}
+
+/// What compact keeps of history stacks when not told. When this says what
+/// to keep, history stacks are also kept to it as items are completed or
+/// deleted.
+#[derive(Deserialize, Default)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub(super) struct RetentionConfig {
+    pub(super) keep: Option<usize>,
+    #[serde(deserialize_with = "days_back")]
+    pub(super) keep_days: Option<u32>,
+    /// Whether older items are archived (the default) or dropped.
+    pub(super) archive: Option<bool>,
+}
+
+impl Config {
+    /// Read the config file named by a SIGI_CONFIG environment variable, or
+    /// else the one in the OS-specific config directory, if there is one.
+    pub(super) fn load() -> Result<Config, SigiError> {
+        let (path, required) = match env::var("SIGI_CONFIG") {
+            Ok(path) if !path.is_empty() => (PathBuf::from(path), true),
+            _ => match ProjectDirs::from("org", "sigi-cli", "sigi") {
+                Some(dirs) => (dirs.config_dir().join(CONFIG_FILE), false),
+                None => return Ok(Config::default()),
+            },
+        };
+
+        let toml = match fs::read_to_string(&path) {
+            Ok(toml) => toml,
+            Err(err) if err.kind() == ErrorKind::NotFound && !required => {
+                return Ok(Config::default())
+            }
+            Err(err) => return Err(SigiError::io(format!("read config file {:?}", path))(err)),
+        };
+
+        toml::from_str(&toml).map_err(|err| SigiError::InvalidSetting {
+            setting: format!("config file {:?}", path),
+            details: err.to_string().trim_end().to_string(),
+        })
+    }
+
+    /// The data store directory, with a leading ~ meaning the home directory.
+    pub(super) fn data_store(&self) -> Option<String> {
+        let dir = self.data_store.as_ref()?;
+        let home = BaseDirs::new().map(|dirs| dirs.home_dir().to_string_lossy().to_string());
+        match (dir.strip_prefix('~'), home) {
+            (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+                Some(format!("{}{}", home, rest))
+            }
+            _ => Some(dir.clone()),
+        }
+    }
+
+    /// The words an alias stands for, if it is one.
+    pub(super) fn alias(&self, name: &str) -> Option<Vec<String>> {
+        let expansion = self.aliases.get(name)?;
+        Some(expansion.split_whitespace().map(String::from).collect())
+    }
+}
+
+/// Read a value of a type that can be parsed from a string.
+fn parsed<'de, D, T>(deserializer: D) -> Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = String::deserialize(deserializer)?;
+    value.parse().map(Some).map_err(serde::de::Error::custom)
+}
+
+fn locale<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Locale>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_locale(&value)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+/// Read a number of days to look back, which mustn't go back further than
+/// times go.
+fn days_back<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    let days = u32::deserialize(deserializer)?;
+    match days_ago(days.into()) {
+        Some(_) => Ok(Some(days)),
+        None => Err(serde::de::Error::custom(format!(
+            "{} days is further back than sigi can go",
+            days
+        ))),
+    }
+}
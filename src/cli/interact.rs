@@ -1,5 +1,5 @@
 use super::*;
-use crate::effects::StackEffect;
+use crate::effects::{ItemFilter, ItemRef, RetentionPolicy, SearchQuery, StackEffect};
 use crate::output::OutputFormat;
 use clap::CommandFactory;
 use rustyline::error::ReadlineError;
@@ -11,7 +11,8 @@ const HUMAN_PROMPT: &str = "🌴 ▶ ";
 pub const INTERACT_INSTRUCTIONS: &str = "INTERACTIVE MODE:
 
 Use subcommands in interactive mode directly. \
-No OPTIONS (flags) of subcommands are understood in interactive mode. \
+Only some OPTIONS (flags) of subcommands are understood in interactive mode; \
+see the long help (--help) for which. \
 The ; character can be used to separate commands.
 
 The following additional commands are available:
@@ -34,7 +35,19 @@ Use subcommands in interactive mode directly. For example:
     🌴 ▶ exit
     exit: Buen biåhe!
 
-No OPTIONS (flags) of subcommands are understood in interactive mode.
+Only these OPTIONS (flags) of subcommands are understood in interactive mode:
+    list            -r/--recursive, --as-of WHEN, +TAG filters
+    count           -r/--recursive, +TAG filters
+    head, tail      +TAG filters
+    list-stacks     --archived, --history, --summary[=DAYS]
+    search          -E/--regex, -i/--ignore-case, --history, --in STACK, --field FIELD
+    report          --since WHEN, --stacks STACK...
+    stats           --stacks STACK..., --weeks N, --oldest N
+    compact         --all, --keep N, --keep-days N, --archive, --drop
+    priority        --to N, --none
+    snooze          --until WHEN
+    due             --by WHEN, --never
+    edit            -b/--body
 
 The ; character can be used to separate commands.
 
@@ -50,7 +63,12 @@ In interactive mode, the following additional commands are available:
 
 // TODO: pagination/scrollback?
 // TODO: more comprehensive tests
-pub fn interact(original_stack: String, data_store: DataStore, output: OutputFormat) {
+pub fn interact(
+    original_stack: String,
+    data_store: DataStore,
+    output: OutputFormat,
+    config: &Config,
+) {
     print_welcome_msg(output);
 
     let mut rl = DefaultEditor::new().expect("Unable to create readline.");
@@ -70,7 +88,7 @@ pub fn interact(original_stack: String, data_store: DataStore, output: OutputFor
         }
 
         use InteractAction::*;
-        let line = line.map_err(handle_error).map(handle_line(&stack));
+        let line = line.map_err(handle_error).map(handle_line(&stack, config));
         let actions = match line {
             Ok(actions) => actions,
             Err(err_action) => vec![err_action],
@@ -81,7 +99,11 @@ pub fn interact(original_stack: String, data_store: DataStore, output: OutputFor
                 ShortHelp => Cli::command().print_help().unwrap(),
                 LongHelp => Cli::command().print_long_help().unwrap(),
                 Clear => clearscreen::clear().expect("Failed to clear screen"),
-                DoEffect(effect) => effect.run(&data_store, &output),
+                DoEffect(effect) => {
+                    if let Err(err) = effect.run(&data_store, &output) {
+                        report_error(&err);
+                    }
+                }
                 UseStack(new_stack) => {
                     stack = new_stack;
                     output.log(vec!["update", "stack"], vec![vec!["Active stack", &stack]]);
@@ -97,6 +119,9 @@ pub fn interact(original_stack: String, data_store: DataStore, output: OutputFor
                         vec![vec![&msg, "missing argument"]],
                     );
                 }
+                InvalidArgument(msg) => {
+                    output.log(vec!["error"], vec![vec![&msg]]);
+                }
                 Error(msg) => {
                     output.log(
                         vec!["exit-message", "exit-reason"],
@@ -143,6 +168,7 @@ enum InteractAction {
     NoContent,
     Exit(String),
     MissingArgument(String),
+    InvalidArgument(String),
     Error(String),
     Unknown(String),
 }
@@ -155,16 +181,19 @@ fn handle_error(err: ReadlineError) -> InteractAction {
     }
 }
 
-fn handle_line(stack: &str) -> impl Fn(String) -> Vec<InteractAction> + '_ {
+fn handle_line<'a>(
+    stack: &'a str,
+    config: &'a Config,
+) -> impl Fn(String) -> Vec<InteractAction> + 'a {
     |line| {
         line.split(';')
             .map(|s| s.to_string())
-            .map(|line| parse_line(line, stack.to_string()))
+            .map(|line| parse_line(line, stack.to_string(), config))
             .collect()
     }
 }
 
-fn parse_line(line: String, stack: String) -> InteractAction {
+fn parse_line(line: String, stack: String, config: &Config) -> InteractAction {
     let tokens = line.split_ascii_whitespace().collect::<Vec<_>>();
 
     if tokens.is_empty() {
@@ -182,10 +211,22 @@ fn parse_line(line: String, stack: String) -> InteractAction {
             Some(stack) => InteractAction::UseStack(stack.to_string()),
             None => InteractAction::MissingArgument("stack name".to_string()),
         },
-        _ => match parse_effect(tokens, stack) {
+        _ => match parse_effect(&tokens, stack.clone(), config) {
             ParseEffectResult::Effect(effect) => InteractAction::DoEffect(effect),
             ParseEffectResult::NotEffect(parse_res) => parse_res,
-            ParseEffectResult::Unknown => InteractAction::Unknown(term),
+            ParseEffectResult::Unknown => match config.alias(&term) {
+                // Aliases are expanded once; an alias of an alias is unknown.
+                Some(mut expansion) => {
+                    expansion.extend(tokens[1..].iter().map(|s| s.to_string()));
+                    let tokens = expansion.iter().map(String::as_str).collect::<Vec<_>>();
+                    match parse_effect(&tokens, stack, config) {
+                        ParseEffectResult::Effect(effect) => InteractAction::DoEffect(effect),
+                        ParseEffectResult::NotEffect(parse_res) => parse_res,
+                        ParseEffectResult::Unknown => InteractAction::Unknown(term),
+                    }
+                }
+                None => InteractAction::Unknown(term),
+            },
         },
     }
 }
@@ -196,54 +237,160 @@ enum ParseEffectResult {
     Unknown,
 }
 
-fn parse_effect(tokens: Vec<&str>, stack: String) -> ParseEffectResult {
+fn parse_effect(tokens: &[&str], stack: String, config: &Config) -> ParseEffectResult {
     let term = tokens.first().unwrap_or(&"");
 
     let parse_n = || tokens.get(1).and_then(|&s| usize::from_str(s).ok());
+    let parse_filter = || ItemFilter {
+        tags: tokens
+            .iter()
+            .skip(1)
+            .filter(|s| s.len() > 1 && s.starts_with('+'))
+            .map(|s| parse_tag(s))
+            .collect(),
+        deferred: false,
+    };
+    let recursive = tokens[1..]
+        .iter()
+        .any(|s| *s == "-r" || *s == "--recursive");
+    let parse_item = || {
+        tokens
+            .get(1)
+            .and_then(|&s| ItemRef::from_str(s).ok())
+            .unwrap_or(ItemRef::Position(0))
+    };
 
     use ParseEffectResult::*;
     use StackEffect::*;
 
+    if ARCHIVE_STACK_TERMS.contains(term) {
+        return Effect(ArchiveStack { stack });
+    }
+    if COMPACT_TERMS.contains(term) {
+        return match parse_compact(&tokens[1..], config) {
+            Ok((all, policy)) => Effect(Compact { stack, all, policy }),
+            Err(msg) => NotEffect(InteractAction::InvalidArgument(msg)),
+        };
+    }
     if COMPLETE_TERMS.contains(term) {
-        let index = parse_n().unwrap_or(0);
-        return Effect(Complete { stack, index });
+        let index = parse_item();
+        let retention = automatic_retention(config);
+        return Effect(Complete {
+            stack,
+            index,
+            retention,
+        });
+    }
+    if COPY_STACK_TERMS.contains(term) {
+        return match tokens.get(1) {
+            Some(dest) => {
+                let dest = dest.to_string();
+                Effect(CopyStack { stack, dest })
+            }
+            None => NotEffect(InteractAction::MissingArgument("new stack".to_string())),
+        };
     }
     if COUNT_TERMS.contains(term) {
-        return Effect(Count { stack });
+        let filter = parse_filter();
+        return Effect(Count {
+            stack,
+            filter,
+            recursive,
+        });
     }
     if DELETE_TERMS.contains(term) {
-        let index = parse_n().unwrap_or(0);
-        return Effect(Delete { stack, index });
+        let index = parse_item();
+        let retention = automatic_retention(config);
+        return Effect(Delete {
+            stack,
+            index,
+            retention,
+        });
     }
     if DELETE_ALL_TERMS.contains(term) {
-        return Effect(DeleteAll { stack });
+        let retention = automatic_retention(config);
+        return Effect(DeleteAll { stack, retention });
+    }
+    if DELETE_STACK_TERMS.contains(term) {
+        return Effect(DeleteStack { stack });
+    }
+    if DUE_TERMS.contains(term) {
+        let (index, when) = split_item_ref_and_time(&tokens[1..], "--by");
+        let due = match when.as_str() {
+            "" => return NotEffect(InteractAction::MissingArgument("due time".to_string())),
+            "never" | "none" | "--never" => None,
+            when => match parse_end_of(when) {
+                Ok(due) => Some(due),
+                Err(msg) => return NotEffect(InteractAction::InvalidArgument(msg)),
+            },
+        };
+        return Effect(Due { stack, index, due });
     }
     if EDIT_TERMS.contains(term) {
-        let index = parse_n().unwrap_or(0);
+        let args = &tokens[1..];
+        let body = args.iter().any(|s| *s == "-b" || *s == "--body");
+        let index = args
+            .iter()
+            .find(|s| !s.starts_with('-'))
+            .and_then(|s| ItemRef::from_str(s).ok())
+            .unwrap_or(ItemRef::Position(0));
         return Effect(Edit {
             stack,
-            editor: resolve_editor(None),
+            editor: resolve_editor(None, config),
             index,
+            body,
         });
     }
     if HEAD_TERMS.contains(term) {
-        let n = parse_n().unwrap_or(DEFAULT_SHORT_LIST_LIMIT);
-        return Effect(Head { stack, n });
+        let n = parse_n().unwrap_or_else(|| list_limit(config));
+        let filter = parse_filter();
+        return Effect(Head { stack, n, filter });
     }
     if IS_EMPTY_TERMS.contains(term) {
         return Effect(IsEmpty { stack });
     }
     if LIST_TERMS.contains(term) {
-        return Effect(ListAll { stack });
+        let filter = parse_filter();
+        let as_of = match parse_as_of(&tokens[1..]) {
+            Ok(as_of) => as_of,
+            Err(msg) => return NotEffect(InteractAction::InvalidArgument(msg)),
+        };
+        return Effect(ListAll {
+            stack,
+            filter,
+            recursive,
+            as_of,
+        });
     }
     if LIST_STACKS_TERMS.contains(term) {
-        return Effect(ListStacks);
+        let flags = &tokens[1..];
+        let summary = flags.iter().find_map(|flag| match *flag {
+            "--summary" => DEFAULT_SUMMARY_DAYS.parse().ok(),
+            flag => flag.strip_prefix("--summary=")?.parse().ok(),
+        });
+        return Effect(ListStacks {
+            archived: flags.contains(&"--archived"),
+            history: flags.contains(&"--history"),
+            summary,
+        });
+    }
+    if MODE_TERMS.contains(term) {
+        let mode = match tokens.get(1).map(|mode| StackMode::from_str(mode)) {
+            Some(Ok(mode)) => Some(mode),
+            Some(Err(msg)) => return NotEffect(InteractAction::InvalidArgument(msg)),
+            None => None,
+        };
+        return Effect(Mode { stack, mode });
     }
     if MOVE_TERMS.contains(term) {
         match tokens.get(1) {
             Some(dest) => {
                 let dest = dest.to_string();
-                return Effect(Move { stack, dest });
+                let index = tokens
+                    .get(2)
+                    .and_then(|&s| ItemRef::from_str(s).ok())
+                    .unwrap_or(ItemRef::Position(0));
+                return Effect(Move { stack, dest, index });
             }
             None => {
                 return NotEffect(InteractAction::MissingArgument(
@@ -268,31 +415,293 @@ fn parse_effect(tokens: Vec<&str>, stack: String) -> ParseEffectResult {
     if NEXT_TERMS.contains(term) {
         return Effect(Next { stack });
     }
+    if OVERDUE_TERMS.contains(term) {
+        return Effect(Overdue);
+    }
     if PEEK_TERMS.contains(term) {
         return Effect(Peek { stack });
     }
     if PICK_TERMS.contains(term) {
         let indices = tokens
             .iter()
-            .filter_map(|s| usize::from_str(s).ok())
+            .skip(1)
+            .filter_map(|s| ItemRef::from_str(s).ok())
             .collect();
         return Effect(Pick { stack, indices });
     }
+    if PRIORITY_TERMS.contains(term) {
+        return match parse_priority(&tokens[1..]) {
+            Ok((index, priority)) => Effect(Prioritize {
+                stack,
+                index,
+                priority,
+            }),
+            Err(msg) => NotEffect(InteractAction::InvalidArgument(msg)),
+        };
+    }
     if PUSH_TERMS.contains(term) {
         // FIXME: This is convenient, but normalizes whitespace. (E.g. multiple spaces always collapsed, tabs to spaces, etc)
-        let content = tokens[1..].join(" ");
-        return Effect(Push { stack, content });
+        let (tags, content) = split_tags(&tokens[1..]);
+        return Effect(Push {
+            stack,
+            content,
+            tags,
+            due: None,
+            priority: None,
+        });
+    }
+    if REDO_TERMS.contains(term) {
+        return Effect(Redo);
+    }
+    if RENAME_STACK_TERMS.contains(term) {
+        return match tokens.get(1) {
+            Some(dest) => {
+                let dest = dest.to_string();
+                Effect(RenameStack { stack, dest })
+            }
+            None => NotEffect(InteractAction::MissingArgument(
+                "new stack name".to_string(),
+            )),
+        };
+    }
+    if REPORT_TERMS.contains(term) {
+        return match parse_report(&tokens[1..]) {
+            Ok((since, stacks)) => Effect(Report { since, stacks }),
+            Err(msg) => NotEffect(InteractAction::InvalidArgument(msg)),
+        };
+    }
+    if RESTORE_TERMS.contains(term) {
+        let index = parse_item();
+        return Effect(Restore { stack, index });
     }
     if ROT_TERMS.contains(term) {
         return Effect(Rot { stack });
     }
+    if SEARCH_TERMS.contains(term) {
+        return match parse_search(&tokens[1..]) {
+            Ok(query) if query.pattern.is_empty() => NotEffect(InteractAction::MissingArgument(
+                "search pattern".to_string(),
+            )),
+            Ok(query) => Effect(Search { query }),
+            Err(msg) => NotEffect(InteractAction::InvalidArgument(msg)),
+        };
+    }
+    if SHOW_TERMS.contains(term) {
+        let index = parse_item();
+        return Effect(Show { stack, index });
+    }
+    if SNOOZE_TERMS.contains(term) {
+        let (index, when) = split_item_ref_and_time(&tokens[1..], "--until");
+        let when = if when.is_empty() { "tomorrow" } else { &when };
+        let until = match parse_start_of(when) {
+            Ok(until) => until,
+            Err(msg) => return NotEffect(InteractAction::InvalidArgument(msg)),
+        };
+        return Effect(Snooze {
+            stack,
+            index,
+            until,
+        });
+    }
+    if STATS_TERMS.contains(term) {
+        return match parse_stats(&tokens[1..]) {
+            Ok((stacks, weeks, oldest)) => Effect(Stats {
+                stacks,
+                weeks,
+                oldest,
+            }),
+            Err(msg) => NotEffect(InteractAction::InvalidArgument(msg)),
+        };
+    }
     if SWAP_TERMS.contains(term) {
         return Effect(Swap { stack });
     }
+    if TAG_TERMS.contains(term) {
+        let (index, tags) = split_item_ref(&tokens[1..]);
+        if tags.is_empty() {
+            return NotEffect(InteractAction::MissingArgument("tags".to_string()));
+        }
+        return Effect(Tag { stack, index, tags });
+    }
     if TAIL_TERMS.contains(term) {
-        let n = parse_n().unwrap_or(DEFAULT_SHORT_LIST_LIMIT);
-        return Effect(Tail { stack, n });
+        let n = parse_n().unwrap_or_else(|| list_limit(config));
+        let filter = parse_filter();
+        return Effect(Tail { stack, n, filter });
+    }
+    if UNDO_TERMS.contains(term) {
+        return Effect(Undo);
+    }
+    if UNTAG_TERMS.contains(term) {
+        let (index, tags) = split_item_ref(&tokens[1..]);
+        if tags.is_empty() {
+            return NotEffect(InteractAction::MissingArgument("tags".to_string()));
+        }
+        return Effect(Untag { stack, index, tags });
     }
 
     Unknown
 }
+
+/// Read the flags of a search, like the CLI's, and the words to search for.
+fn parse_search(args: &[&str]) -> Result<SearchQuery, String> {
+    let mut query = SearchQuery::default();
+    let mut words = vec![];
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match *arg {
+            "-E" | "--regex" => query.regex = true,
+            "-i" | "--ignore-case" => query.ignore_case = true,
+            "--history" => query.history = true,
+            "--in" => match args.next() {
+                Some(stack) => query.stacks.push(stack.to_string()),
+                None => return Err("--in needs a stack".to_string()),
+            },
+            "--field" => match args.next() {
+                Some(field) => {
+                    query.fields.insert(field.parse()?);
+                }
+                None => return Err("--field needs a field".to_string()),
+            },
+            word => words.push(word),
+        };
+    }
+    query.pattern = words.join(" ");
+    Ok(query)
+}
+
+/// Read the options of a report: "--since WHEN", where WHEN may take a few
+/// words, and "--stacks" followed by stacks.
+fn parse_report(args: &[&str]) -> Result<(Timestamp, Vec<String>), String> {
+    let mut since = vec![];
+    let mut stacks = vec![];
+    let mut option = None;
+    for arg in args {
+        match *arg {
+            "--since" | "--stacks" => option = Some(*arg),
+            _ if option == Some("--since") => since.push(*arg),
+            _ if option == Some("--stacks") => {
+                stacks.extend(arg.split(',').filter(|s| !s.is_empty()).map(String::from))
+            }
+            _ => return Err(format!("{:?} isn't an option of report", arg)),
+        }
+    }
+    let since = match since.is_empty() {
+        true => DEFAULT_REPORT_SINCE.to_string(),
+        false => since.join(" "),
+    };
+    Ok((parse_start_of(&since)?, stacks))
+}
+
+/// Read the options of stats: "--stacks" followed by stacks, "--weeks N" and
+/// "--oldest N".
+fn parse_stats(args: &[&str]) -> Result<(Vec<String>, u32, usize), String> {
+    let mut stacks = vec![];
+    let mut weeks = DEFAULT_STATS_WEEKS.to_string();
+    let mut oldest = DEFAULT_STATS_OLDEST.to_string();
+    let mut args = args.iter();
+    let mut in_stacks = false;
+    while let Some(arg) = args.next() {
+        match *arg {
+            "--stacks" => in_stacks = true,
+            "--weeks" => {
+                in_stacks = false;
+                weeks = args.next().ok_or("--weeks needs a number")?.to_string();
+            }
+            "--oldest" => {
+                in_stacks = false;
+                oldest = args.next().ok_or("--oldest needs a number")?.to_string();
+            }
+            _ if in_stacks => {
+                stacks.extend(arg.split(',').filter(|s| !s.is_empty()).map(String::from))
+            }
+            _ => return Err(format!("{:?} isn't an option of stats", arg)),
+        }
+    }
+    let oldest = oldest
+        .parse()
+        .map_err(|_| format!("{:?} isn't a number of items", oldest))?;
+    match weeks.parse() {
+        Ok(weeks) if weeks > 0 => Ok((stacks, weeks, oldest)),
+        _ => Err(format!("{:?} isn't a number of weeks", weeks)),
+    }
+}
+
+/// Read an optional item number or @ID, then a priority or "none".
+fn parse_priority(args: &[&str]) -> Result<(ItemRef, Option<u32>), String> {
+    let args = args
+        .iter()
+        .filter(|arg| **arg != "--to")
+        .copied()
+        .collect::<Vec<_>>();
+    let (index, priority) = match args.as_slice() {
+        [priority] => (ItemRef::Position(0), *priority),
+        [index, priority] => (
+            ItemRef::from_str(index).map_err(|e| e.to_string())?,
+            *priority,
+        ),
+        _ => return Err("priority needs a number, or none to clear it".to_string()),
+    };
+    match priority {
+        "none" | "--none" => Ok((index, None)),
+        _ => match priority.parse() {
+            Ok(priority) => Ok((index, Some(priority))),
+            Err(_) => Err(format!("{:?} isn't a priority", priority)),
+        },
+    }
+}
+
+fn parse_compact(args: &[&str], config: &Config) -> Result<(bool, RetentionPolicy), String> {
+    let (mut all, mut keep, mut keep_days, mut archive) = (false, None, None, None);
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match *arg {
+            "--all" => all = true,
+            "--archive" => archive = Some(true),
+            "--drop" => archive = Some(false),
+            "--keep" => keep = Some(number_after(arg, args.next())?),
+            "--keep-days" => keep_days = Some(number_after(arg, args.next())?),
+            _ => return Err(format!("{:?} isn't an option of compact", arg)),
+        }
+    }
+    Ok((all, retention_policy(keep, keep_days, archive, config)))
+}
+
+/// Read the number given after a flag.
+fn number_after<T: FromStr>(flag: &str, value: Option<&&str>) -> Result<T, String> {
+    value
+        .and_then(|value| value.parse().ok())
+        .ok_or(format!("{} needs a number", flag))
+}
+
+/// Read the time after "--as-of", which may take a few words like "3d ago".
+/// Tags and flags after it aren't part of it.
+fn parse_as_of(args: &[&str]) -> Result<Option<Timestamp>, String> {
+    let when = match args.iter().position(|arg| *arg == "--as-of") {
+        Some(i) => args[i + 1..]
+            .iter()
+            .take_while(|arg| !arg.starts_with('+') && !arg.starts_with("--") && **arg != "-r")
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" "),
+        None => return Ok(None),
+    };
+    if when.is_empty() {
+        return Err("--as-of needs a time, like yesterday or 2024-06-01".to_string());
+    }
+    parse_end_of(&when).map(Some)
+}
+
+/// Split an optional leading item number or @ID off of the words of a time.
+/// The time may follow a flag (like "--until"), as it does on the command line.
+fn split_item_ref_and_time(args: &[&str], flag: &str) -> (ItemRef, String) {
+    let prefix = format!("{}=", flag);
+    let args = args
+        .iter()
+        .filter(|arg| **arg != flag)
+        .map(|arg| arg.strip_prefix(&prefix).unwrap_or(arg))
+        .collect::<Vec<_>>();
+    match args.first().map(|arg| ItemRef::from_str(arg)) {
+        Some(Ok(index)) => (index, args[1..].join(" ")),
+        _ => (ItemRef::Position(0), args.join(" ")),
+    }
+}
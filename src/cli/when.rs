@@ -0,0 +1,108 @@
+//! Reading the times people type, like "tomorrow", "3d" or "2024-06-01".
+
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime};
+
+use crate::data::{now, Timestamp};
+use crate::output::TimeDisplay;
+
+const DATE_TIME_FORMATS: [&str; 4] = [
+    "%Y-%m-%d %H:%M",
+    "%Y-%m-%dT%H:%M",
+    "%Y-%m-%d %H:%M:%S",
+    "%Y-%m-%dT%H:%M:%S",
+];
+
+/// Which moment of the day a plain date (like "tomorrow") means.
+#[derive(Clone, Copy)]
+pub enum DayPart {
+    Start,
+    End,
+}
+
+/// Read a time. Understood are:
+/// - "now", "today", "tomorrow" and "yesterday"
+/// - Durations from now like "30m", "2h", "3d" or "1w". Add "ago" (or a
+///   leading "-") for the past, e.g. "3d ago".
+/// - Dates like "2024-06-01", and times like "2024-06-01 09:30"
+/// - RFC 3339 timestamps like "2024-06-01T09:30:00+02:00"
+///
+/// Days, dates and times are read in the zone times are shown in.
+pub fn parse_when(when: &str, part: DayPart) -> Result<Timestamp, String> {
+    let zone = TimeDisplay::input_zone();
+    let now = now();
+    let normalized = when.trim().to_ascii_lowercase();
+    let today = zone.convert(now).date_naive();
+
+    let date = match normalized.as_str() {
+        "now" => return Ok(now),
+        "today" => Some(today),
+        "tomorrow" => today.succ_opt(),
+        "yesterday" => today.pred_opt(),
+        _ => NaiveDate::parse_from_str(&normalized, "%Y-%m-%d").ok(),
+    };
+    if let Some(date) = date {
+        let naive = match part {
+            DayPart::Start => date.and_hms_opt(0, 0, 0),
+            DayPart::End => date.and_hms_opt(23, 59, 59),
+        };
+        return naive
+            .and_then(|naive| zone.from_wall_clock(naive))
+            .ok_or_else(|| unknown(when));
+    }
+
+    if let Some(duration) = parse_duration(&normalized) {
+        return now
+            .checked_add_signed(duration)
+            .ok_or_else(|| unknown(when));
+    }
+
+    if let Ok(dt) = DateTime::parse_from_rfc3339(when.trim()) {
+        return Ok(dt);
+    }
+
+    DATE_TIME_FORMATS
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(when.trim(), format).ok())
+        .and_then(|naive| zone.from_wall_clock(naive))
+        .ok_or_else(|| unknown(when))
+}
+
+/// Read a time, where a plain date means the start of that day.
+pub fn parse_start_of(when: &str) -> Result<Timestamp, String> {
+    parse_when(when, DayPart::Start)
+}
+
+/// Read a time, where a plain date means the end of that day.
+pub fn parse_end_of(when: &str) -> Result<Timestamp, String> {
+    parse_when(when, DayPart::End)
+}
+
+fn parse_duration(duration: &str) -> Option<Duration> {
+    let (duration, past) = match duration.strip_suffix("ago") {
+        Some(duration) => (duration.trim_end(), true),
+        None => match duration.strip_prefix('-') {
+            Some(duration) => (duration, true),
+            None => (duration.strip_prefix('+').unwrap_or(duration), false),
+        },
+    };
+
+    let unit_at = duration.find(|c: char| !c.is_ascii_digit())?;
+    let (n, unit) = duration.split_at(unit_at);
+    let n = n.parse::<i64>().ok()?;
+    let duration = match unit.trim() {
+        "m" | "min" | "mins" | "minute" | "minutes" => Duration::try_minutes(n)?,
+        "h" | "hour" | "hours" => Duration::try_hours(n)?,
+        "d" | "day" | "days" => Duration::try_days(n)?,
+        "w" | "week" | "weeks" => Duration::try_weeks(n)?,
+        _ => return None,
+    };
+
+    Some(if past { -duration } else { duration })
+}
+
+fn unknown(when: &str) -> String {
+    format!(
+        "{:?} isn't a time sigi understands. Try e.g. tomorrow, 3d, 2h ago or 2024-06-01",
+        when
+    )
+}
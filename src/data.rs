@@ -1,54 +1,245 @@
-use std::error::Error;
+use std::collections::hash_map::RandomState;
+use std::collections::BTreeSet;
+use std::hash::{BuildHasher, Hasher};
 use std::io::ErrorKind;
-use std::{env, fs, path::PathBuf};
+use std::path::{Path, PathBuf};
+use std::{env, fs};
 
 use directories::ProjectDirs;
 
+use crate::error::SigiError;
+
+mod history;
+pub use history::{HistoryEntry, ItemEvent, ItemField, ItemHistory};
+
+mod journal;
+pub use journal::{Journal, JournalChange, JournalEntry, JournalRecorder, MetaChange, StackChange};
+
+mod meta;
+pub use meta::{StackMeta, StackMode};
+
+mod names;
+pub use names::{
+    is_archived, validate_destination, validate_stack_name, ARCHIVE_NAMESPACE, HISTORY_SUFFIX,
+    NAMESPACE_SEPARATOR,
+};
+
+mod json;
+pub use json::JsonStore;
+
+mod sqlite;
+pub use sqlite::SqliteStore;
+
 // TODO: Alternate data stores:
 //       - Redis
-//       - SQLite
-//       - The existing version (JSON via Serde)
-// TODO: Allow an idea of "stack of stacks"
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, FixedOffset, Local, Utc};
 use serde::{Deserialize, Serialize};
 
+const LOCK_FILE: &str = ".lock";
+/// The directory holding a project's own stacks, like `.git` for git.
+pub const PROJECT_DIR: &str = ".sigi";
+const SHORT_ID_LEN: usize = 8;
+
 /// A stack of items.
 pub type Stack = Vec<Item>;
 
-type ItemHistory = Vec<(String, DateTime<Local>)>;
+/// A moment in time, along with the UTC offset of the place it was recorded.
+pub type Timestamp = DateTime<FixedOffset>;
+
+/// The current time, at the local UTC offset.
+pub fn now() -> Timestamp {
+    Local::now().fixed_offset()
+}
+
+/// The time some days before now, if that's a time that can be kept.
+pub fn days_ago(days: i64) -> Option<Timestamp> {
+    chrono::Duration::try_days(days).and_then(|days| now().checked_sub_signed(days))
+}
 
 /// A single stack item.
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "StoredItem")]
 pub struct Item {
+    /// A unique identifier that stays the same however the item is moved.
+    pub id: String,
+    /// The headline of the item.
     pub contents: String,
+    /// Longer notes about the item, like links or steps to reproduce a bug.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>,
     pub history: ItemHistory,
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub tags: BTreeSet<String>,
+    /// When the item should be done by.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub due: Option<Timestamp>,
+    /// The item is deferred (snoozed) and left out of listings until then.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hidden_until: Option<Timestamp>,
+    /// Where the item goes in a stack in priority mode. Lower numbers go first.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+}
+
+/// An item as stored, which may predate item IDs.
+#[derive(Deserialize)]
+struct StoredItem {
+    id: Option<String>,
+    contents: String,
+    #[serde(default)]
+    body: Option<String>,
+    history: ItemHistory,
+    #[serde(default)]
+    tags: BTreeSet<String>,
+    #[serde(default)]
+    due: Option<Timestamp>,
+    #[serde(default)]
+    hidden_until: Option<Timestamp>,
+    #[serde(default)]
+    priority: Option<u32>,
+}
+
+impl From<StoredItem> for Item {
+    fn from(stored: StoredItem) -> Self {
+        let StoredItem {
+            id,
+            contents,
+            body,
+            history,
+            tags,
+            due,
+            hidden_until,
+            priority,
+        } = stored;
+        let id = id.unwrap_or_else(|| backfilled_id(&contents, &history));
+        Item {
+            id,
+            contents,
+            body,
+            history,
+            tags,
+            due,
+            hidden_until,
+            priority,
+        }
+    }
 }
 
 impl Item {
     pub fn new(contents: &str) -> Self {
         Item {
+            id: new_id(),
+            contents: contents.to_string(),
+            body: None,
+            history: vec![HistoryEntry::now(ItemEvent::Created)],
+            tags: BTreeSet::new(),
+            due: None,
+            hidden_until: None,
+            priority: None,
+        }
+    }
+
+    /// Build an item that has no ID yet, such as one from an old file format.
+    pub fn from_history(contents: &str, history: ItemHistory) -> Self {
+        Item {
+            id: backfilled_id(contents, &history),
             contents: contents.to_string(),
-            history: vec![("created".to_string(), Local::now())],
+            body: None,
+            history,
+            tags: BTreeSet::new(),
+            due: None,
+            hidden_until: None,
+            priority: None,
         }
     }
 
+    /// A copy of the item, with an ID of its own, for another stack. The copy
+    /// keeps the history of this item and records where it came from.
+    pub fn copy(&self, from: &str, to: &str) -> Self {
+        let mut copy = self.clone();
+        copy.id = new_id();
+        copy.record(ItemEvent::Copied {
+            from: from.to_string(),
+            to: to.to_string(),
+            id: self.id.clone(),
+        });
+        copy
+    }
+
+    /// The first few characters of the ID, usually enough to tell items apart.
+    pub fn short_id(&self) -> &str {
+        let end = self.id.len().min(SHORT_ID_LEN);
+        &self.id[..end]
+    }
+
+    /// Whether the item is snoozed, as of the given time.
+    pub fn is_deferred(&self, now: Timestamp) -> bool {
+        self.hidden_until.is_some_and(|until| until > now)
+    }
+
+    /// Whether the item is past due, as of the given time.
+    pub fn is_overdue(&self, now: Timestamp) -> bool {
+        self.due.is_some_and(|due| due < now)
+    }
+
+    /// When the item was created, if its history says.
+    pub fn created(&self) -> Option<Timestamp> {
+        self.history
+            .iter()
+            .find(|entry| entry.event == ItemEvent::Created)
+            .map(|entry| entry.time)
+    }
+
+    /// Add something that just happened to the item's history.
+    pub fn record(&mut self, event: ItemEvent) {
+        self.history.push(HistoryEntry::now(event));
+    }
+
     pub fn mark_completed(&mut self) {
-        let event = ("completed".to_string(), Local::now());
-        self.history.push(event);
+        self.record(ItemEvent::Completed);
     }
 
     pub fn mark_deleted(&mut self) {
-        let event = ("deleted".to_string(), Local::now());
-        self.history.push(event);
+        self.record(ItemEvent::Deleted);
     }
 
     pub fn mark_restored(&mut self) {
-        let event = ("restored".to_string(), Local::now());
-        self.history.push(event);
+        self.record(ItemEvent::Restored);
     }
 }
 
+/// Generate an ID for a new item. The std hasher is randomly keyed per
+/// process, so hashing the current time with it gives an unpredictable ID.
+fn new_id() -> String {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_i64(Local::now().timestamp_nanos_opt().unwrap_or_default());
+    format!("{:016x}", hasher.finish())
+}
+
+/// Generate an ID for an item stored before items had IDs. This must give the
+/// same ID every time the item is loaded until it is saved with one, so it's a
+/// (FNV-1a) hash of the item's contents and history.
+fn backfilled_id(contents: &str, history: &ItemHistory) -> String {
+    const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+    const FNV_PRIME: u64 = 0x100000001b3;
+
+    let mut hash = FNV_OFFSET;
+    let mut write = |bytes: &[u8]| {
+        for byte in bytes {
+            hash ^= *byte as u64;
+            hash = hash.wrapping_mul(FNV_PRIME);
+        }
+    };
+    write(contents.as_bytes());
+    for entry in history {
+        write(entry.event.name().as_bytes());
+        // The instant, so the ID is the same wherever and whenever it's read.
+        write(entry.time.with_timezone(&Utc).to_rfc3339().as_bytes());
+    }
+    format!("{:016x}", hash)
+}
+
 pub struct DataStore {
     pub working_dir: WorkingDir,
     pub data_format: DataFormat,
@@ -58,87 +249,201 @@ pub struct DataStore {
 pub enum WorkingDir {
     HomeDir,
     Dir(String),
+    /// A project's `.sigi` directory (see `find_project_dir`).
+    Project(String),
     // TODO: URI (?)
 }
 
 pub enum DataFormat {
+    /// One JSON file per stack.
     SigiJson,
-    // TODO: SQLite
+    /// A single SQLite database file for all stacks.
+    Sqlite,
     // TODO: Redis(?)
 }
 
-impl DataStore {
-    pub fn load(&self, stack_name: &str) -> Result<Stack, impl Error> {
-        match self.data_format {
-            DataFormat::SigiJson => load_json_from(stack_name, &self.dir()),
-        }
+/// Persistence for stacks. Sigi's effects only ever touch stacks through this
+/// trait, so library users can supply their own backend.
+pub trait Storage {
+    /// Load a stack of items. A stack that doesn't exist yet is empty.
+    fn load(&self, stack_name: &str) -> Result<Stack, SigiError>;
+
+    /// Save a stack of items, replacing whatever the stack held before.
+    fn save(&self, stack_name: &str, items: Stack) -> Result<(), SigiError>;
+
+    /// List the names of all stacks.
+    fn list_stacks(&self) -> Result<Vec<String>, SigiError>;
+
+    /// Where the stacks are kept (like a directory), to show people.
+    fn location(&self) -> Option<String> {
+        None
     }
 
-    pub fn save(&self, stack_name: &str, items: Stack) -> Result<(), impl Error> {
-        match self.data_format {
-            DataFormat::SigiJson => save_json_to(stack_name, &self.dir(), items),
+    /// Delete a stack entirely. Deleting a stack that doesn't exist is not an error.
+    fn delete(&self, stack_name: &str) -> Result<(), SigiError>;
+
+    /// Push a single item onto a stack. Backends that can append without
+    /// rewriting the whole stack should override this.
+    fn push(&self, stack_name: &str, item: Item) -> Result<(), SigiError> {
+        let mut items = self.load(stack_name)?;
+        items.push(item);
+        self.save(stack_name, items)
+    }
+
+    /// Take exclusive access to the store until the returned lock is dropped.
+    /// Effects hold this across their whole load-modify-save cycle. Backends
+    /// that are never shared between processes can keep the default no-op.
+    fn lock(&self) -> Result<StoreLock, SigiError> {
+        Ok(StoreLock::default())
+    }
+
+    /// Load the undo/redo journal. Backends without one have nothing to undo.
+    fn load_journal(&self) -> Result<Journal, SigiError> {
+        Ok(Journal::default())
+    }
+
+    /// Save a change to the undo/redo journal. Backends without one can
+    /// ignore it.
+    fn change_journal(&self, _change: JournalChange) -> Result<(), SigiError> {
+        Ok(())
+    }
+
+    /// Load a stack's settings, like its mode. Backends without any have
+    /// the defaults for every stack.
+    fn load_meta(&self, _stack_name: &str) -> Result<StackMeta, SigiError> {
+        Ok(StackMeta::default())
+    }
+
+    /// Save a stack's settings. Saving the defaults forgets them. Backends
+    /// without any can ignore them.
+    fn save_meta(&self, _stack_name: &str, _meta: StackMeta) -> Result<(), SigiError> {
+        Ok(())
+    }
+}
+
+/// Exclusive access to a store. The lock is released when this is dropped.
+#[derive(Default)]
+pub struct StoreLock {
+    _file: Option<fs::File>,
+}
+
+impl StoreLock {
+    /// Lock a data directory against other sigi processes, waiting for any
+    /// current holder. This is an advisory lock on a `.lock` file in the
+    /// directory. A directory we can't write to (like one on a read-only
+    /// mount) can't be changed by us either, so reads there go ahead unlocked.
+    pub fn for_dir(dir: &str) -> Result<StoreLock, SigiError> {
+        let unwritable = |err: &std::io::Error| {
+            matches!(
+                err.kind(),
+                ErrorKind::PermissionDenied | ErrorKind::ReadOnlyFilesystem
+            )
+        };
+        let path = PathBuf::from(dir).join(LOCK_FILE);
+        match fs::create_dir_all(dir) {
+            Ok(()) => (),
+            Err(err) if unwritable(&err) => return Ok(StoreLock::default()),
+            Err(err) => return Err(SigiError::io(format!("create {}", dir))(err)),
         }
+        let file = match fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+        {
+            Ok(file) => file,
+            Err(err) if unwritable(&err) => return Ok(StoreLock::default()),
+            Err(err) => {
+                return Err(SigiError::io(format!("open {}", path.to_string_lossy()))(
+                    err,
+                ))
+            }
+        };
+        file.lock()
+            .map_err(SigiError::io(format!("lock {}", path.to_string_lossy())))?;
+        Ok(StoreLock { _file: Some(file) })
     }
+}
 
-    pub fn list_stacks(&self) -> Result<Vec<String>, impl Error> {
+impl DataStore {
+    fn backend(&self) -> Box<dyn Storage> {
+        let dir = self.dir();
         match self.data_format {
-            DataFormat::SigiJson => list_json_from(&self.dir()),
+            DataFormat::SigiJson => Box::new(JsonStore { dir }),
+            DataFormat::Sqlite => Box::new(SqliteStore { dir }),
         }
     }
 
     fn dir(&self) -> String {
         match self.working_dir.clone() {
             WorkingDir::HomeDir => sigi_path(),
-            WorkingDir::Dir(dir) => dir,
+            WorkingDir::Dir(dir) | WorkingDir::Project(dir) => dir,
         }
     }
 }
 
-/// Save a stack of items.
-// TODO: Create a custom error. This is returning raw filesystem errors.
-fn save_json_to(stack_name: &str, dest_dir: &str, items: Stack) -> Result<(), impl Error> {
-    let data_path: String = sigi_file(dest_dir, stack_name);
-    let json: String = serde_json::to_string(&items).unwrap();
-    let result = fs::write(&data_path, &json);
-    if result.is_err() && result.as_ref().unwrap_err().kind() == ErrorKind::NotFound {
-        fs::create_dir_all(dest_dir).unwrap();
-        fs::write(data_path, json)
-    } else {
-        result
-    }
+/// Find the nearest `.sigi` directory, searching up from the given directory.
+pub fn find_project_dir(from: &Path) -> Option<String> {
+    from.ancestors()
+        .map(|dir| dir.join(PROJECT_DIR))
+        .find(|dir| dir.is_dir())
+        .map(|dir| dir.to_string_lossy().to_string())
 }
 
-/// Load a stack of items.
-// TODO: Create a custom error. This is returning raw serialization errors.
-fn load_json_from(stack_name: &str, dest_dir: &str) -> Result<Stack, impl Error> {
-    let data_path: String = sigi_file(dest_dir, stack_name);
-    let read_result = fs::read_to_string(data_path);
-    if read_result.is_err() && read_result.as_ref().unwrap_err().kind() == ErrorKind::NotFound {
-        return Ok(vec![]);
+/// Create a `.sigi` directory in the given directory, for a project's own
+/// stacks. Gives its path, and whether it was just created.
+pub fn create_project_dir(parent: &Path) -> Result<(String, bool), SigiError> {
+    let dir = parent.join(PROJECT_DIR);
+    let created = !dir.is_dir();
+    fs::create_dir_all(&dir).map_err(SigiError::io(format!("create {}", dir.to_string_lossy())))?;
+    let dir = dir.canonicalize().unwrap_or(dir);
+    Ok((dir.to_string_lossy().to_string(), created))
+}
+
+impl Storage for DataStore {
+    fn load(&self, stack_name: &str) -> Result<Stack, SigiError> {
+        self.backend().load(stack_name)
     }
 
-    let json = read_result.unwrap();
-    let result = serde_json::from_str(&json);
+    fn save(&self, stack_name: &str, items: Stack) -> Result<(), SigiError> {
+        self.backend().save(stack_name, items)
+    }
 
-    if result.is_err() {
-        let v1result = v1_load(&json);
-        if let Ok(v1stack) = v1result {
-            return Ok(v1_to_modern(v1stack));
-        }
+    fn list_stacks(&self) -> Result<Vec<String>, SigiError> {
+        self.backend().list_stacks()
     }
 
-    result
-}
+    fn location(&self) -> Option<String> {
+        self.backend().location()
+    }
+
+    fn delete(&self, stack_name: &str) -> Result<(), SigiError> {
+        self.backend().delete(stack_name)
+    }
+
+    fn push(&self, stack_name: &str, item: Item) -> Result<(), SigiError> {
+        self.backend().push(stack_name, item)
+    }
+
+    fn lock(&self) -> Result<StoreLock, SigiError> {
+        self.backend().lock()
+    }
+
+    fn load_journal(&self) -> Result<Journal, SigiError> {
+        self.backend().load_journal()
+    }
 
-fn list_json_from(dest_dir: &str) -> Result<Vec<String>, impl Error> {
-    let dot_json = ".json";
-    fs::read_dir(dest_dir).map(|files| {
-        files
-            .map(|file| file.unwrap().file_name().into_string().unwrap())
-            .filter(|filename| filename.ends_with(dot_json))
-            .map(|filename| filename.strip_suffix(dot_json).unwrap().to_string())
-            .collect::<Vec<_>>()
-    })
+    fn change_journal(&self, change: JournalChange) -> Result<(), SigiError> {
+        self.backend().change_journal(change)
+    }
+
+    fn load_meta(&self, stack_name: &str) -> Result<StackMeta, SigiError> {
+        self.backend().load_meta(stack_name)
+    }
+
+    fn save_meta(&self, stack_name: &str, meta: StackMeta) -> Result<(), SigiError> {
+        self.backend().save_meta(stack_name, meta)
+    }
 }
 
 fn v1_sigi_path() -> PathBuf {
@@ -162,48 +467,3 @@ fn sigi_path() -> String {
 
     sigi_path.to_string_lossy().to_string()
 }
-
-fn sigi_file(sigi_dir: &str, filename: &str) -> String {
-    let path = format!("{}/{}.json", sigi_dir, filename);
-    PathBuf::from(&path).to_string_lossy().to_string()
-}
-
-/// A single stack item. Used for backwards compatibility with versions of Sigi v1.
-#[derive(Serialize, Deserialize, Debug, Clone)]
-struct V1Item {
-    name: String,
-    created: DateTime<Local>,
-    succeeded: Option<DateTime<Local>>,
-    failed: Option<DateTime<Local>>,
-}
-
-/// A stack of items. Used for backwards compatibility with versions of Sigi v1.
-type V1Stack = Vec<V1Item>;
-
-/// Attempt to read a V1 format file.
-fn v1_load(json_blob: &str) -> Result<V1Stack, impl Error> {
-    serde_json::from_str(json_blob)
-}
-
-fn v1_to_modern(v1stack: V1Stack) -> Stack {
-    v1stack
-        .into_iter()
-        .map(|v1item| {
-            // Translate the old keys to entries.
-            let mut history: ItemHistory = vec![
-                Some(("created", v1item.created)),
-                v1item.succeeded.map(|dt| ("completed", dt)),
-                v1item.failed.map(|dt| ("deleted", dt)),
-            ]
-            .into_iter()
-            .flatten()
-            .map(|(s, dt)| (s.to_string(), dt))
-            .collect();
-            history.sort_by_key(|(_, dt)| *dt);
-            Item {
-                contents: v1item.name,
-                history,
-            }
-        })
-        .collect()
-}
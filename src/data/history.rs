@@ -0,0 +1,150 @@
+//! What happened to an item, and when. Sigi before typed events stored each
+//! entry as a `["name", time]` pair; those are read into the same events.
+
+use std::collections::BTreeSet;
+
+use serde::{Deserialize, Serialize};
+
+use super::{now, Timestamp};
+
+/// The history of an item, oldest first.
+pub type ItemHistory = Vec<HistoryEntry>;
+
+/// One thing that happened to an item.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "StoredEntry")]
+pub struct HistoryEntry {
+    #[serde(flatten)]
+    pub event: ItemEvent,
+    pub time: Timestamp,
+}
+
+/// The kinds of things that happen to items.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum ItemEvent {
+    Created,
+    Completed,
+    Deleted,
+    Restored,
+    /// The item was put on top of its stack, as by swap, rot or pick.
+    Raised,
+    /// The item was put at the bottom of its stack, as by next.
+    Lowered,
+    /// The headline or body was changed from `before`.
+    Edited {
+        field: ItemField,
+        before: Option<String>,
+    },
+    /// The item moved from one stack to another.
+    Moved {
+        from: String,
+        to: String,
+    },
+    /// The item was copied from item `id` in stack `from` to stack `to`. What
+    /// happened before this happened to that item.
+    Copied {
+        from: String,
+        to: String,
+        id: String,
+    },
+    Tagged {
+        tags: BTreeSet<String>,
+    },
+    Untagged {
+        tags: BTreeSet<String>,
+    },
+    /// The item was hidden until the given time.
+    Snoozed {
+        until: Timestamp,
+    },
+    /// The item's due date was set (or cleared).
+    Due {
+        due: Option<Timestamp>,
+    },
+    /// The item's priority was set (or cleared).
+    Prioritized {
+        priority: Option<u32>,
+    },
+    /// An event this version of sigi doesn't know, kept by name.
+    Other {
+        name: String,
+    },
+}
+
+/// The parts of an item that can be edited.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ItemField {
+    Headline,
+    Body,
+}
+
+impl HistoryEntry {
+    /// Something that happened just now.
+    pub fn now(event: ItemEvent) -> Self {
+        HistoryEntry { event, time: now() }
+    }
+}
+
+impl ItemEvent {
+    /// A short name for the event. For events from before typed events, this
+    /// is the name they were stored with.
+    pub fn name(&self) -> &str {
+        match self {
+            ItemEvent::Created => "created",
+            ItemEvent::Completed => "completed",
+            ItemEvent::Deleted => "deleted",
+            ItemEvent::Restored => "restored",
+            ItemEvent::Raised => "raised",
+            ItemEvent::Lowered => "lowered",
+            ItemEvent::Edited { .. } => "edited",
+            ItemEvent::Moved { .. } => "moved",
+            ItemEvent::Copied { .. } => "copied",
+            ItemEvent::Tagged { .. } => "tagged",
+            ItemEvent::Untagged { .. } => "untagged",
+            ItemEvent::Snoozed { .. } => "snoozed",
+            ItemEvent::Due { .. } => "due",
+            ItemEvent::Prioritized { .. } => "prioritized",
+            ItemEvent::Other { name } => name,
+        }
+    }
+
+    /// The event of an untyped history entry with the given name.
+    fn from_name(name: String) -> Self {
+        match name.as_str() {
+            "created" => ItemEvent::Created,
+            "completed" => ItemEvent::Completed,
+            "deleted" => ItemEvent::Deleted,
+            "restored" => ItemEvent::Restored,
+            _ => ItemEvent::Other { name },
+        }
+    }
+}
+
+/// A history entry as stored, which may be an untyped `["name", time]` pair.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredEntry {
+    Typed(TypedEntry),
+    Untyped(String, Timestamp),
+}
+
+#[derive(Deserialize)]
+struct TypedEntry {
+    #[serde(flatten)]
+    event: ItemEvent,
+    time: Timestamp,
+}
+
+impl From<StoredEntry> for HistoryEntry {
+    fn from(stored: StoredEntry) -> Self {
+        match stored {
+            StoredEntry::Typed(TypedEntry { event, time }) => HistoryEntry { event, time },
+            StoredEntry::Untyped(name, time) => HistoryEntry {
+                event: ItemEvent::from_name(name),
+                time,
+            },
+        }
+    }
+}
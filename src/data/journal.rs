@@ -0,0 +1,419 @@
+//! The undo/redo journal. Every effect that changes stacks is recorded as a
+//! journal entry, holding only the part of each stack that changed, so it can
+//! be reverted (undo) or applied again (redo) later.
+
+use std::cell::RefCell;
+
+use serde::{Deserialize, Serialize};
+
+use super::{now, Item, Stack, StackMeta, Storage, StoreLock, Timestamp};
+use crate::error::SigiError;
+
+/// How many entries are kept for undo. Older entries are forgotten.
+pub(super) const JOURNAL_LIMIT: usize = 100;
+
+/// The undo and redo history of a store.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Journal {
+    pub undo: Vec<JournalEntry>,
+    pub redo: Vec<JournalEntry>,
+}
+
+/// Everything one effect changed.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalEntry {
+    pub action: String,
+    pub time: Timestamp,
+    pub changes: Vec<StackChange>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub meta_changes: Vec<MetaChange>,
+}
+
+/// The changed part of one stack. The items outside of the change are left
+/// alone: `before` was replaced by `after`, followed by `suffix` unchanged
+/// items at the top of the stack.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StackChange {
+    pub stack: String,
+    pub suffix: usize,
+    pub before: Stack,
+    pub after: Stack,
+}
+
+/// The settings of one stack, like its mode, before and after.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MetaChange {
+    pub stack: String,
+    pub before: StackMeta,
+    pub after: StackMeta,
+}
+
+/// One change to the journal. Stores save these as they happen, rather than
+/// the whole journal each time.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub enum JournalChange {
+    /// A new entry. Anything that was undone can't be redone anymore.
+    Record(JournalEntry),
+    /// The latest entry was undone.
+    Undo,
+    /// The latest undone entry was redone.
+    Redo,
+}
+
+impl Journal {
+    pub fn apply(&mut self, change: JournalChange) {
+        match change {
+            JournalChange::Record(entry) => {
+                self.undo.push(entry);
+                if self.undo.len() > JOURNAL_LIMIT {
+                    self.undo.drain(..self.undo.len() - JOURNAL_LIMIT);
+                }
+                self.redo.clear();
+            }
+            JournalChange::Undo => self.redo.extend(self.undo.pop()),
+            JournalChange::Redo => self.undo.extend(self.redo.pop()),
+        }
+    }
+
+    /// The changes that make up this journal, oldest first.
+    pub fn changes(self) -> Vec<JournalChange> {
+        let undone = self.redo.len();
+        self.undo
+            .into_iter()
+            .chain(self.redo.into_iter().rev())
+            .map(JournalChange::Record)
+            .chain(std::iter::repeat_n(JournalChange::Undo, undone))
+            .collect()
+    }
+}
+
+impl JournalEntry {
+    /// Put every changed stack back the way it was before the entry.
+    pub fn revert(&self, data_store: &dyn Storage) -> Result<(), SigiError> {
+        let stacks = self
+            .changes
+            .iter()
+            .rev()
+            .map(|change| change.replace(data_store, &change.after, &change.before))
+            .collect::<Result<Vec<_>, _>>()?;
+        let metas = self
+            .meta_changes
+            .iter()
+            .rev()
+            .map(|change| change.replace(data_store, &change.after, &change.before))
+            .collect::<Result<Vec<_>, _>>()?;
+        save_all(data_store, stacks)?;
+        save_all_meta(data_store, metas)
+    }
+
+    /// Apply every change of the entry again.
+    pub fn reapply(&self, data_store: &dyn Storage) -> Result<(), SigiError> {
+        let stacks = self
+            .changes
+            .iter()
+            .map(|change| change.replace(data_store, &change.before, &change.after))
+            .collect::<Result<Vec<_>, _>>()?;
+        let metas = self
+            .meta_changes
+            .iter()
+            .map(|change| change.replace(data_store, &change.before, &change.after))
+            .collect::<Result<Vec<_>, _>>()?;
+        save_all(data_store, stacks)?;
+        save_all_meta(data_store, metas)
+    }
+
+    /// The names of the stacks this entry changed.
+    pub fn stacks(&self) -> Vec<&str> {
+        let mut stacks = self
+            .changes
+            .iter()
+            .map(|c| c.stack.as_str())
+            .collect::<Vec<_>>();
+        for change in self.meta_changes.iter() {
+            if !stacks.contains(&change.stack.as_str()) {
+                stacks.push(&change.stack);
+            }
+        }
+        stacks
+    }
+}
+
+impl StackChange {
+    /// Compute the new contents of the stack with `from` swapped for `to`. If
+    /// the stack no longer holds `from` where expected, it has changed some
+    /// other way since, and it's not safe to touch.
+    fn replace(
+        &self,
+        data_store: &dyn Storage,
+        from: &Stack,
+        to: &Stack,
+    ) -> Result<(String, Stack), SigiError> {
+        let mut items = data_store.load(&self.stack)?;
+        let conflict = || SigiError::JournalConflict {
+            stack: self.stack.clone(),
+        };
+
+        let end = items.len().checked_sub(self.suffix).ok_or_else(conflict)?;
+        let start = end.checked_sub(from.len()).ok_or_else(conflict)?;
+        if items[start..end] != from[..] {
+            return Err(conflict());
+        }
+
+        items.splice(start..end, to.iter().cloned());
+        Ok((self.stack.clone(), items))
+    }
+}
+
+impl MetaChange {
+    /// Check that the stack's settings are still `from`, and give `to` to
+    /// save in their place.
+    fn replace(
+        &self,
+        data_store: &dyn Storage,
+        from: &StackMeta,
+        to: &StackMeta,
+    ) -> Result<(String, StackMeta), SigiError> {
+        if data_store.load_meta(&self.stack)? != *from {
+            return Err(SigiError::JournalConflict {
+                stack: self.stack.clone(),
+            });
+        }
+        Ok((self.stack.clone(), to.clone()))
+    }
+}
+
+fn save_all(data_store: &dyn Storage, stacks: Vec<(String, Stack)>) -> Result<(), SigiError> {
+    for (stack, items) in stacks {
+        data_store.save(&stack, items)?;
+    }
+    Ok(())
+}
+
+fn save_all_meta(
+    data_store: &dyn Storage,
+    metas: Vec<(String, StackMeta)>,
+) -> Result<(), SigiError> {
+    for (stack, meta) in metas {
+        data_store.save_meta(&stack, meta)?;
+    }
+    Ok(())
+}
+
+/// A store that passes everything through to another store, remembering what
+/// each stack looked like before and after so it can be journaled.
+pub struct JournalRecorder<'a> {
+    inner: &'a dyn Storage,
+    touched: RefCell<Vec<Touched>>,
+    metas: RefCell<Vec<MetaChange>>,
+}
+
+struct Touched {
+    stack: String,
+    before: Stack,
+    after: Stack,
+    /// Only pushes have been seen, so `before` and `after` hold just what was
+    /// pushed on top of the untouched stack.
+    pushes_only: bool,
+}
+
+impl<'a> JournalRecorder<'a> {
+    pub fn new(inner: &'a dyn Storage) -> Self {
+        JournalRecorder {
+            inner,
+            touched: RefCell::new(vec![]),
+            metas: RefCell::new(vec![]),
+        }
+    }
+
+    /// Add whatever changed to the journal, if anything did.
+    pub fn commit(self, action: &str) -> Result<(), SigiError> {
+        let changes = self
+            .touched
+            .into_inner()
+            .into_iter()
+            .filter_map(Touched::into_change)
+            .collect::<Vec<_>>();
+        let meta_changes = self
+            .metas
+            .into_inner()
+            .into_iter()
+            .filter(|change| change.before != change.after)
+            .collect::<Vec<_>>();
+
+        if changes.is_empty() && meta_changes.is_empty() {
+            return Ok(());
+        }
+
+        self.inner
+            .change_journal(JournalChange::Record(JournalEntry {
+                action: action.to_string(),
+                time: now(),
+                changes,
+                meta_changes,
+            }))
+    }
+
+    /// Make sure the stack's state from before this effect is known.
+    fn track(&self, stack_name: &str, loaded: Option<&Stack>) -> Result<(), SigiError> {
+        let mut touched = self.touched.borrow_mut();
+        match touched.iter_mut().find(|t| t.stack == stack_name) {
+            Some(t) if t.pushes_only => {
+                let items = match loaded {
+                    Some(items) => items.clone(),
+                    None => self.inner.load(stack_name)?,
+                };
+                let pushed = t.after.len();
+                t.before = items[..items.len().saturating_sub(pushed)].to_vec();
+                t.after = items;
+                t.pushes_only = false;
+            }
+            Some(_) => (),
+            None => {
+                let items = match loaded {
+                    Some(items) => items.clone(),
+                    None => self.inner.load(stack_name)?,
+                };
+                touched.push(Touched {
+                    stack: stack_name.to_string(),
+                    before: items.clone(),
+                    after: items,
+                    pushes_only: false,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn set_after(&self, stack_name: &str, items: Stack) {
+        let mut touched = self.touched.borrow_mut();
+        if let Some(t) = touched.iter_mut().find(|t| t.stack == stack_name) {
+            t.after = items;
+        }
+    }
+}
+
+impl Touched {
+    fn into_change(self) -> Option<StackChange> {
+        let Touched {
+            stack,
+            before,
+            after,
+            pushes_only,
+        } = self;
+
+        if pushes_only {
+            return Some(StackChange {
+                stack,
+                suffix: 0,
+                before,
+                after,
+            });
+        }
+
+        let prefix = before
+            .iter()
+            .zip(after.iter())
+            .take_while(|(a, b)| a == b)
+            .count();
+        let max_suffix = before.len().min(after.len()) - prefix;
+        let suffix = before
+            .iter()
+            .rev()
+            .zip(after.iter().rev())
+            .take(max_suffix)
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        if prefix == before.len() && prefix == after.len() {
+            return None;
+        }
+
+        Some(StackChange {
+            stack,
+            suffix,
+            before: before[prefix..before.len() - suffix].to_vec(),
+            after: after[prefix..after.len() - suffix].to_vec(),
+        })
+    }
+}
+
+impl Storage for JournalRecorder<'_> {
+    fn load(&self, stack_name: &str) -> Result<Stack, SigiError> {
+        let items = self.inner.load(stack_name)?;
+        self.track(stack_name, Some(&items))?;
+        Ok(items)
+    }
+
+    fn save(&self, stack_name: &str, items: Stack) -> Result<(), SigiError> {
+        self.track(stack_name, None)?;
+        self.inner.save(stack_name, items.clone())?;
+        self.set_after(stack_name, items);
+        Ok(())
+    }
+
+    fn list_stacks(&self) -> Result<Vec<String>, SigiError> {
+        self.inner.list_stacks()
+    }
+
+    fn location(&self) -> Option<String> {
+        self.inner.location()
+    }
+
+    fn delete(&self, stack_name: &str) -> Result<(), SigiError> {
+        self.track(stack_name, None)?;
+        self.inner.delete(stack_name)?;
+        self.set_after(stack_name, vec![]);
+        Ok(())
+    }
+
+    fn push(&self, stack_name: &str, item: Item) -> Result<(), SigiError> {
+        self.inner.push(stack_name, item.clone())?;
+
+        let mut touched = self.touched.borrow_mut();
+        match touched.iter_mut().find(|t| t.stack == stack_name) {
+            Some(t) => t.after.push(item),
+            None => touched.push(Touched {
+                stack: stack_name.to_string(),
+                before: vec![],
+                after: vec![item],
+                pushes_only: true,
+            }),
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> Result<StoreLock, SigiError> {
+        // The effect being recorded already holds the inner store's lock.
+        Ok(StoreLock::default())
+    }
+
+    fn load_journal(&self) -> Result<Journal, SigiError> {
+        self.inner.load_journal()
+    }
+
+    fn change_journal(&self, change: JournalChange) -> Result<(), SigiError> {
+        self.inner.change_journal(change)
+    }
+
+    fn load_meta(&self, stack_name: &str) -> Result<StackMeta, SigiError> {
+        self.inner.load_meta(stack_name)
+    }
+
+    fn save_meta(&self, stack_name: &str, meta: StackMeta) -> Result<(), SigiError> {
+        let mut metas = self.metas.borrow_mut();
+        if !metas.iter().any(|change| change.stack == stack_name) {
+            let before = self.inner.load_meta(stack_name)?;
+            metas.push(MetaChange {
+                stack: stack_name.to_string(),
+                before: before.clone(),
+                after: before,
+            });
+        }
+        self.inner.save_meta(stack_name, meta.clone())?;
+        if let Some(change) = metas.iter_mut().find(|change| change.stack == stack_name) {
+            change.after = meta;
+        }
+        Ok(())
+    }
+}
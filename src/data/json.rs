@@ -0,0 +1,339 @@
+//! JSON persistence. Each stack is a single file of the same name in the data
+//! directory, containing the whole stack. Stacks in namespaces, like
+//! `work/bugs`, are kept in subdirectories. Settings of stacks, like their
+//! modes, are kept together in one hidden file.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use super::names::{legacy_stack_path, stack_path, unescape_name_part};
+use super::{
+    HistoryEntry, Item, ItemEvent, ItemHistory, Journal, JournalChange, Stack, StackMeta, Storage,
+    StoreLock, Timestamp,
+};
+use crate::error::SigiError;
+
+const JOURNAL_NAME: &str = "journal";
+/// Once the journal file is this big, it may be rewritten with only the
+/// entries that are still kept.
+const JOURNAL_REWRITE_SIZE: u64 = 1 << 20;
+const META_NAME: &str = "meta";
+
+/// Stacks stored as JSON files in a directory.
+pub struct JsonStore {
+    pub dir: String,
+}
+
+impl Storage for JsonStore {
+    fn load(&self, stack_name: &str) -> Result<Stack, SigiError> {
+        load_json_from(stack_name, &self.dir)
+    }
+
+    fn save(&self, stack_name: &str, items: Stack) -> Result<(), SigiError> {
+        save_json_to(stack_name, &self.dir, items)
+    }
+
+    fn list_stacks(&self) -> Result<Vec<String>, SigiError> {
+        list_json_from(&self.dir)
+    }
+
+    fn location(&self) -> Option<String> {
+        Some(self.dir.clone())
+    }
+
+    fn lock(&self) -> Result<StoreLock, SigiError> {
+        StoreLock::for_dir(&self.dir)
+    }
+
+    fn load_journal(&self) -> Result<Journal, SigiError> {
+        load_journal_from(&self.dir)
+    }
+
+    fn change_journal(&self, change: JournalChange) -> Result<(), SigiError> {
+        change_journal_in(&self.dir, change)
+    }
+
+    fn load_meta(&self, stack_name: &str) -> Result<StackMeta, SigiError> {
+        let mut metas = load_metas_from(&self.dir)?;
+        Ok(metas.remove(stack_name).unwrap_or_default())
+    }
+
+    fn save_meta(&self, stack_name: &str, meta: StackMeta) -> Result<(), SigiError> {
+        let mut metas = load_metas_from(&self.dir)?;
+        let unchanged = match meta.is_default() {
+            true => metas.remove(stack_name).is_none(),
+            false => metas.insert(stack_name.to_string(), meta.clone()) == Some(meta),
+        };
+        match unchanged {
+            true => Ok(()),
+            false => save_metas_to(&self.dir, metas),
+        }
+    }
+
+    fn delete(&self, stack_name: &str) -> Result<(), SigiError> {
+        let data_path = stack_file(&self.dir, stack_name);
+        match fs::remove_file(&data_path) {
+            Err(err) if err.kind() != ErrorKind::NotFound => {
+                return Err(SigiError::io(format!("delete {}", data_path))(err))
+            }
+            _ => (),
+        }
+        // Namespaces left empty go too. Removing a directory that still has
+        // stacks in it fails, which is where this stops.
+        let root = Path::new(&self.dir);
+        let mut dir = Path::new(&data_path).parent();
+        while let Some(namespace) = dir.filter(|dir| dir.starts_with(root) && *dir != root) {
+            if fs::remove_dir(namespace).is_err() {
+                break;
+            }
+            dir = namespace.parent();
+        }
+        Ok(())
+    }
+}
+
+/// Save a stack of items.
+fn save_json_to(stack_name: &str, dest_dir: &str, items: Stack) -> Result<(), SigiError> {
+    let data_path: String = stack_file(dest_dir, stack_name);
+    let json: String = serde_json::to_string(&items).map_err(|err| SigiError::CorruptData {
+        stack: stack_name.to_string(),
+        details: err.to_string(),
+    })?;
+
+    write_atomically(&data_path, json)
+}
+
+/// Write to a temporary file and rename it into place, so the file is only
+/// ever replaced whole, never left half-written.
+fn write_atomically(path: &str, contents: String) -> Result<(), SigiError> {
+    let tmp_path = format!("{}.tmp{}", path, std::process::id());
+    let result = match fs::write(&tmp_path, &contents) {
+        Err(err) if err.kind() == ErrorKind::NotFound => {
+            if let Some(dir) = Path::new(path).parent() {
+                fs::create_dir_all(dir)
+                    .map_err(SigiError::io(format!("create {}", dir.to_string_lossy())))?;
+            }
+            fs::write(&tmp_path, contents)
+        }
+        result => result,
+    };
+    result.map_err(SigiError::io(format!("write {}", tmp_path)))?;
+
+    fs::rename(&tmp_path, path).map_err(|err| {
+        let _ = fs::remove_file(&tmp_path);
+        SigiError::io(format!("write {}", path))(err)
+    })
+}
+
+/// Load the undo/redo journal, by going through the changes made to it.
+fn load_journal_from(dest_dir: &str) -> Result<Journal, SigiError> {
+    let path = journal_file(dest_dir);
+    let lines = match fs::read_to_string(&path) {
+        Ok(lines) => lines,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Journal::default()),
+        Err(err) => return Err(SigiError::io(format!("read {}", path))(err)),
+    };
+    let mut journal = Journal::default();
+    for line in lines.lines().filter(|line| !line.is_empty()) {
+        let change = serde_json::from_str(line).map_err(|err| SigiError::CorruptData {
+            stack: JOURNAL_NAME.to_string(),
+            details: format!("{} ({})", err, path),
+        })?;
+        journal.apply(change);
+    }
+    Ok(journal)
+}
+
+/// Add a change to the end of the undo/redo journal, one change per line.
+fn change_journal_in(dest_dir: &str, change: JournalChange) -> Result<(), SigiError> {
+    let path = journal_file(dest_dir);
+    let line = journal_line(&change)?;
+    fs::create_dir_all(dest_dir).map_err(SigiError::io(format!("create {}", dest_dir)))?;
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(SigiError::io(format!("open {}", path)))?;
+    let size = |file: &fs::File| {
+        file.metadata()
+            .map(|metadata| metadata.len())
+            .map_err(SigiError::io(format!("read {}", path)))
+    };
+    let size_before = size(&file)?;
+    file.write_all(line.as_bytes())
+        .map_err(SigiError::io(format!("write {}", path)))?;
+    let size_after = size(&file)?;
+
+    // The kept entries alone can be big, as they may hold whole stacks. So
+    // the file is only read back each time its size doubles, and only
+    // rewritten if that would at least halve it.
+    let bits = |size: u64| u64::BITS - size.leading_zeros();
+    if size_after < JOURNAL_REWRITE_SIZE || bits(size_after) == bits(size_before) {
+        return Ok(());
+    }
+    let lines = load_journal_from(dest_dir)?
+        .changes()
+        .iter()
+        .map(journal_line)
+        .collect::<Result<String, _>>()?;
+    if lines.len() as u64 * 2 > size_after {
+        return Ok(());
+    }
+    write_atomically(&path, lines)
+}
+
+fn journal_line(change: &JournalChange) -> Result<String, SigiError> {
+    let json = serde_json::to_string(change).map_err(|err| SigiError::CorruptData {
+        stack: JOURNAL_NAME.to_string(),
+        details: err.to_string(),
+    })?;
+    Ok(format!("{}\n", json))
+}
+
+/// Load the settings of every stack that has any.
+fn load_metas_from(dest_dir: &str) -> Result<BTreeMap<String, StackMeta>, SigiError> {
+    let path = meta_file(dest_dir);
+    let json = match fs::read_to_string(&path) {
+        Ok(json) => json,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(BTreeMap::new()),
+        Err(err) => return Err(SigiError::io(format!("read {}", path))(err)),
+    };
+    serde_json::from_str(&json).map_err(|err| SigiError::CorruptData {
+        stack: META_NAME.to_string(),
+        details: format!("{} ({})", err, path),
+    })
+}
+
+/// Save the settings of every stack that has any.
+fn save_metas_to(dest_dir: &str, metas: BTreeMap<String, StackMeta>) -> Result<(), SigiError> {
+    let path = meta_file(dest_dir);
+    let json = serde_json::to_string(&metas).map_err(|err| SigiError::CorruptData {
+        stack: META_NAME.to_string(),
+        details: err.to_string(),
+    })?;
+    write_atomically(&path, json)
+}
+
+/// Load a stack of items.
+fn load_json_from(stack_name: &str, dest_dir: &str) -> Result<Stack, SigiError> {
+    let data_path: String = stack_file(dest_dir, stack_name);
+    let json = match fs::read_to_string(&data_path) {
+        Ok(json) => json,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => return Err(SigiError::io(format!("read {}", data_path))(err)),
+    };
+
+    serde_json::from_str(&json).or_else(|err| match v1_load(&json) {
+        Ok(v1stack) => Ok(v1_to_modern(v1stack)),
+        Err(_) => Err(SigiError::CorruptData {
+            stack: stack_name.to_string(),
+            details: format!("{} ({})", err, data_path),
+        }),
+    })
+}
+
+fn list_json_from(dest_dir: &str) -> Result<Vec<String>, SigiError> {
+    let mut stacks = vec![];
+    list_json_in(dest_dir, "", &mut stacks)?;
+    Ok(stacks)
+}
+
+/// Find stacks in a directory and its subdirectories (namespaces), naming
+/// each with the given namespace prefix.
+fn list_json_in(dir: &str, prefix: &str, stacks: &mut Vec<String>) -> Result<(), SigiError> {
+    let dot_json = ".json";
+    let files = match fs::read_dir(dir) {
+        Ok(files) => files,
+        // No data directory just means no stacks yet.
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(SigiError::io(format!("read {}", dir))(err)),
+    };
+
+    for file in files {
+        let file = file.map_err(SigiError::io(format!("read {}", dir)))?;
+        let filename = file.file_name().to_string_lossy().to_string();
+        // Hidden files, like the journal, are sigi's own bookkeeping.
+        if filename.starts_with('.') {
+            continue;
+        }
+        if file.file_type().is_ok_and(|t| t.is_dir()) {
+            let path = file.path().to_string_lossy().to_string();
+            let namespace = unescape_name_part(&filename);
+            list_json_in(&path, &format!("{}{}/", prefix, namespace), stacks)?;
+        } else if let Some(stack) = filename.strip_suffix(dot_json) {
+            stacks.push(format!("{}{}", prefix, unescape_name_part(stack)));
+        }
+    }
+    Ok(())
+}
+
+/// The journal is JSON Lines, so changes can be added to the end of it.
+fn journal_file(sigi_dir: &str) -> String {
+    let path = format!("{}/.{}.jsonl", sigi_dir, JOURNAL_NAME);
+    PathBuf::from(&path).to_string_lossy().to_string()
+}
+
+fn meta_file(sigi_dir: &str) -> String {
+    sigi_file(sigi_dir, &format!(".{}", META_NAME))
+}
+
+/// The file a stack is stored in. Its name is escaped, so whatever it is,
+/// the file is inside the data directory. A stack older versions stored
+/// under its unescaped name stays where it is.
+fn stack_file(sigi_dir: &str, stack_name: &str) -> String {
+    let file = sigi_file(sigi_dir, &stack_path(stack_name));
+    if Path::new(&file).exists() {
+        return file;
+    }
+    legacy_stack_path(stack_name)
+        .map(|legacy| sigi_file(sigi_dir, &legacy))
+        .filter(|legacy| Path::new(legacy).exists())
+        .unwrap_or(file)
+}
+
+fn sigi_file(sigi_dir: &str, filename: &str) -> String {
+    let path = format!("{}/{}.json", sigi_dir, filename);
+    PathBuf::from(&path).to_string_lossy().to_string()
+}
+
+/// A single stack item. Used for backwards compatibility with versions of Sigi v1.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+struct V1Item {
+    name: String,
+    created: Timestamp,
+    succeeded: Option<Timestamp>,
+    failed: Option<Timestamp>,
+}
+
+/// A stack of items. Used for backwards compatibility with versions of Sigi v1.
+type V1Stack = Vec<V1Item>;
+
+/// Attempt to read a V1 format file.
+fn v1_load(json_blob: &str) -> Result<V1Stack, impl Error> {
+    serde_json::from_str(json_blob)
+}
+
+fn v1_to_modern(v1stack: V1Stack) -> Stack {
+    v1stack
+        .into_iter()
+        .map(|v1item| {
+            // Translate the old keys to entries.
+            let mut history: ItemHistory = vec![
+                Some((ItemEvent::Created, v1item.created)),
+                v1item.succeeded.map(|dt| (ItemEvent::Completed, dt)),
+                v1item.failed.map(|dt| (ItemEvent::Deleted, dt)),
+            ]
+            .into_iter()
+            .flatten()
+            .map(|(event, time)| HistoryEntry { event, time })
+            .collect();
+            history.sort_by_key(|entry| entry.time);
+            Item::from_history(&v1item.name, history)
+        })
+        .collect()
+}
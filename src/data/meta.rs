@@ -0,0 +1,60 @@
+//! What's kept about a stack apart from its items, like its mode.
+
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+
+/// Settings of one stack. A stack nobody has changed these for has the
+/// defaults, and nothing is stored for it.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct StackMeta {
+    pub mode: StackMode,
+}
+
+/// The order items come off a stack in. Sigi keeps each stack's items in
+/// this order, so the top is always the one to do next.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum StackMode {
+    /// Last in, first out: new items go on top.
+    #[default]
+    Stack,
+    /// First in, first out: new items go on the bottom.
+    Queue,
+    /// Items with the lowest priority number go on top, and items of the same
+    /// priority are first in, first out. Items without a priority go last.
+    Priority,
+}
+
+impl StackMeta {
+    pub fn is_default(&self) -> bool {
+        *self == StackMeta::default()
+    }
+}
+
+impl StackMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StackMode::Stack => "stack",
+            StackMode::Queue => "queue",
+            StackMode::Priority => "priority",
+        }
+    }
+}
+
+impl FromStr for StackMode {
+    type Err = String;
+
+    fn from_str(mode: &str) -> Result<Self, Self::Err> {
+        match mode.to_ascii_lowercase().as_str() {
+            "stack" | "lifo" => Ok(StackMode::Stack),
+            "queue" | "fifo" => Ok(StackMode::Queue),
+            "priority" => Ok(StackMode::Priority),
+            _ => Err(format!(
+                "{:?} isn't a stack mode. Try stack, queue or priority",
+                mode
+            )),
+        }
+    }
+}
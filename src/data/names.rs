@@ -0,0 +1,140 @@
+//! Stack names: which are allowed, and how they're written as file names.
+
+use crate::error::SigiError;
+
+/// Ends the name of the stack a stack's completed and deleted items go to.
+pub const HISTORY_SUFFIX: &str = "_history";
+
+/// Separates the parts of a stack name, like `work/backend/bugs`.
+pub const NAMESPACE_SEPARATOR: char = '/';
+
+/// The namespace archived stacks are kept in, out of the way.
+pub const ARCHIVE_NAMESPACE: &str = "_archive";
+
+/// Names Windows won't give a file, whatever the extension.
+const RESERVED_NAMES: [&str; 22] = [
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8",
+    "com9", "lpt1", "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+/// Check that a stack name can be stored, so nothing is ever written outside
+/// the data store. Slashes nest stacks in namespaces, so each part between
+/// them is checked on its own.
+pub fn validate_stack_name(stack: &str) -> Result<(), SigiError> {
+    let invalid = |reason: &str| {
+        Err(SigiError::InvalidStackName {
+            stack: stack.to_string(),
+            reason: reason.to_string(),
+        })
+    };
+
+    if stack.is_empty() {
+        return invalid("it's empty");
+    }
+    for part in stack.split(NAMESPACE_SEPARATOR) {
+        let base = part.split('.').next().unwrap_or_default();
+        match part {
+            "" => return invalid("namespaces can't be empty (check for extra slashes)"),
+            "." | ".." => return invalid("\".\" and \"..\" aren't allowed"),
+            _ if part.chars().any(char::is_control) => {
+                return invalid("control characters aren't allowed")
+            }
+            _ if RESERVED_NAMES.contains(&base.to_ascii_lowercase().as_str()) => {
+                return invalid(&format!("{:?} is reserved on some systems", base))
+            }
+            _ => (),
+        }
+    }
+    Ok(())
+}
+
+/// Check a stack name as a place to put new items. History stacks only get
+/// items by completing or deleting them.
+pub fn validate_destination(stack: &str) -> Result<(), SigiError> {
+    validate_stack_name(stack)?;
+    if stack.ends_with(HISTORY_SUFFIX) {
+        return Err(SigiError::InvalidStackName {
+            stack: stack.to_string(),
+            reason: format!(
+                "names ending in {:?} are for history stacks",
+                HISTORY_SUFFIX
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Whether a stack has been archived.
+pub fn is_archived(stack: &str) -> bool {
+    stack
+        .strip_prefix(ARCHIVE_NAMESPACE)
+        .is_some_and(|rest| rest.starts_with(NAMESPACE_SEPARATOR))
+}
+
+/// Characters some systems (Windows) don't allow in file names, and `%`,
+/// which starts an escape.
+const ESCAPED_CHARS: [char; 9] = ['%', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// Write one part of a stack name so it's a safe file name. Characters that
+/// aren't, and `%` itself, are written as `%XX`. So is a leading `.`, which
+/// would hide the file (sigi's own files are hidden ones), and a trailing `.`
+/// or space, which Windows drops.
+fn escape_name_part(part: &str) -> String {
+    let last = part.chars().count().saturating_sub(1);
+    let mut escaped = String::with_capacity(part.len());
+    for (i, c) in part.chars().enumerate() {
+        match c {
+            c if ESCAPED_CHARS.contains(&c) => escaped.push_str(&format!("%{:02X}", c as u32)),
+            '.' if i == 0 => escaped.push_str("%2E"),
+            '.' | ' ' if i == last => escaped.push_str(&format!("%{:02X}", c as u32)),
+            c if c.is_control() => {
+                let mut buf = [0; 4];
+                for byte in c.encode_utf8(&mut buf).bytes() {
+                    escaped.push_str(&format!("%{:02X}", byte));
+                }
+            }
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Read a part of a stack name back from a file name. Anything that isn't a
+/// valid escape is kept as it is.
+pub(super) fn unescape_name_part(part: &str) -> String {
+    let bytes = part.as_bytes();
+    let mut unescaped = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|hex| std::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                unescaped.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                unescaped.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8(unescaped).unwrap_or_else(|_| part.to_string())
+}
+
+/// The relative path, without extension, a stack is stored at.
+pub(super) fn stack_path(stack: &str) -> String {
+    stack
+        .split(NAMESPACE_SEPARATOR)
+        .map(escape_name_part)
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Where older versions stored a stack: its name as it is, unescaped. `None`
+/// if that's where it's stored now.
+pub(super) fn legacy_stack_path(stack: &str) -> Option<String> {
+    Some(stack.to_string()).filter(|path| *path != stack_path(stack))
+}
@@ -0,0 +1,339 @@
+//! SQLite persistence. All stacks in a data directory share a single database
+//! file, with one row per item. Items themselves are stored as JSON, so they
+//! stay in step with the JSON format as items grow new fields. So are the
+//! settings of stacks, like their modes.
+
+use std::fs;
+use std::path::PathBuf;
+
+use rusqlite::types::Type;
+use rusqlite::{params, Connection, ErrorCode, OpenFlags, OptionalExtension, Result};
+
+use super::journal::JOURNAL_LIMIT;
+use super::{Item, Journal, JournalChange, Stack, StackMeta, Storage, StoreLock};
+use crate::error::SigiError;
+
+const SIGI_DB_FILE: &str = "sigi.db";
+const JOURNAL_NAME: &str = "journal";
+
+/// Stacks stored in a single SQLite database in a directory.
+pub struct SqliteStore {
+    pub dir: String,
+}
+
+impl Storage for SqliteStore {
+    fn load(&self, stack_name: &str) -> Result<Stack, SigiError> {
+        load_sqlite_from(stack_name, &self.dir).map_err(db_error(stack_name, &self.dir))
+    }
+
+    fn save(&self, stack_name: &str, items: Stack) -> Result<(), SigiError> {
+        create_dir(&self.dir)?;
+        save_sqlite_to(stack_name, &self.dir, items).map_err(db_error(stack_name, &self.dir))
+    }
+
+    fn list_stacks(&self) -> Result<Vec<String>, SigiError> {
+        list_sqlite_from(&self.dir).map_err(db_error("", &self.dir))
+    }
+
+    fn location(&self) -> Option<String> {
+        Some(db_path(&self.dir).to_string_lossy().to_string())
+    }
+
+    fn delete(&self, stack_name: &str) -> Result<(), SigiError> {
+        delete_sqlite_from(stack_name, &self.dir).map_err(db_error(stack_name, &self.dir))
+    }
+
+    fn lock(&self) -> Result<StoreLock, SigiError> {
+        StoreLock::for_dir(&self.dir)
+    }
+
+    fn push(&self, stack_name: &str, item: Item) -> Result<(), SigiError> {
+        create_dir(&self.dir)?;
+        push_sqlite_to(stack_name, &self.dir, item).map_err(db_error(stack_name, &self.dir))
+    }
+
+    fn load_journal(&self) -> Result<Journal, SigiError> {
+        load_journal_from(&self.dir).map_err(db_error(JOURNAL_NAME, &self.dir))
+    }
+
+    fn change_journal(&self, change: JournalChange) -> Result<(), SigiError> {
+        create_dir(&self.dir)?;
+        change_journal_in(&self.dir, change).map_err(db_error(JOURNAL_NAME, &self.dir))
+    }
+
+    fn load_meta(&self, stack_name: &str) -> Result<StackMeta, SigiError> {
+        load_meta_from(stack_name, &self.dir).map_err(db_error(stack_name, &self.dir))
+    }
+
+    fn save_meta(&self, stack_name: &str, meta: StackMeta) -> Result<(), SigiError> {
+        create_dir(&self.dir)?;
+        save_meta_to(stack_name, &self.dir, meta).map_err(db_error(stack_name, &self.dir))
+    }
+}
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS stacks (
+    name TEXT PRIMARY KEY
+);
+CREATE TABLE IF NOT EXISTS items (
+    stack    TEXT    NOT NULL,
+    position INTEGER NOT NULL,
+    item     TEXT    NOT NULL,
+    PRIMARY KEY (stack, position)
+);
+CREATE TABLE IF NOT EXISTS journal_entries (
+    seq     INTEGER PRIMARY KEY AUTOINCREMENT,
+    undone  INTEGER NOT NULL,
+    entry   TEXT    NOT NULL
+);
+CREATE TABLE IF NOT EXISTS stack_meta (
+    stack TEXT PRIMARY KEY,
+    meta  TEXT NOT NULL
+);
+";
+
+/// Load a stack of items.
+fn load_sqlite_from(stack_name: &str, dest_dir: &str) -> Result<Stack> {
+    let conn = match open_existing(dest_dir)? {
+        Some(conn) => conn,
+        None => return Ok(vec![]),
+    };
+
+    let mut query = conn.prepare("SELECT item FROM items WHERE stack = ?1 ORDER BY position")?;
+    let rows = query.query_map(params![stack_name], |row| row.get::<_, String>(0))?;
+
+    let mut items = vec![];
+    for json in rows {
+        let item = serde_json::from_str(&json?)
+            .map_err(|err| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, err.into()))?;
+        items.push(item);
+    }
+    Ok(items)
+}
+
+/// Save a stack of items, replacing whatever the stack held before.
+fn save_sqlite_to(stack_name: &str, dest_dir: &str, items: Stack) -> Result<()> {
+    let mut conn = open_or_create(dest_dir)?;
+    let tx = conn.transaction()?;
+
+    tx.execute(
+        "INSERT OR IGNORE INTO stacks (name) VALUES (?1)",
+        params![stack_name],
+    )?;
+    tx.execute("DELETE FROM items WHERE stack = ?1", params![stack_name])?;
+    {
+        let mut insert =
+            tx.prepare("INSERT INTO items (stack, position, item) VALUES (?1, ?2, ?3)")?;
+        for (position, item) in items.iter().enumerate() {
+            let json = serde_json::to_string(item)
+                .map_err(|err| rusqlite::Error::ToSqlConversionFailure(err.into()))?;
+            insert.execute(params![stack_name, position as i64, json])?;
+        }
+    }
+
+    tx.commit()?;
+    Ok(())
+}
+
+/// Push a single item onto the top of a stack. This is a single-row insert;
+/// the rest of the stack is left untouched.
+fn push_sqlite_to(stack_name: &str, dest_dir: &str, item: Item) -> Result<()> {
+    let conn = open_or_create(dest_dir)?;
+    let json = serde_json::to_string(&item)
+        .map_err(|err| rusqlite::Error::ToSqlConversionFailure(err.into()))?;
+
+    conn.execute(
+        "INSERT OR IGNORE INTO stacks (name) VALUES (?1)",
+        params![stack_name],
+    )?;
+    conn.execute(
+        "INSERT INTO items (stack, position, item)
+         VALUES (?1, (SELECT COALESCE(MAX(position), -1) + 1 FROM items WHERE stack = ?1), ?2)",
+        params![stack_name, json],
+    )?;
+    Ok(())
+}
+
+/// List the names of all stacks in the database.
+fn list_sqlite_from(dest_dir: &str) -> Result<Vec<String>> {
+    let conn = match open_existing(dest_dir)? {
+        Some(conn) => conn,
+        None => return Ok(vec![]),
+    };
+
+    let mut query = conn.prepare("SELECT name FROM stacks")?;
+    let names = query
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    Ok(names)
+}
+
+/// Delete a stack and all of its items.
+fn delete_sqlite_from(stack_name: &str, dest_dir: &str) -> Result<()> {
+    let mut conn = match open_existing(dest_dir)? {
+        Some(conn) => conn,
+        None => return Ok(()),
+    };
+
+    let tx = conn.transaction()?;
+    tx.execute("DELETE FROM items WHERE stack = ?1", params![stack_name])?;
+    tx.execute("DELETE FROM stacks WHERE name = ?1", params![stack_name])?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Load the undo/redo journal. Entries are in the order they were made, and
+/// the undone ones are the latest, so undo takes the last entry that isn't
+/// undone and redo the first that is.
+fn load_journal_from(dest_dir: &str) -> Result<Journal> {
+    let conn = match open_existing(dest_dir)? {
+        Some(conn) => conn,
+        None => return Ok(Journal::default()),
+    };
+
+    let mut query = conn.prepare("SELECT undone, entry FROM journal_entries ORDER BY seq")?;
+    let mut journal = Journal::default();
+    for row in query.query_map([], |row| {
+        Ok((row.get::<_, bool>(0)?, row.get::<_, String>(1)?))
+    })? {
+        let (undone, json) = row?;
+        let entry = serde_json::from_str(&json)
+            .map_err(|err| rusqlite::Error::FromSqlConversionFailure(1, Type::Text, err.into()))?;
+        match undone {
+            true => journal.redo.insert(0, entry),
+            false => journal.undo.push(entry),
+        }
+    }
+    Ok(journal)
+}
+
+/// Save a change to the undo/redo journal, touching only the entries it
+/// changes.
+fn change_journal_in(dest_dir: &str, change: JournalChange) -> Result<()> {
+    let mut conn = open_or_create(dest_dir)?;
+    let tx = conn.transaction()?;
+    match change {
+        JournalChange::Record(entry) => {
+            let json = serde_json::to_string(&entry)
+                .map_err(|err| rusqlite::Error::ToSqlConversionFailure(err.into()))?;
+            tx.execute("DELETE FROM journal_entries WHERE undone = 1", [])?;
+            tx.execute(
+                "INSERT INTO journal_entries (undone, entry) VALUES (0, ?1)",
+                params![json],
+            )?;
+            tx.execute(
+                "DELETE FROM journal_entries WHERE seq NOT IN
+                    (SELECT seq FROM journal_entries ORDER BY seq DESC LIMIT ?1)",
+                params![JOURNAL_LIMIT as i64],
+            )?;
+        }
+        JournalChange::Undo => {
+            tx.execute(
+                "UPDATE journal_entries SET undone = 1 WHERE seq =
+                    (SELECT MAX(seq) FROM journal_entries WHERE undone = 0)",
+                [],
+            )?;
+        }
+        JournalChange::Redo => {
+            tx.execute(
+                "UPDATE journal_entries SET undone = 0 WHERE seq =
+                    (SELECT MIN(seq) FROM journal_entries WHERE undone = 1)",
+                [],
+            )?;
+        }
+    }
+    tx.commit()
+}
+
+/// Load a stack's settings.
+fn load_meta_from(stack_name: &str, dest_dir: &str) -> Result<StackMeta> {
+    let conn = match open_existing(dest_dir)? {
+        Some(conn) => conn,
+        None => return Ok(StackMeta::default()),
+    };
+
+    let json = conn
+        .query_row(
+            "SELECT meta FROM stack_meta WHERE stack = ?1",
+            params![stack_name],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?;
+    match json {
+        Some(json) => serde_json::from_str(&json)
+            .map_err(|err| rusqlite::Error::FromSqlConversionFailure(0, Type::Text, err.into())),
+        None => Ok(StackMeta::default()),
+    }
+}
+
+/// Save a stack's settings. The defaults aren't stored.
+fn save_meta_to(stack_name: &str, dest_dir: &str, meta: StackMeta) -> Result<()> {
+    if meta.is_default() {
+        if let Some(conn) = open_existing(dest_dir)? {
+            conn.execute(
+                "DELETE FROM stack_meta WHERE stack = ?1",
+                params![stack_name],
+            )?;
+        }
+        return Ok(());
+    }
+    let conn = open_or_create(dest_dir)?;
+    let json = serde_json::to_string(&meta)
+        .map_err(|err| rusqlite::Error::ToSqlConversionFailure(err.into()))?;
+    conn.execute(
+        "INSERT OR REPLACE INTO stack_meta (stack, meta) VALUES (?1, ?2)",
+        params![stack_name, json],
+    )?;
+    Ok(())
+}
+
+fn db_path(dest_dir: &str) -> PathBuf {
+    PathBuf::from(dest_dir).join(SIGI_DB_FILE)
+}
+
+/// Open the database, or `None` if it hasn't been created yet. Reads never
+/// create a database file.
+fn open_existing(dest_dir: &str) -> Result<Option<Connection>> {
+    let path = db_path(dest_dir);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_WRITE)?;
+    conn.execute_batch(SCHEMA)?;
+    Ok(Some(conn))
+}
+
+fn open_or_create(dest_dir: &str) -> Result<Connection> {
+    let conn = Connection::open(db_path(dest_dir))?;
+    conn.execute_batch(SCHEMA)?;
+    Ok(conn)
+}
+
+fn create_dir(dest_dir: &str) -> Result<(), SigiError> {
+    fs::create_dir_all(dest_dir).map_err(SigiError::io(format!("create {}", dest_dir)))
+}
+
+/// Sort database errors into unreadable data, which is worth telling apart,
+/// and everything else, which is an IO problem as far as sigi is concerned.
+fn db_error(stack_name: &str, dest_dir: &str) -> impl FnOnce(rusqlite::Error) -> SigiError {
+    let stack = stack_name.to_string();
+    let path = db_path(dest_dir).to_string_lossy().to_string();
+    move |err| match &err {
+        rusqlite::Error::FromSqlConversionFailure(..) => SigiError::CorruptData {
+            stack,
+            details: format!("{} ({})", err, path),
+        },
+        rusqlite::Error::SqliteFailure(failure, _)
+            if matches!(
+                failure.code,
+                ErrorCode::DatabaseCorrupt | ErrorCode::NotADatabase
+            ) =>
+        {
+            SigiError::CorruptData {
+                stack,
+                details: format!("{} ({})", err, path),
+            }
+        }
+        _ => SigiError::io(format!("access {}", path))(std::io::Error::other(err)),
+    }
+}
@@ -1,11 +1,19 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet};
 use std::process::Command;
+use std::str::FromStr;
+use std::{error, fmt};
 
-use chrono::Local;
+use chrono::NaiveDate;
+use regex::{Regex, RegexBuilder};
 
-use crate::data::{DataStore, Item};
-use crate::output::OutputFormat;
-
-const HISTORY_SUFFIX: &str = "_history";
+use crate::data::{
+    days_ago, is_archived, now, validate_destination, validate_stack_name, HistoryEntry, Item,
+    ItemEvent, ItemField, JournalChange, JournalRecorder, Stack, StackMeta, StackMode, Storage,
+    Timestamp, ARCHIVE_NAMESPACE, HISTORY_SUFFIX, NAMESPACE_SEPARATOR,
+};
+use crate::error::SigiError;
+use crate::output::{NoiseLevel, OutputFormat};
 
 // TODO: Consider more shuffle words: https://docs.factorcode.org/content/article-shuffle-words.html
 
@@ -13,30 +21,48 @@ pub enum StackEffect {
     Push {
         stack: String,
         content: String,
+        tags: BTreeSet<String>,
+        due: Option<Timestamp>,
+        priority: Option<u32>,
     },
     Complete {
         stack: String,
-        index: usize,
+        index: ItemRef,
+        /// Trim the history stack to this afterwards.
+        retention: Option<RetentionPolicy>,
+    },
+    Compact {
+        stack: String,
+        /// Every history stack that isn't archived, not just the stack's own.
+        all: bool,
+        policy: RetentionPolicy,
     },
     Delete {
         stack: String,
-        index: usize,
+        index: ItemRef,
+        /// Trim the history stack to this afterwards.
+        retention: Option<RetentionPolicy>,
     },
     DeleteAll {
         stack: String,
+        /// Trim the history stack to this afterwards.
+        retention: Option<RetentionPolicy>,
     },
     Edit {
         stack: String,
         editor: String,
-        index: usize,
+        index: ItemRef,
+        /// Edit the item's body instead of its headline.
+        body: bool,
     },
     Pick {
         stack: String,
-        indices: Vec<usize>,
+        indices: Vec<ItemRef>,
     },
     Move {
         stack: String,
         dest: String,
+        index: ItemRef,
     },
     MoveAll {
         stack: String,
@@ -54,520 +80,2875 @@ pub enum StackEffect {
     Peek {
         stack: String,
     },
+    Show {
+        stack: String,
+        index: ItemRef,
+    },
     ListAll {
         stack: String,
+        filter: ItemFilter,
+        /// Include the stacks in the stack's namespace, like `work/bugs` for `work`.
+        recursive: bool,
+        /// List the items as they were at this time, instead of now.
+        as_of: Option<Timestamp>,
+    },
+    ListStacks {
+        /// List archived stacks instead of the others.
+        archived: bool,
+        /// Include history stacks, each under the stack it belongs to.
+        history: bool,
+        /// Summarize each stack, counting items completed in this many days.
+        summary: Option<u32>,
+    },
+    RenameStack {
+        stack: String,
+        dest: String,
+    },
+    CopyStack {
+        stack: String,
+        dest: String,
+    },
+    DeleteStack {
+        stack: String,
+    },
+    ArchiveStack {
+        stack: String,
     },
-    ListStacks,
     Head {
         stack: String,
         n: usize,
+        filter: ItemFilter,
     },
     Tail {
         stack: String,
         n: usize,
+        filter: ItemFilter,
     },
     Count {
         stack: String,
+        filter: ItemFilter,
+        /// Include the stacks in the stack's namespace, like `work/bugs` for `work`.
+        recursive: bool,
     },
     IsEmpty {
         stack: String,
     },
+    Restore {
+        stack: String,
+        index: ItemRef,
+    },
+    Tag {
+        stack: String,
+        index: ItemRef,
+        tags: BTreeSet<String>,
+    },
+    Untag {
+        stack: String,
+        index: ItemRef,
+        tags: BTreeSet<String>,
+    },
+    Snooze {
+        stack: String,
+        index: ItemRef,
+        until: Timestamp,
+    },
+    Due {
+        stack: String,
+        index: ItemRef,
+        due: Option<Timestamp>,
+    },
+    /// Print the stack's mode, or change it.
+    Mode {
+        stack: String,
+        mode: Option<StackMode>,
+    },
+    Overdue,
+    Prioritize {
+        stack: String,
+        index: ItemRef,
+        priority: Option<u32>,
+    },
+    Report {
+        since: Timestamp,
+        /// Only these stacks, and the stacks in their namespaces. Empty
+        /// means every stack that isn't archived.
+        stacks: Vec<String>,
+    },
+    Search {
+        query: SearchQuery,
+    },
+    Stats {
+        /// Only these stacks, and the stacks in their namespaces. Empty
+        /// means every stack that isn't archived.
+        stacks: Vec<String>,
+        /// How many weeks back to count completed items for throughput.
+        weeks: u32,
+        /// How many of the oldest open items to show.
+        oldest: usize,
+    },
+    Undo,
+    Redo,
 }
 
-impl StackEffect {
-    pub fn run(self, data_store: &DataStore, output: &OutputFormat) {
-        use StackEffect::*;
+/// How much of a history stack `compact` keeps. An item is kept only if
+/// every limit given keeps it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// Keep the N most recently finished items.
+    pub keep: Option<usize>,
+    /// Keep items finished in the last N days.
+    pub keep_days: Option<u32>,
+    /// Move older items to a stack under `_archive` for the month they were
+    /// finished in, instead of dropping them.
+    pub archive: bool,
+}
+
+/// A way to point at one item in a stack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ItemRef {
+    /// The item's position, counted from the top of the stack (0 is the top).
+    Position(usize),
+    /// A prefix of the item's ID. Written as `@3fa9` on the command line.
+    Id(String),
+}
+
+impl FromStr for ItemRef {
+    type Err = InvalidItemRef;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.strip_prefix('@') {
+            Some(id) if !id.is_empty() => Ok(ItemRef::Id(id.to_ascii_lowercase())),
+            Some(_) => Err(InvalidItemRef {
+                given: s.to_string(),
+            }),
+            None => usize::from_str(s)
+                .map(ItemRef::Position)
+                .map_err(|_| InvalidItemRef {
+                    given: s.to_string(),
+                }),
+        }
+    }
+}
+
+impl fmt::Display for ItemRef {
+    fn fmt(&self, out: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         match self {
-            Push { stack, content } => push_content(stack, content, data_store, output),
-            Complete { stack, index } => complete_item(stack, index, data_store, output),
-            Delete { stack, index } => delete_latest_item(stack, index, data_store, output),
-            DeleteAll { stack } => delete_all_items(stack, data_store, output),
-            Edit {
-                stack,
-                editor,
-                index,
-            } => edit_item(stack, editor, index, data_store, output),
-            Pick { stack, indices } => pick_indices(stack, indices, data_store, output),
-            Move { stack, dest } => move_latest_item(stack, dest, data_store, output),
-            MoveAll { stack, dest } => move_all_items(stack, dest, data_store, output),
-            Swap { stack } => swap_latest_two_items(stack, data_store, output),
-            Rot { stack } => rotate_latest_three_items(stack, data_store, output),
-            Next { stack } => next_to_latest(stack, data_store, output),
-            Peek { stack } => peek_latest_item(stack, data_store, output),
-            ListAll { stack } => list_all_items(stack, data_store, output),
-            ListStacks => list_stacks(data_store, output),
-            Head { stack, n } => list_n_latest_items(stack, n, data_store, output),
-            Tail { stack, n } => list_n_oldest_items(stack, n, data_store, output),
-            Count { stack } => count_all_items(stack, data_store, output),
-            IsEmpty { stack } => is_empty(stack, data_store, output),
+            ItemRef::Position(n) => write!(out, "{}", n),
+            ItemRef::Id(id) => write!(out, "@{}", id),
         }
     }
 }
 
-fn push_content(stack: String, content: String, data_store: &DataStore, output: &OutputFormat) {
-    let item = Item::new(&content);
-    push_item(stack, item, data_store, output);
+/// Which items to include when listing or counting. The default filter
+/// matches every item that isn't deferred.
+#[derive(Debug, Clone, Default)]
+pub struct ItemFilter {
+    /// Only items with all of these tags.
+    pub tags: BTreeSet<String>,
+    /// Only the deferred items, instead of only the ones that aren't.
+    pub deferred: bool,
 }
 
-fn push_item(stack: String, item: Item, data_store: &DataStore, output: &OutputFormat) {
-    let contents = item.contents.clone();
+impl ItemFilter {
+    pub fn matches(&self, item: &Item, now: Timestamp) -> bool {
+        item.is_deferred(now) == self.deferred && self.tags.is_subset(&item.tags)
+    }
+}
 
-    let items = if let Ok(items) = data_store.load(&stack) {
-        let mut items = items;
-        items.push(item);
-        items
-    } else {
-        vec![item]
-    };
+/// What to look for when searching items across stacks.
+#[derive(Debug, Clone, Default)]
+pub struct SearchQuery {
+    pub pattern: String,
+    /// Read the pattern as a regular expression, instead of as plain text.
+    pub regex: bool,
+    pub ignore_case: bool,
+    /// Search history stacks too.
+    pub history: bool,
+    /// Only search these stacks, and the stacks in their namespaces. Empty
+    /// means every stack that isn't archived.
+    pub stacks: Vec<String>,
+    /// Which parts of items to search. Empty means headlines and bodies.
+    pub fields: BTreeSet<SearchField>,
+}
+
+/// A part of an item that can be searched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SearchField {
+    Headline,
+    Body,
+    Tags,
+}
+
+impl FromStr for SearchField {
+    type Err = String;
+
+    fn from_str(field: &str) -> Result<Self, Self::Err> {
+        match field.to_ascii_lowercase().as_str() {
+            "headline" | "item" => Ok(SearchField::Headline),
+            "body" => Ok(SearchField::Body),
+            "tags" | "tag" => Ok(SearchField::Tags),
+            _ => Err(format!(
+                "{:?} isn't a field sigi can search. Try headline, body or tags",
+                field
+            )),
+        }
+    }
+}
 
-    data_store.save(&stack, items).unwrap();
+impl SearchQuery {
+    fn matcher(&self) -> Result<Regex, SigiError> {
+        let pattern = match self.regex {
+            true => self.pattern.clone(),
+            false => regex::escape(&self.pattern),
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(self.ignore_case)
+            .build()
+            .map_err(|err| SigiError::InvalidPattern {
+                pattern: self.pattern.clone(),
+                details: err.to_string(),
+            })
+    }
+
+    fn searches(&self, field: SearchField) -> bool {
+        match self.fields.is_empty() {
+            true => field != SearchField::Tags,
+            false => self.fields.contains(&field),
+        }
+    }
+
+    fn includes_stack(&self, stack: &str) -> bool {
+        let stack = match self.history {
+            true => stack.strip_suffix(HISTORY_SUFFIX).unwrap_or(stack),
+            false if stack.ends_with(HISTORY_SUFFIX) => return false,
+            false => stack,
+        };
+        is_chosen(stack, &self.stacks)
+    }
 
-    output.log(vec!["action", "item"], vec![vec!["Created", &contents]]);
+    fn matches(&self, matcher: &Regex, item: &Item) -> bool {
+        (self.searches(SearchField::Headline) && matcher.is_match(&item.contents))
+            || (self.searches(SearchField::Body)
+                && item
+                    .body
+                    .as_ref()
+                    .is_some_and(|body| matcher.is_match(body)))
+            || (self.searches(SearchField::Tags) && item.tags.iter().any(|t| matcher.is_match(t)))
+    }
 }
 
-fn complete_item(stack: String, index: usize, data_store: &DataStore, output: &OutputFormat) {
-    if let Ok(items) = data_store.load(&stack) {
-        let mut items = items;
+#[derive(Debug)]
+pub struct InvalidItemRef {
+    given: String,
+}
 
-        if items.len() > index {
-            let mut item = items.remove(items.len() - index - 1);
-            item.mark_completed();
+impl error::Error for InvalidItemRef {}
 
-            // Push the now-marked-completed item to history stack.
-            push_item(
-                stack_history_of(&stack),
-                item.clone(),
-                data_store,
-                &OutputFormat::Silent,
-            );
+impl fmt::Display for InvalidItemRef {
+    fn fmt(&self, out: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(
+            out,
+            "{:?} is neither an item number nor an item ID like @3fa9",
+            self.given
+        )
+    }
+}
+
+impl StackEffect {
+    /// Run the effect against any storage backend.
+    pub fn run(self, data_store: &dyn Storage, output: &OutputFormat) -> Result<(), SigiError> {
+        self.validate()?;
+
+        // An editor can be open for a long time, so editing takes the lock
+        // only to read the item and then to save the edit.
+        if let StackEffect::Edit {
+            stack,
+            editor,
+            index,
+            body,
+        } = self
+        {
+            return edit_item(stack, editor, index, body, data_store, output);
+        }
 
-            // Save the original stack without that item.
-            data_store.save(&stack, items).unwrap();
+        // Held until the effect is done, so concurrent sigi processes take turns.
+        let _lock = data_store.lock()?;
 
-            output.log(
-                vec!["action", "item"],
-                vec![vec!["Completed", &item.contents]],
-            );
+        // Undo and redo move through the journal rather than adding to it,
+        // and effects that only read have nothing to add.
+        if self.is_read_only() || matches!(self, StackEffect::Undo | StackEffect::Redo) {
+            return self.apply(data_store, output);
         }
+
+        let action = self.name();
+        journaled(action, data_store, |recorder| self.apply(recorder, output))
     }
 
-    if output.is_nonquiet_for_humans() {
-        peek_latest_item(stack, data_store, output);
+    /// Check the stack names the effect was given before touching any data.
+    fn validate(&self) -> Result<(), SigiError> {
+        use StackEffect::*;
+        match self {
+            Push { stack, .. }
+            | Mode {
+                stack,
+                mode: Some(_),
+            } => validate_destination(stack),
+            Move { stack, dest, .. }
+            | MoveAll { stack, dest }
+            | RenameStack { stack, dest }
+            | CopyStack { stack, dest } => {
+                validate_stack_name(stack)?;
+                validate_destination(dest)
+            }
+            Complete { stack, .. }
+            | Compact { stack, .. }
+            | Delete { stack, .. }
+            | DeleteAll { stack, .. }
+            | Edit { stack, .. }
+            | Pick { stack, .. }
+            | Swap { stack }
+            | Rot { stack }
+            | Next { stack }
+            | Peek { stack }
+            | Show { stack, .. }
+            | ListAll { stack, .. }
+            | Head { stack, .. }
+            | Tail { stack, .. }
+            | Count { stack, .. }
+            | IsEmpty { stack }
+            | Restore { stack, .. }
+            | Tag { stack, .. }
+            | Untag { stack, .. }
+            | Snooze { stack, .. }
+            | Due { stack, .. }
+            | Mode { stack, mode: None }
+            | Prioritize { stack, .. }
+            | DeleteStack { stack }
+            | ArchiveStack { stack } => validate_stack_name(stack),
+            Search { query } => query
+                .stacks
+                .iter()
+                .try_for_each(|stack| validate_stack_name(stack)),
+            Report { stacks, .. } | Stats { stacks, .. } => stacks
+                .iter()
+                .try_for_each(|stack| validate_stack_name(stack)),
+            ListStacks { .. } | Overdue | Undo | Redo => Ok(()),
+        }
+    }
+
+    /// Whether the effect only reads stacks, never changing them.
+    fn is_read_only(&self) -> bool {
+        use StackEffect::*;
+        matches!(
+            self,
+            Peek { .. }
+                | Show { .. }
+                | ListAll { .. }
+                | ListStacks { .. }
+                | Head { .. }
+                | Tail { .. }
+                | Count { .. }
+                | IsEmpty { .. }
+                | Overdue
+                | Report { .. }
+                | Search { .. }
+                | Stats { .. }
+                | Mode { mode: None, .. }
+        )
+    }
+
+    /// A short name for the effect, as in the CLI.
+    pub fn name(&self) -> &'static str {
+        use StackEffect::*;
+        match self {
+            Push { .. } => "push",
+            Complete { .. } => "complete",
+            Compact { .. } => "compact",
+            Delete { .. } => "delete",
+            DeleteAll { .. } => "delete-all",
+            Edit { .. } => "edit",
+            Pick { .. } => "pick",
+            Move { .. } => "move",
+            MoveAll { .. } => "move-all",
+            Swap { .. } => "swap",
+            Rot { .. } => "rot",
+            Next { .. } => "next",
+            Peek { .. } => "peek",
+            Show { .. } => "show",
+            ListAll { .. } => "list",
+            ListStacks { .. } => "list-stacks",
+            RenameStack { .. } => "rename-stack",
+            CopyStack { .. } => "copy-stack",
+            DeleteStack { .. } => "delete-stack",
+            ArchiveStack { .. } => "archive-stack",
+            Head { .. } => "head",
+            Tail { .. } => "tail",
+            Count { .. } => "count",
+            IsEmpty { .. } => "is-empty",
+            Restore { .. } => "restore",
+            Tag { .. } => "tag",
+            Untag { .. } => "untag",
+            Snooze { .. } => "snooze",
+            Due { .. } => "due",
+            Mode { .. } => "mode",
+            Overdue => "overdue",
+            Prioritize { .. } => "priority",
+            Report { .. } => "report",
+            Search { .. } => "search",
+            Stats { .. } => "stats",
+            Undo => "undo",
+            Redo => "redo",
+        }
+    }
+
+    fn apply(self, data_store: &dyn Storage, output: &OutputFormat) -> Result<(), SigiError> {
+        use StackEffect::*;
+        match self {
+            Push {
+                stack,
+                content,
+                tags,
+                due,
+                priority,
+            } => push_content(stack, content, tags, due, priority, data_store, output),
+            Complete {
+                stack,
+                index,
+                retention,
+            } => complete_item(stack, index, retention, data_store, output),
+            Compact { stack, all, policy } => {
+                compact_history(stack, all, policy, data_store, output)
+            }
+            Delete {
+                stack,
+                index,
+                retention,
+            } => delete_latest_item(stack, index, retention, data_store, output),
+            DeleteAll { stack, retention } => {
+                delete_all_items(stack, retention, data_store, output)
+            }
+            Edit { .. } => unreachable!("[BUG] Edits are run by themselves, outside the lock."),
+            Pick { stack, indices } => pick_indices(stack, indices, data_store, output),
+            Move { stack, dest, index } => move_item(stack, dest, index, data_store, output),
+            MoveAll { stack, dest } => move_all_items(stack, dest, data_store, output),
+            Swap { stack } => swap_latest_two_items(stack, data_store, output),
+            Rot { stack } => rotate_latest_three_items(stack, data_store, output),
+            Next { stack } => next_to_latest(stack, data_store, output),
+            Peek { stack } => peek_latest_item(stack, data_store, output),
+            Show { stack, index } => show_item(stack, index, data_store, output),
+            ListAll {
+                stack,
+                filter,
+                recursive: false,
+                as_of,
+            } => list_all_items(stack, filter, as_of, data_store, output),
+            ListAll {
+                stack,
+                filter,
+                recursive: true,
+                as_of,
+            } => list_namespace(stack, filter, as_of, data_store, output),
+            ListStacks {
+                archived,
+                history,
+                summary,
+            } => list_stacks(archived, history, summary, data_store, output),
+            RenameStack { stack, dest } => rename_stack(stack, dest, data_store, output),
+            CopyStack { stack, dest } => copy_stack(stack, dest, data_store, output),
+            DeleteStack { stack } => delete_stack(stack, data_store, output),
+            ArchiveStack { stack } => archive_stack(stack, data_store, output),
+            Head { stack, n, filter } => list_n_latest_items(stack, n, filter, data_store, output),
+            Tail { stack, n, filter } => list_n_oldest_items(stack, n, filter, data_store, output),
+            Count {
+                stack,
+                filter,
+                recursive,
+            } => count_all_items(stack, filter, recursive, data_store, output),
+            IsEmpty { stack } => is_empty(stack, data_store, output),
+            Restore { stack, index } => restore_item(stack, index, data_store, output),
+            Tag { stack, index, tags } => tag_item(stack, index, tags, data_store, output),
+            Untag { stack, index, tags } => untag_item(stack, index, tags, data_store, output),
+            Snooze {
+                stack,
+                index,
+                until,
+            } => snooze_item(stack, index, until, data_store, output),
+            Due { stack, index, due } => set_due(stack, index, due, data_store, output),
+            Mode { stack, mode } => stack_mode(stack, mode, data_store, output),
+            Overdue => list_overdue(data_store, output),
+            Prioritize {
+                stack,
+                index,
+                priority,
+            } => set_priority(stack, index, priority, data_store, output),
+            Report { since, stacks } => report(since, stacks, data_store, output),
+            Search { query } => search_items(query, data_store, output),
+            Stats {
+                stacks,
+                weeks,
+                oldest,
+            } => show_stats(stacks, weeks, oldest, data_store, output),
+            Undo => undo_latest(data_store, output),
+            Redo => redo_latest(data_store, output),
+        }
     }
 }
 
-fn delete_latest_item(stack: String, index: usize, data_store: &DataStore, output: &OutputFormat) {
-    if let Ok(items) = data_store.load(&stack) {
-        let mut items = items;
+type EffectResult = Result<(), SigiError>;
+
+/// Make changes to the store, journaling whatever they change, even if they
+/// fail partway.
+fn journaled(
+    action: &str,
+    data_store: &dyn Storage,
+    change: impl FnOnce(&dyn Storage) -> EffectResult,
+) -> EffectResult {
+    let recorder = JournalRecorder::new(data_store);
+    let result = change(&recorder);
+    recorder.commit(action)?;
+    result
+}
 
-        if items.len() > index {
-            let mut item = items.remove(items.len() - index - 1);
-            item.mark_deleted();
+fn push_content(
+    stack: String,
+    content: String,
+    tags: BTreeSet<String>,
+    due: Option<Timestamp>,
+    priority: Option<u32>,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    let mut item = Item::new(&content);
+    item.tags = tags;
+    if due.is_some() {
+        item.due = due;
+        item.record(ItemEvent::Due { due });
+    }
+    if priority.is_some() {
+        item.priority = priority;
+        item.record(ItemEvent::Prioritized { priority });
+    }
+    push_item(stack, item, data_store, output)
+}
 
-            // Push the now-marked-deleted item to history stack.
-            push_item(
-                stack_history_of(&stack),
-                item.clone(),
-                data_store,
-                &OutputFormat::Silent,
-            );
+fn push_item(
+    stack: String,
+    item: Item,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    let contents = item.contents.clone();
+    let id = display_id(&item, output);
+
+    match data_store.load_meta(&stack)?.mode {
+        StackMode::Stack => data_store.push(&stack, item)?,
+        mode => {
+            let mut items = data_store.load(&stack)?;
+            place_item(&mut items, item, mode);
+            data_store.save(&stack, items)?;
+        }
+    }
 
-            // Save the original stack without that item.
-            data_store.save(&stack, items).unwrap();
+    output.log(
+        vec!["action", "item", "id"],
+        vec![vec!["Created", &contents, &id]],
+    );
+    Ok(())
+}
 
-            output.log(
-                vec!["action", "item"],
-                vec![vec!["Deleted", &item.contents]],
-            );
+fn complete_item(
+    stack: String,
+    index: ItemRef,
+    retention: Option<RetentionPolicy>,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    let mut items = data_store.load(&stack)?;
+
+    if let Some(i) = find_item(&stack, &items, &index)? {
+        let mut item = items.remove(i);
+        item.mark_completed();
+
+        // Push the now-marked-completed item to history stack.
+        push_item(
+            stack_history_of(&stack),
+            item.clone(),
+            data_store,
+            &OutputFormat::Silent,
+        )?;
+
+        // Save the original stack without that item.
+        data_store.save(&stack, items)?;
+        if let Some(policy) = retention {
+            apply_retention(&stack_history_of(&stack), &policy, data_store)?;
         }
+
+        output.log(
+            vec!["action", "item"],
+            vec![vec!["Completed", &item.contents]],
+        );
     }
 
     if output.is_nonquiet_for_humans() {
-        peek_latest_item(stack, data_store, output);
+        peek_latest_item(stack, data_store, output)?;
     }
+    Ok(())
 }
 
-fn delete_all_items(stack: String, data_store: &DataStore, output: &OutputFormat) {
-    if let Ok(items) = data_store.load(&stack) {
-        let mut items = items;
-        items.iter_mut().for_each(|item| item.mark_deleted());
-        let n_deleted = items.len();
-
-        // Push the now-marked-deleted items to history stack.
-        let history_stack = &stack_history_of(&stack);
-        let mut history = data_store.load(history_stack).unwrap_or_default();
-        history.append(&mut items);
-        data_store.save(history_stack, history).unwrap();
-
-        // Save the original stack as empty now.
-        data_store.save(&stack, vec![]).unwrap();
+fn delete_latest_item(
+    stack: String,
+    index: ItemRef,
+    retention: Option<RetentionPolicy>,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    let mut items = data_store.load(&stack)?;
+
+    if let Some(i) = find_item(&stack, &items, &index)? {
+        let mut item = items.remove(i);
+        item.mark_deleted();
+
+        // Push the now-marked-deleted item to history stack.
+        push_item(
+            stack_history_of(&stack),
+            item.clone(),
+            data_store,
+            &OutputFormat::Silent,
+        )?;
+
+        // Save the original stack without that item.
+        data_store.save(&stack, items)?;
+        if let Some(policy) = retention {
+            apply_retention(&stack_history_of(&stack), &policy, data_store)?;
+        }
 
         output.log(
             vec!["action", "item"],
-            vec![vec!["Deleted", &format!("{} items", n_deleted)]],
+            vec![vec!["Deleted", &item.contents]],
         );
     }
+
+    if output.is_nonquiet_for_humans() {
+        peek_latest_item(stack, data_store, output)?;
+    }
+    Ok(())
 }
 
-fn edit_item(
+fn restore_item(
     stack: String,
-    editor: String,
-    index: usize,
-    data_store: &DataStore,
-    output: &OutputFormat,
-) {
-    if let Ok(items) = data_store.load(&stack) {
-        let mut items = items;
-        if index < items.len() {
-            let tmp = std::env::temp_dir().as_path().join("sigi");
-            std::fs::create_dir_all(&tmp).unwrap_or_else(|err| {
-                panic!(
-                    "Unable to create temporary directory {:?} for editing: {}",
-                    tmp, err
-                )
-            });
-            let tmpfile = tmp.as_path().join(Local::now().timestamp().to_string());
-            std::fs::write(&tmpfile, &items[index].contents).unwrap_or_else(|err| {
-                panic!(
-                    "Unable to write to temporary file {:?} for editing: {}",
-                    tmpfile, err
-                )
-            });
+    index: ItemRef,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    let history_stack = stack_history_of(&stack);
+    let mut history = data_store.load(&history_stack)?;
 
-            let editor = editor.split_whitespace().collect::<Vec<_>>();
+    if let Some(i) = find_any_item(&history_stack, &history, &index)? {
+        let mut item = history.remove(i);
+        item.mark_restored();
 
-            let edit_exit_code = Command::new(editor[0])
-                .args(&editor[1..])
-                .arg(&tmpfile)
-                .status()
-                .unwrap_or_else(|err| panic!("Failed to execute {:?} editor: {}", editor, err));
+        // Push the now-marked-restored item back to the original stack.
+        push_item(
+            stack.clone(),
+            item.clone(),
+            data_store,
+            &OutputFormat::Silent,
+        )?;
 
-            if edit_exit_code.success() {
-                let new_content = std::fs::read_to_string(&tmpfile).unwrap_or_else(|err| {
-                    panic!(
-                        "Unable to read from temporary file {:?} after editing: {}",
-                        tmpfile, err
-                    )
-                });
-                items[index].contents.clone_from(&new_content);
+        // Save the history stack without that item.
+        data_store.save(&history_stack, history)?;
 
-                data_store.save(&stack, items).unwrap();
+        output.log(
+            vec!["action", "item"],
+            vec![vec!["Restored", &item.contents]],
+        );
+    }
 
-                output.log(vec!["action", "item"], vec![vec!["Edited", &new_content]]);
-            }
-        }
+    if output.is_nonquiet_for_humans() {
+        peek_latest_item(stack, data_store, output)?;
     }
+    Ok(())
 }
 
-fn pick_indices(stack: String, indices: Vec<usize>, data_store: &DataStore, output: &OutputFormat) {
-    if let Ok(items) = data_store.load(&stack) {
-        let mut items = items;
-        let mut seen: Vec<usize> = vec![];
-        seen.reserve_exact(indices.len());
-        let indices: Vec<usize> = indices.iter().map(|i| items.len() - 1 - i).rev().collect();
-        for i in indices {
-            if i > items.len() || seen.contains(&i) {
-                // TODO: What should be the output here? Some stderr?
-                // command.log("Pick", "ignoring out-of-bounds index");
-                // command.log("Pick", "ignoring duplicate index");
-                continue;
-            }
-            let i = i - seen.iter().filter(|j| j < &&i).count();
-            let picked = items.remove(i);
-            items.push(picked);
-            seen.push(i);
+fn tag_item(
+    stack: String,
+    index: ItemRef,
+    tags: BTreeSet<String>,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    let mut items = data_store.load(&stack)?;
+
+    if let Some(i) = find_item(&stack, &items, &index)? {
+        let item = &mut items[i];
+        let added = tags
+            .difference(&item.tags)
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        if !added.is_empty() {
+            item.tags.extend(added.iter().cloned());
+            item.record(ItemEvent::Tagged { tags: added });
         }
+        let (contents, tags) = (item.contents.clone(), display_tags(item, output));
 
-        data_store.save(&stack, items).unwrap();
+        data_store.save(&stack, items)?;
 
-        if output.is_nonquiet_for_humans() {
-            list_n_latest_items(stack, seen.len(), data_store, output);
-        }
+        output.log(
+            vec!["action", "item", "tags"],
+            vec![vec!["Tagged", &contents, &tags]],
+        );
     }
+    Ok(())
 }
 
-fn move_latest_item(source: String, dest: String, data_store: &DataStore, output: &OutputFormat) {
-    if let Ok(items) = data_store.load(&source) {
-        let mut items = items;
-        if let Some(item) = items.pop() {
-            data_store.save(&source, items).unwrap();
+fn untag_item(
+    stack: String,
+    index: ItemRef,
+    tags: BTreeSet<String>,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    let mut items = data_store.load(&stack)?;
+
+    if let Some(i) = find_item(&stack, &items, &index)? {
+        let item = &mut items[i];
+        let removed = tags
+            .intersection(&item.tags)
+            .cloned()
+            .collect::<BTreeSet<_>>();
+        if !removed.is_empty() {
+            item.tags.retain(|tag| !removed.contains(tag));
+            item.record(ItemEvent::Untagged { tags: removed });
+        }
+        let (contents, tags) = (item.contents.clone(), display_tags(item, output));
 
-            output.log(
-                vec!["action", "new-stack", "old-stack"],
-                vec![vec!["Move", &dest, &source]],
-            );
+        data_store.save(&stack, items)?;
 
-            push_item(dest, item, data_store, &OutputFormat::Silent);
-        }
+        output.log(
+            vec!["action", "item", "tags"],
+            vec![vec!["Untagged", &contents, &tags]],
+        );
     }
+    Ok(())
 }
 
-fn move_all_items(source: String, dest: String, data_store: &DataStore, output: &OutputFormat) {
-    if let Ok(src_items) = data_store.load(&source) {
-        let count = src_items.len();
+fn snooze_item(
+    stack: String,
+    index: ItemRef,
+    until: Timestamp,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    let mut items = data_store.load(&stack)?;
 
-        if !src_items.is_empty() {
-            let all_items = match data_store.load(&dest) {
-                Ok(dest_items) => {
-                    let mut all_items = dest_items;
-                    for item in src_items {
-                        all_items.push(item);
-                    }
-                    all_items
-                }
-                _ => src_items,
-            };
+    if let Some(i) = find_item(&stack, &items, &index)? {
+        items[i].hidden_until = Some(until);
+        items[i].record(ItemEvent::Snoozed { until });
+        let contents = items[i].contents.clone();
 
-            data_store.save(&dest, all_items).unwrap();
-            data_store.save(&source, vec![]).unwrap();
-        }
+        data_store.save(&stack, items)?;
 
         output.log(
-            vec!["action", "new-stack", "old-stack", "num-moved"],
-            vec![vec!["Move All", &dest, &source, &count.to_string()]],
+            vec!["action", "item", "until"],
+            vec![vec!["Snoozed", &contents, &output.format_time(until)]],
         );
     }
-}
 
-fn swap_latest_two_items(stack: String, data_store: &DataStore, output: &OutputFormat) {
-    if let Ok(items) = data_store.load(&stack) {
-        let mut items = items;
+    if output.is_nonquiet_for_humans() {
+        peek_latest_item(stack, data_store, output)?;
+    }
+    Ok(())
+}
 
-        if items.len() < 2 {
-            return;
-        }
+fn set_due(
+    stack: String,
+    index: ItemRef,
+    due: Option<Timestamp>,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    let mut items = data_store.load(&stack)?;
 
-        let a = items.pop().unwrap();
-        let b = items.pop().unwrap();
-        items.push(a);
-        items.push(b);
+    if let Some(i) = find_item(&stack, &items, &index)? {
+        items[i].due = due;
+        items[i].record(ItemEvent::Due { due });
+        let contents = items[i].contents.clone();
 
-        data_store.save(&stack, items).unwrap();
+        data_store.save(&stack, items)?;
 
-        if output.is_nonquiet_for_humans() {
-            list_n_latest_items(stack, 2, data_store, output);
-        }
+        let due = due
+            .map(|due| output.format_time(due))
+            .unwrap_or_else(|| "never".to_string());
+        output.log(
+            vec!["action", "item", "due"],
+            vec![vec!["Due", &contents, &due]],
+        );
     }
+    Ok(())
 }
 
-fn rotate_latest_three_items(stack: String, data_store: &DataStore, output: &OutputFormat) {
-    if let Ok(items) = data_store.load(&stack) {
-        let mut items = items;
-
-        if items.len() < 3 {
-            swap_latest_two_items(stack, data_store, output);
-            return;
+fn set_priority(
+    stack: String,
+    index: ItemRef,
+    priority: Option<u32>,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    let mut items = data_store.load(&stack)?;
+
+    if let Some(i) = find_item(&stack, &items, &index)? {
+        let mut item = items.remove(i);
+        item.priority = priority;
+        item.record(ItemEvent::Prioritized { priority });
+        let contents = item.contents.clone();
+
+        // A priority stack puts the item where its new priority goes.
+        match data_store.load_meta(&stack)?.mode {
+            StackMode::Priority => place_item(&mut items, item, StackMode::Priority),
+            _ => items.insert(i, item),
         }
+        data_store.save(&stack, items)?;
 
-        let a = items.pop().unwrap();
-        let b = items.pop().unwrap();
-        let c = items.pop().unwrap();
+        let priority = priority
+            .map(|priority| priority.to_string())
+            .unwrap_or_else(|| "none".to_string());
+        output.log(
+            vec!["action", "item", "priority"],
+            vec![vec!["Priority", &contents, &priority]],
+        );
+    }
+    Ok(())
+}
 
-        items.push(a);
-        items.push(c);
-        items.push(b);
+fn delete_all_items(
+    stack: String,
+    retention: Option<RetentionPolicy>,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    let mut items = data_store.load(&stack)?;
+    items.iter_mut().for_each(|item| item.mark_deleted());
+    let n_deleted = items.len();
+
+    // Push the now-marked-deleted items to history stack.
+    let history_stack = &stack_history_of(&stack);
+    let mut history = data_store.load(history_stack)?;
+    history.append(&mut items);
+    data_store.save(history_stack, history)?;
+    if let Some(policy) = retention {
+        apply_retention(history_stack, &policy, data_store)?;
+    }
 
-        data_store.save(&stack, items).unwrap();
+    // Save the original stack as empty now.
+    data_store.save(&stack, vec![])?;
 
-        if output.is_nonquiet_for_humans() {
-            list_n_latest_items(stack, 3, data_store, output);
-        }
-    }
+    output.log(
+        vec!["action", "item"],
+        vec![vec!["Deleted", &format!("{} items", n_deleted)]],
+    );
+    Ok(())
 }
 
-fn next_to_latest(stack: String, data_store: &DataStore, output: &OutputFormat) {
-    if let Ok(items) = data_store.load(&stack) {
-        let mut items = items;
-        if items.is_empty() {
-            return;
+fn edit_item(
+    stack: String,
+    editor: String,
+    index: ItemRef,
+    body: bool,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    let original = {
+        let _lock = data_store.lock()?;
+        let items = data_store.load(&stack)?;
+        match find_item(&stack, &items, &index)? {
+            Some(i) => items[i].clone(),
+            None => return Ok(()),
         }
-        let to_the_back = items.pop().unwrap();
-        items.insert(0, to_the_back);
-
-        data_store.save(&stack, items).unwrap();
+    };
+    let old_content = match body {
+        true => original.body.clone().unwrap_or_default(),
+        false => original.contents.clone(),
+    };
 
-        if output.is_nonquiet_for_humans() {
-            peek_latest_item(stack, data_store, output);
-        }
+    let tmp = std::env::temp_dir().as_path().join("sigi");
+    std::fs::create_dir_all(&tmp).map_err(SigiError::io(format!(
+        "create temporary directory {:?} for editing",
+        tmp
+    )))?;
+    let tmpfile = tmp.as_path().join(now().timestamp().to_string());
+    std::fs::write(&tmpfile, old_content).map_err(SigiError::io(format!(
+        "write to temporary file {:?} for editing",
+        tmpfile
+    )))?;
+
+    let editor = editor.split_whitespace().collect::<Vec<_>>();
+
+    let edit_exit_code = Command::new(editor[0])
+        .args(&editor[1..])
+        .arg(&tmpfile)
+        .status()
+        .map_err(SigiError::io(format!("execute {:?} editor", editor)))?;
+
+    if !edit_exit_code.success() {
+        return Ok(());
     }
+    let new_content = std::fs::read_to_string(&tmpfile).map_err(SigiError::io(format!(
+        "read from temporary file {:?} after editing",
+        tmpfile
+    )))?;
+    // Editors like to end files with a newline, which isn't part of the item.
+    let new_content = new_content.trim_end_matches(['\r', '\n']).to_string();
+
+    // The stack may have changed while the editor was open. The edit is only
+    // saved if the item itself didn't.
+    let _lock = data_store.lock()?;
+    journaled("edit", data_store, |data_store| {
+        let mut items = data_store.load(&stack)?;
+        let index = items
+            .iter()
+            .position(|item| item.id == original.id)
+            .filter(|i| items[*i] == original)
+            .ok_or_else(|| SigiError::EditConflict {
+                stack: stack.clone(),
+                id: original.id.clone(),
+            })?;
+        let item = &mut items[index];
+        if body {
+            let new_body = Some(new_content).filter(|body| !body.is_empty());
+            if new_body != item.body {
+                let before = std::mem::replace(&mut item.body, new_body);
+                item.record(ItemEvent::Edited {
+                    field: ItemField::Body,
+                    before,
+                });
+            }
+        } else if new_content != item.contents {
+            let before = std::mem::replace(&mut item.contents, new_content);
+            item.record(ItemEvent::Edited {
+                field: ItemField::Headline,
+                before: Some(before),
+            });
+        }
+        let contents = item.contents.clone();
+
+        data_store.save(&stack, items)?;
+
+        output.log(vec!["action", "item"], vec![vec!["Edited", &contents]]);
+        Ok(())
+    })
 }
 
-fn peek_latest_item(stack: String, data_store: &DataStore, output: &OutputFormat) {
-    if let OutputFormat::Silent = output {
-        return;
+fn pick_indices(
+    stack: String,
+    indices: Vec<ItemRef>,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    let mut items = data_store.load(&stack)?;
+    let mut seen: Vec<usize> = vec![];
+    seen.reserve_exact(indices.len());
+    let mut picked_indices = vec![];
+    for index in indices.iter() {
+        match index {
+            // TODO: What should be the output here? Some stderr?
+            // command.log("Pick", "ignoring out-of-bounds index");
+            ItemRef::Position(n) if *n >= visible_indices(&items).len() => continue,
+            _ => picked_indices.extend(find_item(&stack, &items, index)?),
+        }
+    }
+    for i in picked_indices.into_iter().rev() {
+        if seen.contains(&i) {
+            // command.log("Pick", "ignoring duplicate index");
+            continue;
+        }
+        let i = i - seen.iter().filter(|j| j < &&i).count();
+        let mut picked = items.remove(i);
+        picked.record(ItemEvent::Raised);
+        items.push(picked);
+        seen.push(i);
     }
 
-    if let Ok(items) = data_store.load(&stack) {
-        let top_item = items.last().map(|i| i.contents.as_str());
-
-        let output_it = |it| output.log_always(vec!["position", "item"], it);
+    data_store.save(&stack, items)?;
 
-        match top_item {
-            Some(contents) => output_it(vec![vec!["Now", contents]]),
-            None => {
-                if output.is_nonquiet_for_humans() {
-                    output_it(vec![vec!["Now", "NOTHING"]])
-                } else {
-                    output_it(vec![])
-                }
-            }
-        }
+    if output.is_nonquiet_for_humans() {
+        list_n_latest_items(stack, seen.len(), ItemFilter::default(), data_store, output)?;
     }
+    Ok(())
 }
 
-fn count_all_items(stack: String, data_store: &DataStore, output: &OutputFormat) {
-    if let OutputFormat::Silent = output {
-        return;
-    }
+fn move_item(
+    source: String,
+    dest: String,
+    index: ItemRef,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    let mut items = data_store.load(&source)?;
+    if let Some(i) = find_item(&source, &items, &index)? {
+        let mut item = items.remove(i);
+        item.record(ItemEvent::Moved {
+            from: source.clone(),
+            to: dest.clone(),
+        });
+        data_store.save(&source, items)?;
+
+        output.log(
+            vec!["action", "new-stack", "old-stack"],
+            vec![vec!["Move", &dest, &source]],
+        );
 
-    if let Ok(items) = data_store.load(&stack) {
-        let len = items.len().to_string();
-        output.log_always(vec!["items"], vec![vec![&len]])
+        push_item(dest, item, data_store, &OutputFormat::Silent)?;
     }
+    Ok(())
 }
 
-fn is_empty(stack: String, data_store: &DataStore, output: &OutputFormat) {
-    if let Ok(items) = data_store.load(&stack) {
-        if !items.is_empty() {
-            output.log_always(vec!["empty"], vec![vec!["false"]]);
-            // Exit with a failure (nonzero status) when not empty.
-            // This helps people who do shell scripting do something like:
-            //     while ! sigi -t $stack is-empty ; do <ETC> ; done
-            // TODO: It would be better modeled as an error, if anyone uses as a lib this will surprise.
-            if let OutputFormat::TerseText = output {
-                return;
-            } else {
-                std::process::exit(1);
-            }
+fn move_all_items(
+    source: String,
+    dest: String,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    let mut src_items = data_store.load(&source)?;
+    let count = src_items.len();
+
+    if !src_items.is_empty() {
+        src_items.iter_mut().for_each(|item| {
+            item.record(ItemEvent::Moved {
+                from: source.clone(),
+                to: dest.clone(),
+            })
+        });
+        let mut all_items = data_store.load(&dest)?;
+        match data_store.load_meta(&dest)?.mode {
+            StackMode::Stack => all_items.extend(src_items),
+            // The top item is placed first, so it stays ahead of the rest.
+            mode => src_items
+                .into_iter()
+                .rev()
+                .for_each(|item| place_item(&mut all_items, item, mode)),
         }
-    }
-    output.log_always(vec!["empty"], vec![vec!["true"]]);
-}
 
-fn list_stacks(data_store: &DataStore, output: &OutputFormat) {
-    if let Ok(stacks) = data_store.list_stacks() {
-        let mut stacks = stacks;
-        stacks.sort();
-        let strs = stacks.iter().map(|stack| vec![stack.as_str()]).collect();
-        output.log_always(vec!["stack"], strs);
+        data_store.save(&dest, all_items)?;
+        data_store.save(&source, vec![])?;
     }
-}
 
-// ===== ListAll/Head/Tail =====
+    output.log(
+        vec!["action", "new-stack", "old-stack", "num-moved"],
+        vec![vec!["Move All", &dest, &source, &count.to_string()]],
+    );
+    Ok(())
+}
 
-struct ListRange {
+fn swap_latest_two_items(
     stack: String,
-    // Ignored if starting "from_end".
-    start: usize,
-    limit: Option<usize>,
-    from_end: bool,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    let mut items = data_store.load(&stack)?;
+    let visible = visible_indices(&items);
+
+    if visible.len() < 2 {
+        return Ok(());
+    }
+
+    items.swap(visible[0], visible[1]);
+    items[visible[0]].record(ItemEvent::Raised);
+
+    data_store.save(&stack, items)?;
+
+    if output.is_nonquiet_for_humans() {
+        list_n_latest_items(stack, 2, ItemFilter::default(), data_store, output)?;
+    }
+    Ok(())
+}
+
+fn rotate_latest_three_items(
+    stack: String,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    let mut items = data_store.load(&stack)?;
+    let visible = visible_indices(&items);
+
+    if visible.len() < 3 {
+        return swap_latest_two_items(stack, data_store, output);
+    }
+
+    // The second item becomes current, the third second, and the current third.
+    let (a, b, c) = (visible[0], visible[1], visible[2]);
+    items.swap(a, b);
+    items.swap(b, c);
+    // Raised in order from the bottom, so the new top is raised last.
+    items[b].record(ItemEvent::Raised);
+    items[a].record(ItemEvent::Raised);
+
+    data_store.save(&stack, items)?;
+
+    if output.is_nonquiet_for_humans() {
+        list_n_latest_items(stack, 3, ItemFilter::default(), data_store, output)?;
+    }
+    Ok(())
+}
+
+fn next_to_latest(stack: String, data_store: &dyn Storage, output: &OutputFormat) -> EffectResult {
+    let mut items = data_store.load(&stack)?;
+    let top = match visible_indices(&items).first() {
+        Some(top) => *top,
+        None => return Ok(()),
+    };
+    let mut to_the_back = items.remove(top);
+    to_the_back.record(ItemEvent::Lowered);
+    items.insert(0, to_the_back);
+
+    data_store.save(&stack, items)?;
+
+    if output.is_nonquiet_for_humans() {
+        peek_latest_item(stack, data_store, output)?;
+    }
+    Ok(())
 }
 
-fn list_range(range: ListRange, data_store: &DataStore, output: &OutputFormat) {
+fn peek_latest_item(
+    stack: String,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
     if let OutputFormat::Silent = output {
-        return;
+        return Ok(());
     }
 
-    if let Ok(items) = data_store.load(&range.stack) {
-        let limit = match range.limit {
-            Some(n) => n,
-            None => items.len(),
-        };
+    let items = data_store.load(&stack)?;
+    let top_item = visible_indices(&items).first().map(|i| &items[*i]);
 
-        let start = if range.from_end {
-            if limit <= items.len() {
-                items.len() - limit
+    let output_it = |it| output.log_always(ITEM_LABELS.to_vec(), it);
+
+    match top_item {
+        Some(item) => {
+            let mut line = item_line(Some(0), item, output, now());
+            line[0] = "Now".to_string();
+            let mut lines = vec![line];
+            if let OutputFormat::Human(NoiseLevel::Verbose) = output {
+                lines.extend(body_lines(item, "    "));
+            }
+            output_it(
+                lines
+                    .iter()
+                    .map(|line| line.iter().map(|s| s.as_str()).collect())
+                    .collect(),
+            )
+        }
+        None => {
+            if output.is_nonquiet_for_humans() {
+                output_it(vec![vec!["Now", "NOTHING"]])
             } else {
-                0
+                output_it(vec![])
             }
+        }
+    }
+    Ok(())
+}
+
+fn show_item(
+    stack: String,
+    index: ItemRef,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    if let OutputFormat::Silent = output {
+        return Ok(());
+    }
+
+    let items = data_store.load(&stack)?;
+    let i = match find_item(&stack, &items, &index)? {
+        Some(i) => i,
+        None => {
+            if output.is_nonquiet_for_humans() {
+                output.log(ITEM_LABELS.to_vec(), vec![vec!["Now", "NOTHING"]]);
+            }
+            return Ok(());
+        }
+    };
+    let position = visible_indices(&items).iter().position(|j| *j == i);
+    let item = &items[i];
+
+    let mut lines = vec![item_line(position, item, output, now())];
+    if let OutputFormat::Human(_) | OutputFormat::TerseText = output {
+        lines.extend(body_lines(item, ""));
+    }
+
+    // Get the lines into a "borrow" state (&str instead of String) to make log happy.
+    let lines = lines
+        .iter()
+        .map(|line| line.iter().map(|s| s.as_str()).collect())
+        .collect();
+
+    output.log_always(ITEM_LABELS.to_vec(), lines);
+    Ok(())
+}
+
+fn count_all_items(
+    stack: String,
+    filter: ItemFilter,
+    recursive: bool,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    if let OutputFormat::Silent = output {
+        return Ok(());
+    }
+
+    let stacks = match recursive {
+        true => stacks_under(&stack, data_store)?,
+        false => vec![stack],
+    };
+    let now = now();
+    let mut len = 0;
+    for stack in stacks {
+        len += data_store
+            .load(&stack)?
+            .iter()
+            .filter(|item| filter.matches(item, now))
+            .count();
+    }
+    let len = len.to_string();
+    output.log_always(vec!["items"], vec![vec![&len]]);
+    Ok(())
+}
+
+fn is_empty(stack: String, data_store: &dyn Storage, output: &OutputFormat) -> EffectResult {
+    let items = data_store.load(&stack)?;
+    if !visible_indices(&items).is_empty() {
+        output.log_always(vec!["empty"], vec![vec!["false"]]);
+        // Exit with a failure (nonzero status) when not empty.
+        // This helps people who do shell scripting do something like:
+        //     while ! sigi -t $stack is-empty ; do <ETC> ; done
+        // TODO: It would be better modeled as an error, if anyone uses as a lib this will surprise.
+        if let OutputFormat::TerseText = output {
+            return Ok(());
         } else {
-            range.start
+            std::process::exit(1);
+        }
+    }
+    output.log_always(vec!["empty"], vec![vec!["true"]]);
+    Ok(())
+}
+
+fn list_stacks(
+    archived: bool,
+    history: bool,
+    summary: Option<u32>,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    if let (OutputFormat::Human(NoiseLevel::Verbose), Some(location)) =
+        (output, data_store.location())
+    {
+        output.log(vec!["store"], vec![vec!["Store", &location]]);
+    }
+
+    // A summary already counts what's in each stack's history.
+    let history = history && summary.is_none();
+    let mut stacks = data_store
+        .list_stacks()?
+        .into_iter()
+        .filter(|stack| is_archived(stack) == archived)
+        .filter(|stack| history || !stack.ends_with(HISTORY_SUFFIX))
+        .collect::<Vec<_>>();
+    stacks.sort_by(|a, b| stack_order(a).cmp(&stack_order(b)));
+
+    if let Some(days) = summary {
+        return summarize_stacks(&stacks, days, data_store, output);
+    }
+
+    // People get a tree of namespaces; everything else gets full stack names.
+    let stacks = match output.is_nonquiet_for_humans() {
+        true => stack_tree(&stacks),
+        false => stacks,
+    };
+    let strs = stacks.iter().map(|stack| vec![stack.as_str()]).collect();
+    output.log_always(vec!["stack"], strs);
+    Ok(())
+}
+
+/// Sort by namespace, then by name, with each history right after the stack
+/// it belongs to.
+fn stack_order(stack: &str) -> (Vec<&str>, bool) {
+    match stack.strip_suffix(HISTORY_SUFFIX) {
+        Some(parent) => (parent.split(NAMESPACE_SEPARATOR).collect(), true),
+        None => (stack.split(NAMESPACE_SEPARATOR).collect(), false),
+    }
+}
+
+/// Lay out sorted stack names as an indented tree, with each namespace on a
+/// line of its own ending in the separator, and each history under its stack.
+fn stack_tree(stacks: &[String]) -> Vec<String> {
+    let mut lines = vec![];
+    let mut previous: Vec<&str> = vec![];
+    for stack in stacks {
+        let (parts, history) = stack_order(stack);
+        let shared = parts
+            .iter()
+            .zip(&previous)
+            .take_while(|(part, prev)| part == prev)
+            .count();
+        for (depth, part) in parts.iter().enumerate().skip(shared) {
+            let indent = "  ".repeat(depth);
+            match depth + 1 == parts.len() {
+                true => lines.push(format!("{}{}", indent, part)),
+                false => lines.push(format!("{}{}{}", indent, part, NAMESPACE_SEPARATOR)),
+            }
+        }
+        if let (true, Some(last)) = (history, parts.last()) {
+            let indent = "  ".repeat(parts.len());
+            lines.push(format!("{}{}{}", indent, last, HISTORY_SUFFIX));
+        }
+        previous = parts;
+    }
+    lines
+}
+
+/// Print a line about each stack: how many items it has, the top one, when the
+/// newest and oldest were created, and how many were completed lately.
+fn summarize_stacks(
+    stacks: &[String],
+    days: u32,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    if let OutputFormat::Silent = output {
+        return Ok(());
+    }
+
+    let since =
+        days_ago(days.into()).ok_or_else(|| too_far_back("summary", format!("{} days", days)))?;
+    let mut lines = vec![];
+    for stack in stacks {
+        let items = data_store.load(stack)?;
+        let visible = visible_indices(&items);
+        let top = visible.first().map(|i| items[*i].contents.clone());
+        let created = items.iter().filter_map(|item| item.created());
+        let (newest, oldest) = (created.clone().max(), created.min());
+        let completed = data_store
+            .load(&stack_history_of(stack))?
+            .iter()
+            .filter(|item| {
+                item.history
+                    .iter()
+                    .any(|entry| entry.event == ItemEvent::Completed && entry.time >= since)
+            })
+            .count();
+
+        let line = match output {
+            OutputFormat::Human(_) | OutputFormat::TerseText => {
+                let mut summary = vec![match visible.len() {
+                    1 => "1 item".to_string(),
+                    n => format!("{} items", n),
+                }];
+                summary.extend(top.map(|top| format!("now: {}", top)));
+                summary.extend(newest.map(|dt| format!("newest {}", output.format_time(dt))));
+                summary.extend(oldest.map(|dt| format!("oldest {}", output.format_time(dt))));
+                summary.push(format!("{} completed in {} days", completed, days));
+                vec![stack.clone(), summary.join(", ")]
+            }
+            _ => vec![
+                stack.clone(),
+                visible.len().to_string(),
+                top.unwrap_or_default(),
+                newest.map(|dt| output.format_time(dt)).unwrap_or_default(),
+                oldest.map(|dt| output.format_time(dt)).unwrap_or_default(),
+                completed.to_string(),
+            ],
         };
+        lines.push(line);
+    }
 
-        let lines = items
-            .into_iter()
-            .rev()
-            .enumerate()
-            .skip(start)
-            .take(limit)
-            .map(|(i, item)| {
-                // Pad human output numbers to line up nicely with "Now".
-                let position = if output.is_nonquiet_for_humans() {
-                    match i {
-                        0 => "Now".to_string(),
-                        1..=9 => format!("  {}", i),
-                        10..=99 => format!(" {}", i),
-                        _ => i.to_string(),
-                    }
-                } else {
-                    i.to_string()
+    let labels = vec!["stack", "items", "top", "newest", "oldest", "completed"];
+
+    // Get the lines into a "borrow" state (&str instead of String) to make log happy.
+    let lines = lines
+        .iter()
+        .map(|line| line.iter().map(|s| s.as_str()).collect())
+        .collect();
+
+    output.log_always(labels, lines);
+    Ok(())
+}
+
+/// Whether a stack is the given one, or in its namespace.
+fn is_in_namespace(stack: &str, namespace: &str) -> bool {
+    stack
+        .strip_prefix(namespace)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(NAMESPACE_SEPARATOR))
+}
+
+/// Whether a stack is one of those chosen, or in one of their namespaces.
+/// Choosing none means every stack that isn't archived.
+fn is_chosen(stack: &str, chosen: &[String]) -> bool {
+    match chosen.is_empty() {
+        true => !is_archived(stack),
+        false => chosen
+            .iter()
+            .any(|namespace| is_in_namespace(stack, namespace)),
+    }
+}
+
+/// A stack and the stacks in its namespace, leaving out histories.
+fn stacks_under(stack: &str, data_store: &dyn Storage) -> Result<Vec<String>, SigiError> {
+    let namespace = format!("{}{}", stack, NAMESPACE_SEPARATOR);
+    let mut stacks = data_store
+        .list_stacks()?
+        .into_iter()
+        .filter(|name| name.starts_with(&namespace) && !name.ends_with(HISTORY_SUFFIX))
+        .collect::<Vec<_>>();
+    stacks.sort_by(|a, b| {
+        a.split(NAMESPACE_SEPARATOR)
+            .cmp(b.split(NAMESPACE_SEPARATOR))
+    });
+    stacks.insert(0, stack.to_string());
+    Ok(stacks)
+}
+
+fn list_overdue(data_store: &dyn Storage, output: &OutputFormat) -> EffectResult {
+    if let OutputFormat::Silent = output {
+        return Ok(());
+    }
+
+    let now = now();
+    let mut overdue = vec![];
+    for stack in data_store.list_stacks()? {
+        if stack.ends_with(HISTORY_SUFFIX) || is_archived(&stack) {
+            continue;
+        }
+        let items = data_store.load(&stack)?;
+        let positions = visible_indices(&items);
+        for (i, item) in items.iter().enumerate().rev() {
+            if item.is_overdue(now) {
+                let position = positions.iter().position(|p| *p == i);
+                overdue.push((stack.clone(), position, item.clone()));
+            }
+        }
+    }
+    overdue.sort_by_key(|(_, _, item)| item.due);
+
+    let labels = vec!["stack", "item", "position", "due", "id"];
+
+    if overdue.is_empty() {
+        if output.is_nonquiet_for_humans() {
+            output.log(labels, vec![vec!["Overdue", "NOTHING"]]);
+        }
+        return Ok(());
+    }
+
+    let lines = overdue
+        .into_iter()
+        .map(|(stack, position, item)| {
+            vec![
+                stack,
+                item.contents.clone(),
+                display_position(position, output),
+                display_due(&item, output),
+                display_id(&item, output),
+            ]
+        })
+        .collect::<Vec<_>>();
+
+    // Get the lines into a "borrow" state (&str instead of String) to make log happy.
+    let lines = lines
+        .iter()
+        .map(|line| line.iter().map(|s| s.as_str()).collect())
+        .collect();
+
+    output.log_always(labels, lines);
+    Ok(())
+}
+
+fn search_items(
+    query: SearchQuery,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    if let OutputFormat::Silent = output {
+        return Ok(());
+    }
+
+    let matcher = query.matcher()?;
+    let mut stacks = data_store
+        .list_stacks()?
+        .into_iter()
+        .filter(|stack| query.includes_stack(stack))
+        .collect::<Vec<_>>();
+    stacks.sort_by(|a, b| stack_order(a).cmp(&stack_order(b)));
+
+    let mut lines = vec![];
+    for stack in stacks {
+        let items = data_store.load(&stack)?;
+        let positions = visible_indices(&items);
+        for (i, item) in items.iter().enumerate().rev() {
+            if !query.matches(&matcher, item) {
+                continue;
+            }
+            let position = positions.iter().position(|p| *p == i);
+            let created = item
+                .created()
+                .map(|dt| output.format_time(dt))
+                .unwrap_or_else(|| "unknown".to_string());
+            lines.push(vec![
+                stack.clone(),
+                item.contents.clone(),
+                display_position(position, output),
+                created,
+                display_id(item, output),
+            ]);
+        }
+    }
+
+    let labels = vec!["stack", "item", "position", "created", "id"];
+
+    if lines.is_empty() {
+        if output.is_nonquiet_for_humans() {
+            output.log(labels, vec![vec!["Found", "NOTHING"]]);
+        }
+        return Ok(());
+    }
+
+    // Get the lines into a "borrow" state (&str instead of String) to make log happy.
+    let lines = lines
+        .iter()
+        .map(|line| line.iter().map(|s| s.as_str()).collect())
+        .collect();
+
+    output.log_always(labels, lines);
+    Ok(())
+}
+
+// ===== Report =====
+
+/// Something that happened to an item, for a report.
+struct ReportEntry {
+    day: NaiveDate,
+    /// The stack the item was on (or, for history, the stack it was from).
+    stack: String,
+    time: Timestamp,
+    action: &'static str,
+    contents: String,
+    id: String,
+}
+
+/// Print the items created, completed and deleted since a time, grouped by
+/// day and then by stack. People get headings; programs get every column.
+fn report(
+    since: Timestamp,
+    stacks: Vec<String>,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    if let OutputFormat::Silent = output {
+        return Ok(());
+    }
+
+    let now = now();
+    let mut entries = vec![];
+    for current in data_store.list_stacks()? {
+        for item in data_store.load(&current)? {
+            for entry in item.history.iter() {
+                if entry.time < since || entry.time > now {
+                    continue;
+                }
+                let action = match entry.event {
+                    ItemEvent::Created => "Created",
+                    ItemEvent::Completed => "Completed",
+                    ItemEvent::Deleted => "Deleted",
+                    _ => continue,
                 };
+                // Where the item was at the time, which may not be where it is now.
+                let stack = past_place(&item, &current, entry.time)
+                    .map(|place| place.stack)
+                    .unwrap_or_else(|| current.clone());
+                let stack = stack.strip_suffix(HISTORY_SUFFIX).unwrap_or(&stack);
+                if !is_chosen(stack, &stacks) {
+                    continue;
+                }
+                entries.push(ReportEntry {
+                    day: output.day_of(entry.time),
+                    stack: stack.to_string(),
+                    time: entry.time,
+                    action,
+                    contents: item.contents.clone(),
+                    id: display_id(&item, output),
+                });
+            }
+        }
+    }
+    entries.sort_by_cached_key(|entry| {
+        let stack = entry.stack.split(NAMESPACE_SEPARATOR).map(str::to_string);
+        (entry.day, stack.collect::<Vec<_>>(), entry.time)
+    });
 
-                let created = item
-                    .history
-                    .iter()
-                    .find(|(status, _)| status == "created")
-                    .map(|(_, dt)| output.format_time(*dt))
-                    .unwrap_or_else(|| "unknown".to_string());
+    let mut labels = vec!["day", "stack", "action", "item", "time", "id"];
 
-                vec![position, item.contents, created]
-            })
-            .collect::<Vec<_>>();
+    if entries.is_empty() {
+        if output.is_nonquiet_for_humans() {
+            output.log(labels, vec![vec!["Report", "NOTHING"]]);
+        }
+        return Ok(());
+    }
 
-        let labels = vec!["position", "item", "created"];
+    let mut lines = vec![];
+    let mut heading: Option<(NaiveDate, &str)> = None;
+    for entry in entries.iter() {
+        let time = output.format_time(entry.time);
+        let new_day = heading.is_none_or(|(day, _)| day != entry.day);
+        let new_stack = new_day || heading.is_some_and(|(_, stack)| stack != entry.stack);
+        heading = Some((entry.day, &entry.stack));
+        match output {
+            OutputFormat::Human(NoiseLevel::Quiet) | OutputFormat::TerseText => {
+                lines.push(vec![entry.action.to_string(), entry.contents.clone()]);
+            }
+            OutputFormat::Human(_) => {
+                if new_day {
+                    lines.push(vec![output.format_day(entry.day)]);
+                }
+                if new_stack {
+                    lines.push(vec![format!("  {}:", entry.stack)]);
+                }
+                lines.push(vec![
+                    format!("    {}", entry.action),
+                    entry.contents.clone(),
+                    time,
+                    entry.id.clone(),
+                ]);
+            }
+            OutputFormat::Markdown => {
+                if new_day {
+                    lines.push(vec![format!("## {}", output.format_day(entry.day))]);
+                }
+                if new_stack {
+                    lines.push(vec![format!("### {}", entry.stack)]);
+                }
+                lines.push(vec![
+                    entry.action.to_string(),
+                    entry.contents.clone(),
+                    time,
+                    entry.id.clone(),
+                ]);
+            }
+            _ => lines.push(vec![
+                output.format_day(entry.day),
+                entry.stack.clone(),
+                entry.action.to_string(),
+                entry.contents.clone(),
+                time,
+                entry.id.clone(),
+            ]),
+        }
+    }
+    // Markdown tables go under the day and stack headings.
+    if let OutputFormat::Markdown = output {
+        labels.drain(..2);
+    }
 
-        if lines.is_empty() {
-            if output.is_nonquiet_for_humans() {
-                output.log(labels, vec![vec!["Now", "NOTHING"]]);
+    // Get the lines into a "borrow" state (&str instead of String) to make log happy.
+    let lines = lines
+        .iter()
+        .map(|line| line.iter().map(|s| s.as_str()).collect())
+        .collect();
+
+    output.log_always(labels, lines);
+    Ok(())
+}
+
+// ===== Stats =====
+
+/// Numbers about the items of a stack, or of several stacks together.
+#[derive(Default)]
+struct StackStats {
+    open: usize,
+    completed: usize,
+    deleted: usize,
+    /// How many were completed each of the weeks being counted, latest first.
+    by_week: Vec<usize>,
+    /// How long each completed item took, from created to completed.
+    times_to_complete: Vec<chrono::Duration>,
+    /// When the oldest open items were created, and their headlines, oldest
+    /// first.
+    oldest: Vec<(Timestamp, String)>,
+}
+
+impl StackStats {
+    /// Count a stack's open items, and the completed and deleted ones in its
+    /// history. Items restored since count as open. Completed items are
+    /// counted by week for the weeks from `since`.
+    fn of(
+        stack: &str,
+        since: Timestamp,
+        weeks: u32,
+        n_oldest: usize,
+        data_store: &dyn Storage,
+    ) -> Result<Self, SigiError> {
+        let items = data_store.load(stack)?;
+        let mut stats = StackStats {
+            open: items.len(),
+            by_week: vec![0; weeks as usize],
+            ..StackStats::default()
+        };
+        stats.add_oldest(
+            items
+                .iter()
+                .filter_map(|item| Some((item.created()?, item.contents.clone())))
+                .collect(),
+            n_oldest,
+        );
+        for item in data_store.load(&stack_history_of(stack))? {
+            let outcome = item
+                .history
+                .iter()
+                .rev()
+                .find(|entry| matches!(entry.event, ItemEvent::Completed | ItemEvent::Deleted));
+            match outcome {
+                Some(entry) if entry.event == ItemEvent::Completed => {
+                    stats.completed += 1;
+                    if entry.time >= since {
+                        let weeks_after = (entry.time - since).num_weeks() as usize;
+                        let week = stats.by_week.len() - 1 - weeks_after.min(weeks as usize - 1);
+                        stats.by_week[week] += 1;
+                    }
+                    if let Some(created) = item.created() {
+                        stats.times_to_complete.push(entry.time - created);
+                    }
+                }
+                Some(_) => stats.deleted += 1,
+                None => (),
             }
-            return;
         }
+        Ok(stats)
+    }
 
-        // Get the lines into a "borrow" state (&str instead of String) to make log happy.
-        let lines = lines
+    fn add(&mut self, other: StackStats, n_oldest: usize) {
+        self.open += other.open;
+        self.completed += other.completed;
+        self.deleted += other.deleted;
+        self.by_week
+            .resize(self.by_week.len().max(other.by_week.len()), 0);
+        for (week, n) in other.by_week.into_iter().enumerate() {
+            self.by_week[week] += n;
+        }
+        self.times_to_complete.extend(other.times_to_complete);
+        self.add_oldest(other.oldest, n_oldest);
+    }
+
+    /// Keep the oldest of these items and the ones already kept.
+    fn add_oldest(&mut self, items: Vec<(Timestamp, String)>, n_oldest: usize) {
+        self.oldest.extend(items);
+        self.oldest.sort();
+        self.oldest.truncate(n_oldest);
+    }
+
+    /// The percent of finished items that were completed, not deleted.
+    fn completion_rate(&self) -> Option<u32> {
+        let finished = self.completed + self.deleted;
+        match finished {
+            0 => None,
+            _ => Some((100.0 * self.completed as f64 / finished as f64).round() as u32),
+        }
+    }
+
+    fn median_time_to_complete(&self) -> Option<chrono::Duration> {
+        let mut times = self.times_to_complete.clone();
+        times.sort();
+        let middle = times.len() / 2;
+        match times.len() {
+            0 => None,
+            n if n % 2 == 0 => Some((times[middle - 1] + times[middle]) / 2),
+            _ => Some(times[middle]),
+        }
+    }
+
+    /// Describe the numbers, with values for each of the `stats_labels`.
+    /// People get them in a sentence instead.
+    fn line(&self, name: &str, n_oldest: usize, output: &OutputFormat) -> Vec<String> {
+        let weeks = self.by_week.len();
+        let recently_completed = self.by_week.iter().sum::<usize>();
+        let per_week = format!("{:.1}", recently_completed as f64 / weeks as f64);
+        let by_week = self
+            .by_week
             .iter()
-            .map(|line| line.iter().map(|s| s.as_str()).collect())
-            .collect();
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>();
+        let median = self.median_time_to_complete();
+        match output {
+            OutputFormat::Human(_) | OutputFormat::TerseText => {
+                let mut finished =
+                    format!("{} completed, {} deleted", self.completed, self.deleted);
+                if let Some(rate) = self.completion_rate() {
+                    finished.push_str(&format!(" ({}% completed)", rate));
+                }
+                let mut summary = vec![format!("{} open", self.open), finished];
+                summary.push(match weeks {
+                    1 => format!("{} completed in the last week", recently_completed),
+                    _ => format!(
+                        "{} completed a week over {} weeks (latest first: {})",
+                        per_week,
+                        weeks,
+                        by_week.join(", ")
+                    ),
+                });
+                summary.extend(median.map(|median| {
+                    format!("median {} to complete", output.format_duration(median))
+                }));
+                if !self.oldest.is_empty() {
+                    let oldest = self
+                        .oldest
+                        .iter()
+                        .map(|(created, contents)| {
+                            format!("{} ({})", contents, output.format_time(*created))
+                        })
+                        .collect::<Vec<_>>();
+                    summary.push(format!("oldest: {}", oldest.join(", ")));
+                }
+                vec![name.to_string(), summary.join(", ")]
+            }
+            _ => {
+                let mut line = vec![
+                    name.to_string(),
+                    self.open.to_string(),
+                    self.completed.to_string(),
+                    self.deleted.to_string(),
+                    self.completion_rate()
+                        .map(|rate| rate.to_string())
+                        .unwrap_or_default(),
+                    per_week,
+                    by_week.join(" "),
+                    median
+                        .map(|median| output.format_duration(median))
+                        .unwrap_or_default(),
+                ];
+                for i in 0..n_oldest {
+                    let oldest = self.oldest.get(i);
+                    let (created, contents) = match oldest {
+                        Some((created, contents)) => {
+                            (output.format_time(*created), contents.clone())
+                        }
+                        None => (String::new(), String::new()),
+                    };
+                    line.push(contents);
+                    line.push(created);
+                }
+                line
+            }
+        }
+    }
+}
+
+/// The labels of the stats of a stack, with a headline and a created time
+/// for each of the oldest items.
+fn stats_labels(n_oldest: usize) -> Vec<String> {
+    let mut labels = [
+        "stack",
+        "open",
+        "completed",
+        "deleted",
+        "completion-rate",
+        "per-week",
+        "by-week",
+        "median-time-to-complete",
+    ]
+    .map(String::from)
+    .to_vec();
+    for i in 1..=n_oldest {
+        labels.push(format!("oldest-{}", i));
+        labels.push(format!("oldest-{}-created", i));
+    }
+    labels
+}
+
+/// Print numbers about each stack, from the stack and its history, and
+/// about all of them together.
+fn show_stats(
+    stacks: Vec<String>,
+    weeks: u32,
+    n_oldest: usize,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    if let OutputFormat::Silent = output {
+        return Ok(());
+    }
+
+    let since = days_ago(i64::from(weeks) * 7)
+        .ok_or_else(|| too_far_back("number of weeks", format!("{} weeks", weeks)))?;
+    // Stacks whose items have all been completed may only have a history.
+    let mut names = data_store
+        .list_stacks()?
+        .into_iter()
+        .map(|stack| match stack.strip_suffix(HISTORY_SUFFIX) {
+            Some(parent) => parent.to_string(),
+            None => stack,
+        })
+        .filter(|stack| is_chosen(stack, &stacks))
+        .collect::<Vec<_>>();
+    names.sort_by(|a, b| {
+        a.split(NAMESPACE_SEPARATOR)
+            .cmp(b.split(NAMESPACE_SEPARATOR))
+    });
+    names.dedup();
+
+    let labels = stats_labels(n_oldest);
+    let labels = labels.iter().map(String::as_str).collect::<Vec<_>>();
+
+    if names.is_empty() {
+        if output.is_nonquiet_for_humans() {
+            output.log(labels, vec![vec!["Stats", "NOTHING"]]);
+        }
+        return Ok(());
+    }
+
+    let mut lines = vec![];
+    let mut total = StackStats::default();
+    for stack in names.iter() {
+        let stats = StackStats::of(stack, since, weeks, n_oldest, data_store)?;
+        lines.push(stats.line(stack, n_oldest, output));
+        total.add(stats, n_oldest);
+    }
+    // Programs get "*" for all the stacks together.
+    let name = match output.is_programmatic() {
+        true => "*",
+        false => "Total",
+    };
+    lines.push(total.line(name, n_oldest, output));
+
+    // Get the lines into a "borrow" state (&str instead of String) to make log happy.
+    let lines = lines
+        .iter()
+        .map(|line| line.iter().map(|s| s.as_str()).collect())
+        .collect();
 
-        output.log_always(labels, lines);
+    output.log_always(labels, lines);
+    Ok(())
+}
+
+/// The error for looking further back than times go.
+fn too_far_back(setting: &str, amount: String) -> SigiError {
+    SigiError::InvalidSetting {
+        setting: setting.to_string(),
+        details: format!("{} is further back than sigi can go", amount),
     }
 }
 
-fn list_all_items(stack: String, data_store: &DataStore, output: &OutputFormat) {
+// ===== Compact =====
+
+/// Trim history stacks down to what a retention policy keeps. The rest are
+/// archived, in a stack for each month like `_archive/work_history/2024-06`,
+/// or dropped.
+fn compact_history(
+    stack: String,
+    all: bool,
+    policy: RetentionPolicy,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    if policy.keep.is_none() && policy.keep_days.is_none() {
+        return Err(SigiError::InvalidSetting {
+            setting: "retention policy".to_string(),
+            details: "nothing says what to keep. Give --keep or --keep-days, \
+                      or set keep or keep-days under [retention] in the config file"
+                .to_string(),
+        });
+    }
+
+    let histories = match all {
+        true => {
+            let mut histories = data_store
+                .list_stacks()?
+                .into_iter()
+                .filter(|stack| stack.ends_with(HISTORY_SUFFIX) && !is_archived(stack))
+                .collect::<Vec<_>>();
+            histories.sort_by(|a, b| {
+                a.split(NAMESPACE_SEPARATOR)
+                    .cmp(b.split(NAMESPACE_SEPARATOR))
+            });
+            histories
+        }
+        false => vec![stack_history_of(&stack)],
+    };
+
+    let mut lines = vec![];
+    for history in histories {
+        let (n_kept, removed) = apply_retention(&history, &policy, data_store)?;
+        let (archived, dropped) = match policy.archive {
+            true => (removed, 0),
+            false => (0, removed),
+        };
+        lines.push(match output {
+            OutputFormat::Human(_) | OutputFormat::TerseText => {
+                let gone = match policy.archive {
+                    true => format!("{} archived", archived),
+                    false => format!("{} dropped", dropped),
+                };
+                vec![history, format!("{} kept, {}", n_kept, gone)]
+            }
+            _ => vec![
+                history,
+                n_kept.to_string(),
+                archived.to_string(),
+                dropped.to_string(),
+            ],
+        });
+    }
+
+    let labels = vec!["stack", "kept", "archived", "dropped"];
+
+    if lines.is_empty() {
+        output.log(labels, vec![vec!["Compacted", "NOTHING"]]);
+        return Ok(());
+    }
+
+    // Get the lines into a "borrow" state (&str instead of String) to make log happy.
+    let lines = lines
+        .iter()
+        .map(|line| line.iter().map(|s| s.as_str()).collect())
+        .collect();
+
+    output.log(labels, lines);
+    Ok(())
+}
+
+/// Trim a history stack down to what the policy keeps, archiving or dropping
+/// the rest. Gives how many items were kept and how many removed.
+fn apply_retention(
+    history: &str,
+    policy: &RetentionPolicy,
+    data_store: &dyn Storage,
+) -> Result<(usize, usize), SigiError> {
+    let cutoff = policy
+        .keep_days
+        .map(|days| {
+            days_ago(days.into()).ok_or_else(|| too_far_back("keep-days", format!("{} days", days)))
+        })
+        .transpose()?;
+    let items = data_store.load(history)?;
+    // Histories are oldest first, so the items to keep are at the end.
+    let first_kept = policy
+        .keep
+        .map_or(0, |keep| items.len().saturating_sub(keep));
+    let (mut kept, mut old) = (vec![], BTreeMap::<String, Stack>::new());
+    for (i, item) in items.into_iter().enumerate() {
+        let finished = finished_time(&item);
+        let too_old = cutoff.zip(finished).is_some_and(|(c, f)| f < c);
+        if i < first_kept || too_old {
+            let month = finished.map_or("undated".to_string(), |f| f.format("%Y-%m").to_string());
+            old.entry(month).or_default().push(item);
+        } else {
+            kept.push(item);
+        }
+    }
+
+    let (n_kept, removed) = (kept.len(), old.values().map(Vec::len).sum::<usize>());
+    if removed > 0 {
+        if policy.archive {
+            for (month, items) in old {
+                let dest =
+                    [ARCHIVE_NAMESPACE, history, &month].join(&NAMESPACE_SEPARATOR.to_string());
+                let mut archived = data_store.load(&dest)?;
+                archived.extend(items);
+                data_store.save(&dest, archived)?;
+            }
+        }
+        data_store.save(history, kept)?;
+    }
+    Ok((n_kept, removed))
+}
+
+/// When an item was last completed or deleted, or else created.
+fn finished_time(item: &Item) -> Option<Timestamp> {
+    item.history
+        .iter()
+        .rev()
+        .find(|entry| matches!(entry.event, ItemEvent::Completed | ItemEvent::Deleted))
+        .map(|entry| entry.time)
+        .or_else(|| item.created())
+}
+
+// ===== Stack modes =====
+
+/// Print a stack's mode, or change it. Changing to queue or priority puts the
+/// items in that mode's order; changing to stack leaves them as they are.
+fn stack_mode(
+    stack: String,
+    mode: Option<StackMode>,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    let mut meta = data_store.load_meta(&stack)?;
+    let labels = vec!["action", "mode", "stack"];
+
+    let mode = match mode {
+        Some(mode) => mode,
+        None => {
+            output.log_always(labels, vec![vec!["Mode", meta.mode.name(), &stack]]);
+            return Ok(());
+        }
+    };
+
+    let mut items = data_store.load(&stack)?;
+    match mode {
+        StackMode::Stack => (),
+        // Oldest on top, so the newest is at the bottom.
+        StackMode::Queue => items.sort_by_key(|item| Reverse(item.created())),
+        StackMode::Priority => {
+            items.sort_by_key(|item| Reverse((priority_rank(item), item.created())))
+        }
+    }
+    data_store.save(&stack, items)?;
+    meta.mode = mode;
+    data_store.save_meta(&stack, meta)?;
+
+    output.log(labels, vec![vec!["Mode", mode.name(), &stack]]);
+    if output.is_nonquiet_for_humans() {
+        peek_latest_item(stack, data_store, output)?;
+    }
+    Ok(())
+}
+
+/// Put an item into a stack where the stack's mode says it goes: on top of a
+/// stack, at the bottom of a queue, or below the items of a priority stack
+/// that go before it.
+fn place_item(items: &mut Stack, mut item: Item, mode: StackMode) {
+    match mode {
+        StackMode::Stack => items.push(item),
+        StackMode::Queue => {
+            item.record(ItemEvent::Lowered);
+            items.insert(0, item);
+        }
+        StackMode::Priority => {
+            let rank = priority_rank(&item);
+            let i = items
+                .iter()
+                .position(|other| priority_rank(other) <= rank)
+                .unwrap_or(items.len());
+            items.insert(i, item);
+        }
+    }
+}
+
+/// How soon an item comes up in a priority stack, lowest first. Items
+/// without a priority come up last.
+fn priority_rank(item: &Item) -> (bool, Option<u32>) {
+    (item.priority.is_none(), item.priority)
+}
+
+// ===== Whole stacks =====
+
+fn rename_stack(
+    stack: String,
+    dest: String,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    transfer_stack(&stack, &dest, data_store)?;
+    output.log(
+        vec!["action", "new-stack", "old-stack"],
+        vec![vec!["Renamed", &dest, &stack]],
+    );
+    Ok(())
+}
+
+fn copy_stack(
+    stack: String,
+    dest: String,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    if !data_store.list_stacks()?.contains(&stack) {
+        return Err(SigiError::UnknownStack { stack });
+    }
+    if is_taken(&dest, data_store)? {
+        return Err(SigiError::StackExists { stack: dest });
+    }
+
+    // Only the items are copied; what happened to the stack before stays with it.
+    let items = data_store
+        .load(&stack)?
+        .iter()
+        .map(|item| item.copy(&stack, &dest))
+        .collect::<Stack>();
+    let count = items.len().to_string();
+    data_store.save(&dest, items)?;
+    data_store.save_meta(&dest, data_store.load_meta(&stack)?)?;
+
+    output.log(
+        vec!["action", "new-stack", "old-stack", "num-copied"],
+        vec![vec!["Copied", &dest, &stack, &count]],
+    );
+    Ok(())
+}
+
+fn delete_stack(stack: String, data_store: &dyn Storage, output: &OutputFormat) -> EffectResult {
+    let stacks = data_store.list_stacks()?;
+    let history = stack_history_of(&stack);
+    if !stacks.contains(&stack) && !stacks.contains(&history) {
+        return Err(SigiError::UnknownStack { stack });
+    }
+
+    let count = data_store.load(&stack)?.len().to_string();
+    data_store.delete(&stack)?;
+    data_store.delete(&history)?;
+    data_store.save_meta(&stack, StackMeta::default())?;
+
+    output.log(
+        vec!["action", "stack", "num-deleted"],
+        vec![vec!["Deleted stack", &stack, &count]],
+    );
+    Ok(())
+}
+
+fn archive_stack(stack: String, data_store: &dyn Storage, output: &OutputFormat) -> EffectResult {
+    let dest = format!("{}{}{}", ARCHIVE_NAMESPACE, NAMESPACE_SEPARATOR, stack);
+    transfer_stack(&stack, &dest, data_store)?;
+    output.log(
+        vec!["action", "stack", "archived-as"],
+        vec![vec!["Archived", &stack, &dest]],
+    );
+    Ok(())
+}
+
+/// Give a stack, and its history, a new name that isn't already taken.
+fn transfer_stack(stack: &str, dest: &str, data_store: &dyn Storage) -> EffectResult {
+    let stacks = data_store.list_stacks()?;
+    let exists = |name: &str| stacks.iter().any(|s| s == name);
+    let (history, dest_history) = (stack_history_of(stack), stack_history_of(dest));
+    if !exists(stack) {
+        return Err(SigiError::UnknownStack {
+            stack: stack.to_string(),
+        });
+    }
+    for name in [dest, &dest_history] {
+        if is_taken(name, data_store)? {
+            return Err(SigiError::StackExists {
+                stack: name.to_string(),
+            });
+        }
+    }
+
+    for (from, to) in [(stack, dest), (&history, &dest_history)] {
+        if exists(from) {
+            data_store.save(to, data_store.load(from)?)?;
+            data_store.delete(from)?;
+        }
+    }
+    data_store.save_meta(dest, data_store.load_meta(stack)?)?;
+    data_store.save_meta(stack, StackMeta::default())?;
+    Ok(())
+}
+
+/// Whether a stack name is in use. Empty stacks, like those left behind by
+/// undoing a rename, don't count.
+fn is_taken(stack: &str, data_store: &dyn Storage) -> Result<bool, SigiError> {
+    Ok(!data_store.load(stack)?.is_empty())
+}
+
+// ===== Undo/Redo =====
+
+fn undo_latest(data_store: &dyn Storage, output: &OutputFormat) -> EffectResult {
+    let journal = data_store.load_journal()?;
+    let labels = vec!["action", "effect", "stacks"];
+
+    match journal.undo.last() {
+        Some(entry) => {
+            entry.revert(data_store)?;
+            output.log(
+                labels,
+                vec![vec!["Undone", &entry.action, &entry.stacks().join(" ")]],
+            );
+            data_store.change_journal(JournalChange::Undo)
+        }
+        None => {
+            output.log(labels, vec![vec!["Undone", "NOTHING"]]);
+            Ok(())
+        }
+    }
+}
+
+fn redo_latest(data_store: &dyn Storage, output: &OutputFormat) -> EffectResult {
+    let journal = data_store.load_journal()?;
+    let labels = vec!["action", "effect", "stacks"];
+
+    match journal.redo.last() {
+        Some(entry) => {
+            entry.reapply(data_store)?;
+            output.log(
+                labels,
+                vec![vec!["Redone", &entry.action, &entry.stacks().join(" ")]],
+            );
+            data_store.change_journal(JournalChange::Redo)
+        }
+        None => {
+            output.log(labels, vec![vec!["Redone", "NOTHING"]]);
+            Ok(())
+        }
+    }
+}
+
+// ===== ListAll/Head/Tail =====
+
+struct ListRange {
+    stack: String,
+    // Ignored if starting "from_end".
+    start: usize,
+    limit: Option<usize>,
+    from_end: bool,
+    filter: ItemFilter,
+    /// List the stack as it was at this time (see `stack_as_of`).
+    as_of: Option<Timestamp>,
+}
+
+fn list_range(range: ListRange, data_store: &dyn Storage, output: &OutputFormat) -> EffectResult {
+    if let OutputFormat::Silent = output {
+        return Ok(());
+    }
+
+    let lines = range_lines(&range, data_store, output)?;
+    let labels = ITEM_LABELS.to_vec();
+
+    if lines.is_empty() {
+        if output.is_nonquiet_for_humans() {
+            output.log(labels, vec![vec!["Now", "NOTHING"]]);
+        }
+        return Ok(());
+    }
+
+    // Get the lines into a "borrow" state (&str instead of String) to make log happy.
+    let lines = lines
+        .iter()
+        .map(|line| line.iter().map(|s| s.as_str()).collect())
+        .collect();
+
+    output.log_always(labels, lines);
+    Ok(())
+}
+
+/// The lines describing the items in a range of a stack.
+fn range_lines(
+    range: &ListRange,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> Result<Vec<Vec<String>>, SigiError> {
+    // Items keep their stack positions, even when others are filtered out.
+    // Deferred items have no position until they come back.
+    let (now, items) = match range.as_of {
+        Some(as_of) => (as_of, stack_as_of(&range.stack, as_of, data_store)?),
+        None => (now(), data_store.load(&range.stack)?),
+    };
+    let mut next_position = 0..;
+    let items = items
+        .into_iter()
+        .rev()
+        .map(|item| match item.is_deferred(now) {
+            true => (None, item),
+            false => (next_position.next(), item),
+        })
+        .filter(|(_, item)| range.filter.matches(item, now))
+        .collect::<Vec<_>>();
+    let limit = match range.limit {
+        Some(n) => n,
+        None => items.len(),
+    };
+
+    let start = if range.from_end {
+        if limit <= items.len() {
+            items.len() - limit
+        } else {
+            0
+        }
+    } else {
+        range.start
+    };
+
+    let lines = items
+        .into_iter()
+        .skip(start)
+        .take(limit)
+        .flat_map(|(i, item)| {
+            let mut lines = vec![item_line(i, &item, output, now)];
+            // Only the verbose human output has room for bodies among items.
+            if let OutputFormat::Human(NoiseLevel::Verbose) = output {
+                lines.extend(body_lines(&item, "    "));
+            }
+            lines
+        })
+        .collect();
+    Ok(lines)
+}
+
+/// List the items of a stack and of every stack in its namespace. People get
+/// a heading for each stack; programmatic formats get a "stack" column.
+fn list_namespace(
+    stack: String,
+    filter: ItemFilter,
+    as_of: Option<Timestamp>,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
+    if let OutputFormat::Silent = output {
+        return Ok(());
+    }
+
+    let headings = output.is_nonquiet_for_humans();
+    let stack_column = output.is_programmatic();
+    let mut lines = vec![];
+    for stack in stacks_under(&stack, data_store)? {
+        let range = ListRange {
+            stack: stack.clone(),
+            start: 0,
+            limit: None,
+            from_end: false,
+            filter: filter.clone(),
+            as_of,
+        };
+        let stack_lines = range_lines(&range, data_store, output)?;
+        if stack_lines.is_empty() {
+            continue;
+        }
+        if headings {
+            lines.push(vec![format!("{}:", stack)]);
+        }
+        lines.extend(stack_lines.into_iter().map(|mut line| {
+            if stack_column {
+                line.insert(0, stack.clone());
+            }
+            line
+        }));
+    }
+
+    let mut labels = ITEM_LABELS.to_vec();
+    if stack_column {
+        labels.insert(0, "stack");
+    }
+
+    if lines.is_empty() {
+        if headings {
+            output.log(labels, vec![vec!["Now", "NOTHING"]]);
+        }
+        return Ok(());
+    }
+
+    // Get the lines into a "borrow" state (&str instead of String) to make log happy.
+    let lines = lines
+        .iter()
+        .map(|line| line.iter().map(|s| s.as_str()).collect())
+        .collect();
+
+    output.log_always(labels, lines);
+    Ok(())
+}
+
+fn list_all_items(
+    stack: String,
+    filter: ItemFilter,
+    as_of: Option<Timestamp>,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
     let range = ListRange {
         stack,
         start: 0,
         limit: None,
         from_end: false,
+        filter,
+        as_of,
     };
 
-    list_range(range, data_store, output);
+    list_range(range, data_store, output)
 }
 
-fn list_n_latest_items(stack: String, n: usize, data_store: &DataStore, output: &OutputFormat) {
+fn list_n_latest_items(
+    stack: String,
+    n: usize,
+    filter: ItemFilter,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
     let range = ListRange {
         stack,
         start: 0,
         limit: Some(n),
         from_end: false,
+        filter,
+        as_of: None,
     };
 
-    list_range(range, data_store, output);
+    list_range(range, data_store, output)
 }
 
-fn list_n_oldest_items(stack: String, n: usize, data_store: &DataStore, output: &OutputFormat) {
+fn list_n_oldest_items(
+    stack: String,
+    n: usize,
+    filter: ItemFilter,
+    data_store: &dyn Storage,
+    output: &OutputFormat,
+) -> EffectResult {
     let range = ListRange {
         stack,
         start: 0,
         limit: Some(n),
         from_end: true,
+        filter,
+        as_of: None,
+    };
+
+    list_range(range, data_store, output)
+}
+
+// ===== ListAll as of a past time =====
+
+/// Where an item was as of some time, and where in that stack.
+struct PastPlace {
+    stack: String,
+    /// Put at the bottom (by next) rather than on top.
+    lowered: bool,
+    /// When the item got there.
+    since: Timestamp,
+}
+
+/// Rebuild a stack as it was at a past time, from the events its items
+/// recorded. Items may have been moved to another stack since, so every
+/// stack (history stacks included) is read.
+fn stack_as_of(
+    stack: &str,
+    as_of: Timestamp,
+    data_store: &dyn Storage,
+) -> Result<Stack, SigiError> {
+    let mut past = vec![];
+    for current in data_store.list_stacks()? {
+        for item in data_store.load(&current)? {
+            if let Some(place) = past_place(&item, &current, as_of) {
+                if place.stack == stack {
+                    past.push((place, item_as_of(item, as_of)));
+                }
+            }
+        }
+    }
+
+    // Bottom first: the most recently lowered items, then the others in the
+    // order they got on top. Ties keep the order the stacks have now.
+    past.sort_by(|(a, _), (b, _)| match (a.lowered, b.lowered) {
+        (true, true) => b.since.cmp(&a.since),
+        (false, false) => a.since.cmp(&b.since),
+        (a_lowered, b_lowered) => b_lowered.cmp(&a_lowered),
+    });
+    Ok(past.into_iter().map(|(_, item)| item).collect())
+}
+
+/// Where an item, now in the `current` stack, was at a past time. `None` if
+/// it didn't exist yet.
+fn past_place(item: &Item, current: &str, as_of: Timestamp) -> Option<PastPlace> {
+    // A copy didn't exist before it was copied; until then, its history is
+    // that of the item it was copied from.
+    let copied = item
+        .history
+        .iter()
+        .rev()
+        .find_map(|entry| match entry.event {
+            ItemEvent::Copied { .. } => Some(entry.time),
+            _ => None,
+        });
+    if copied.is_some_and(|copied| copied > as_of) {
+        return None;
+    }
+
+    let parent = |stack: &str| {
+        stack
+            .strip_suffix(HISTORY_SUFFIX)
+            .unwrap_or(stack)
+            .to_string()
+    };
+    // Items start where they were first moved from, or else where they are now.
+    let origin = item
+        .history
+        .iter()
+        .find_map(|entry| match &entry.event {
+            ItemEvent::Moved { from, .. } => Some(parent(from)),
+            _ => None,
+        })
+        .unwrap_or_else(|| parent(current));
+    let arrived = |stack: String, since: Timestamp| PastPlace {
+        stack,
+        lowered: false,
+        since,
+    };
+
+    // Items from before created events were recorded have been around forever.
+    let mut place = match item.created() {
+        Some(_) => None,
+        None => item
+            .history
+            .first()
+            .filter(|entry| entry.time <= as_of)
+            .map(|entry| arrived(origin.clone(), entry.time)),
     };
+    for entry in item.history.iter().filter(|entry| entry.time <= as_of) {
+        let stack = place
+            .as_ref()
+            .map(|place| parent(&place.stack))
+            .unwrap_or_else(|| origin.clone());
+        place = match (&entry.event, place) {
+            (ItemEvent::Created, _) => Some(arrived(origin.clone(), entry.time)),
+            (ItemEvent::Moved { to, .. } | ItemEvent::Copied { to, .. }, _) => {
+                Some(arrived(to.clone(), entry.time))
+            }
+            (ItemEvent::Completed | ItemEvent::Deleted, _) => {
+                Some(arrived(stack_history_of(&stack), entry.time))
+            }
+            (ItemEvent::Restored, _) => Some(arrived(stack, entry.time)),
+            (ItemEvent::Raised, Some(place)) => Some(arrived(place.stack, entry.time)),
+            (ItemEvent::Lowered, Some(place)) => Some(PastPlace {
+                lowered: true,
+                since: entry.time,
+                ..place
+            }),
+            (_, place) => place,
+        };
+    }
+    place
+}
 
-    list_range(range, data_store, output);
+/// An item as it was at a past time, undoing the changes recorded since.
+fn item_as_of(mut item: Item, as_of: Timestamp) -> Item {
+    let later = match item.history.iter().position(|entry| entry.time > as_of) {
+        Some(i) => item.history.split_off(i),
+        None => vec![],
+    };
+    for entry in later.iter().rev() {
+        match &entry.event {
+            ItemEvent::Edited {
+                field: ItemField::Headline,
+                before,
+            } => item.contents = before.clone().unwrap_or_default(),
+            ItemEvent::Edited {
+                field: ItemField::Body,
+                before,
+            } => item.body = before.clone(),
+            ItemEvent::Tagged { tags } => item.tags.retain(|tag| !tags.contains(tag)),
+            ItemEvent::Untagged { tags } => item.tags.extend(tags.iter().cloned()),
+            _ => (),
+        }
+    }
+
+    item.hidden_until = item
+        .history
+        .iter()
+        .rev()
+        .find_map(|entry| match entry.event {
+            ItemEvent::Snoozed { until } => Some(until),
+            _ => None,
+        });
+    // Due dates set before due events were recorded are kept as they are.
+    let due_events = |history: &[HistoryEntry]| {
+        history.iter().rev().find_map(|entry| match entry.event {
+            ItemEvent::Due { due } => Some(due),
+            _ => None,
+        })
+    };
+    if let Some(due) = due_events(&item.history) {
+        item.due = due;
+    } else if due_events(&later).is_some() {
+        item.due = None;
+    }
+    item.priority = item
+        .history
+        .iter()
+        .rev()
+        .find_map(|entry| match entry.event {
+            ItemEvent::Prioritized { priority } => Some(priority),
+            _ => None,
+        })
+        .flatten();
+    item
 }
 
 // ===== Helper functions =====
 
+const ITEM_LABELS: [&str; 8] = [
+    "position", "item", "created", "id", "tags", "due", "priority", "body",
+];
+
+/// Describe an item, with values for each of `ITEM_LABELS`.
+fn item_line(
+    position: Option<usize>,
+    item: &Item,
+    output: &OutputFormat,
+    now: Timestamp,
+) -> Vec<String> {
+    let created = item
+        .created()
+        .map(|dt| output.format_time(dt))
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // People get the body on lines of its own instead (see `body_lines`).
+    let body = match output {
+        OutputFormat::Human(_) | OutputFormat::TerseText => String::new(),
+        _ => item.body.clone().unwrap_or_default(),
+    };
+
+    vec![
+        display_position(position, output),
+        display_contents(item, output, now),
+        created,
+        display_id(item, output),
+        display_tags(item, output),
+        display_due(item, output),
+        display_priority(item, output),
+        body,
+    ]
+}
+
+/// The lines of an item's body, for people to read after its headline.
+fn body_lines(item: &Item, indent: &str) -> Vec<Vec<String>> {
+    item.body
+        .iter()
+        .flat_map(|body| body.lines())
+        .map(|line| vec![format!("{}{}", indent, line)])
+        .collect()
+}
+
 fn stack_history_of(stack: &str) -> String {
     stack.to_string() + HISTORY_SUFFIX
 }
+
+/// Find the index into the items of the item a reference points at. Positions
+/// only count items that aren't deferred. Nothing to do on an empty stack is
+/// not an error, except when asking for an ID.
+fn find_item(stack: &str, items: &Stack, item: &ItemRef) -> Result<Option<usize>, SigiError> {
+    find_among(stack, items, &visible_indices(items), item)
+}
+
+/// Find an item like `find_item`, but with positions counting every item.
+fn find_any_item(stack: &str, items: &Stack, item: &ItemRef) -> Result<Option<usize>, SigiError> {
+    let all = (0..items.len()).rev().collect::<Vec<_>>();
+    find_among(stack, items, &all, item)
+}
+
+fn find_among(
+    stack: &str,
+    items: &Stack,
+    positions: &[usize],
+    item: &ItemRef,
+) -> Result<Option<usize>, SigiError> {
+    let prefix = match item {
+        ItemRef::Position(index) => return index_from_top(stack, positions, *index),
+        ItemRef::Id(prefix) => prefix,
+    };
+
+    let mut matches = items
+        .iter()
+        .enumerate()
+        .filter(|(_, item)| item.id.starts_with(prefix.as_str()))
+        .map(|(i, _)| i);
+
+    match (matches.next(), matches.count()) {
+        (Some(i), 0) => Ok(Some(i)),
+        (Some(_), others) => Err(SigiError::AmbiguousId {
+            stack: stack.to_string(),
+            id: prefix.to_string(),
+            matches: others + 1,
+        }),
+        (None, _) => Err(SigiError::UnknownId {
+            stack: stack.to_string(),
+            id: prefix.to_string(),
+        }),
+    }
+}
+
+/// The indices of the items that aren't deferred, from the top of the stack down.
+fn visible_indices(items: &Stack) -> Vec<usize> {
+    let now = now();
+    (0..items.len())
+        .rev()
+        .filter(|i| !items[*i].is_deferred(now))
+        .collect()
+}
+
+/// How to show an item's position. People get numbers padded to line up
+/// nicely with "Now", and deferred items have no position at all.
+fn display_position(position: Option<usize>, output: &OutputFormat) -> String {
+    if output.is_nonquiet_for_humans() {
+        match position {
+            None => "Later".to_string(),
+            Some(0) => "Now".to_string(),
+            Some(i @ 1..=9) => format!("  {}", i),
+            Some(i @ 10..=99) => format!(" {}", i),
+            Some(i) => i.to_string(),
+        }
+    } else {
+        match position {
+            None => "-".to_string(),
+            Some(i) => i.to_string(),
+        }
+    }
+}
+
+/// How to show an item's contents. People are told when an item is overdue.
+fn display_contents(item: &Item, output: &OutputFormat, now: Timestamp) -> String {
+    match output {
+        OutputFormat::Human(_) if item.is_overdue(now) => format!("{} [overdue]", item.contents),
+        _ => item.contents.clone(),
+    }
+}
+
+fn display_due(item: &Item, output: &OutputFormat) -> String {
+    match (item.due, output) {
+        (None, _) => String::new(),
+        (Some(due), OutputFormat::Human(_)) => format!("due {}", output.format_time(due)),
+        (Some(due), _) => output.format_time(due),
+    }
+}
+
+fn display_priority(item: &Item, output: &OutputFormat) -> String {
+    match (item.priority, output) {
+        (None, _) => String::new(),
+        (Some(priority), OutputFormat::Human(_)) => format!("priority {}", priority),
+        (Some(priority), _) => priority.to_string(),
+    }
+}
+
+/// How to show an item's ID. People get a short ID to type after an @, and
+/// programs get the whole thing.
+fn display_id(item: &Item, output: &OutputFormat) -> String {
+    match output {
+        OutputFormat::Human(_) => format!("@{}", item.short_id()),
+        _ => item.id.clone(),
+    }
+}
+
+/// How to show an item's tags. People see them the way they're typed.
+fn display_tags(item: &Item, output: &OutputFormat) -> String {
+    let prefix = match output {
+        OutputFormat::Human(_) => "+",
+        _ => "",
+    };
+    item.tags
+        .iter()
+        .map(|tag| format!("{}{}", prefix, tag))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Translate a position counted from the top of the stack into an index into
+/// the items, given the indices of the items that have positions from the top
+/// down. Nothing to do on an empty stack is not an error, but asking for an
+/// item that isn't there is.
+fn index_from_top(
+    stack: &str,
+    positions: &[usize],
+    index: usize,
+) -> Result<Option<usize>, SigiError> {
+    if positions.is_empty() {
+        Ok(None)
+    } else if index < positions.len() {
+        Ok(Some(positions[index]))
+    } else {
+        Err(SigiError::InvalidIndex {
+            stack: stack.to_string(),
+            index,
+        })
+    }
+}
@@ -0,0 +1,115 @@
+use std::{error, fmt, io};
+
+/// Everything that can go wrong while running a sigi effect.
+#[derive(Debug)]
+pub enum SigiError {
+    /// Reading or writing the data store failed.
+    Io { context: String, source: io::Error },
+    /// A stack's stored data could not be understood.
+    CorruptData { stack: String, details: String },
+    /// A stack that was expected to exist does not.
+    UnknownStack { stack: String },
+    /// A stack that was expected not to exist already does.
+    StackExists { stack: String },
+    /// No item exists at the requested position.
+    InvalidIndex { stack: String, index: usize },
+    /// No item in the stack has an ID starting with the given prefix.
+    UnknownId { stack: String, id: String },
+    /// More than one item in the stack has an ID starting with the given prefix.
+    AmbiguousId {
+        stack: String,
+        id: String,
+        matches: usize,
+    },
+    /// A stack changed since the journal entry being undone or redone.
+    JournalConflict { stack: String },
+    /// An item changed (or went) while it was open in an editor.
+    EditConflict { stack: String, id: String },
+    /// A setting (e.g. from the environment) has a value sigi doesn't understand.
+    InvalidSetting { setting: String, details: String },
+    /// A stack name that can't be used, like one with ".." in it.
+    InvalidStackName { stack: String, reason: String },
+    /// A search pattern that isn't a valid regular expression.
+    InvalidPattern { pattern: String, details: String },
+}
+
+impl SigiError {
+    /// The process exit code for this kind of error. These follow the BSD
+    /// sysexits convention so scripts can tell failures apart.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            SigiError::InvalidIndex { .. }
+            | SigiError::UnknownId { .. }
+            | SigiError::AmbiguousId { .. }
+            | SigiError::InvalidStackName { .. }
+            | SigiError::InvalidPattern { .. } => 64,
+            SigiError::CorruptData { .. } => 65,
+            SigiError::UnknownStack { .. } => 66,
+            SigiError::StackExists { .. } => 73,
+            SigiError::Io { .. } => 74,
+            SigiError::JournalConflict { .. } | SigiError::EditConflict { .. } => 75,
+            SigiError::InvalidSetting { .. } => 78,
+        }
+    }
+
+    pub(crate) fn io(context: impl Into<String>) -> impl FnOnce(io::Error) -> SigiError {
+        let context = context.into();
+        move |source| SigiError::Io { context, source }
+    }
+}
+
+impl error::Error for SigiError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            SigiError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for SigiError {
+    fn fmt(&self, out: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            SigiError::Io { context, source } => write!(out, "Unable to {}: {}", context, source),
+            SigiError::CorruptData { stack, details } => write!(
+                out,
+                "The data for stack {:?} is corrupt and could not be read: {}",
+                stack, details
+            ),
+            SigiError::UnknownStack { stack } => write!(out, "No stack named {:?} exists", stack),
+            SigiError::StackExists { stack } => {
+                write!(out, "A stack named {:?} already exists", stack)
+            }
+            SigiError::InvalidIndex { stack, index } => {
+                write!(out, "Stack {:?} has no item number {}", stack, index)
+            }
+            SigiError::UnknownId { stack, id } => {
+                write!(out, "Stack {:?} has no item with ID @{}", stack, id)
+            }
+            SigiError::AmbiguousId { stack, id, matches } => write!(
+                out,
+                "{} items in stack {:?} have an ID starting with @{}; use more of the ID",
+                matches, stack, id
+            ),
+            SigiError::JournalConflict { stack } => write!(
+                out,
+                "Stack {:?} has changed since then, so it can't be safely undone or redone",
+                stack
+            ),
+            SigiError::EditConflict { stack, id } => write!(
+                out,
+                "Item @{} in stack {:?} changed while it was being edited, so the edit wasn't saved",
+                id, stack
+            ),
+            SigiError::InvalidSetting { setting, details } => {
+                write!(out, "Invalid {}: {}", setting, details)
+            }
+            SigiError::InvalidStackName { stack, reason } => {
+                write!(out, "Can't use {:?} as a stack name: {}", stack, reason)
+            }
+            SigiError::InvalidPattern { pattern, details } => {
+                write!(out, "Can't search for {:?}: {}", pattern, details)
+            }
+        }
+    }
+}
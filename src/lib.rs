@@ -4,10 +4,11 @@
 //!
 //! - https://github.com/sigi-cli/sigi
 //!
-//! Its "database" is currently little more than json files, and handles only
+//! Its "database" is by default little more than json files, and handles only
 //! String values. It can work for research or small loads, but would be
-//! sluggish for anything that needs to care about performance. Other data
-//! stores like Redis and SQLite are planned.
+//! sluggish for anything that needs to care about performance. A SQLite data
+//! store is available for larger stacks, and other data stores like Redis are
+//! planned.
 //!
 //! Other internals are documented, but the project is early in development
 //! and should be considered **unstable** at best.
@@ -25,3 +26,6 @@ pub mod data;
 
 /// The printing implementation.
 pub mod output;
+
+/// The error type shared by effects and storage.
+pub mod error;
@@ -1,4 +1,12 @@
-use chrono::{DateTime, Local};
+use std::str::FromStr;
+
+use chrono::{Duration, NaiveDate, NaiveTime};
+
+use crate::data::Timestamp;
+
+mod time;
+use time::approximate;
+pub use time::{parse_locale, DisplayZone, TimeDisplay, TimeStyle};
 
 /// The general idea in this module is to take a table-ish output and render it in common formats.
 ///
@@ -23,7 +31,7 @@ use chrono::{DateTime, Local};
 ///     }
 /// ]
 /// ```
-
+///
 /// Output formats supported by Sigi.
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub enum OutputFormat {
@@ -35,6 +43,8 @@ pub enum OutputFormat {
     Json,
     /// JSON (JavaScript Object Notation) - No newlines or indentation.
     JsonCompact,
+    /// Markdown tables, for pasting into notes and documents.
+    Markdown,
     /// Print nothing at all.
     Silent,
     /// Print only on printing actions.
@@ -43,6 +53,29 @@ pub enum OutputFormat {
     Tsv,
 }
 
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    /// Read a format by the name of the flag that chooses it, like "verbose" or "json".
+    fn from_str(format: &str) -> Result<Self, Self::Err> {
+        match format.trim().to_ascii_lowercase().as_str() {
+            "csv" => Ok(OutputFormat::Csv),
+            "normal" => Ok(OutputFormat::Human(NoiseLevel::Normal)),
+            "quiet" => Ok(OutputFormat::Human(NoiseLevel::Quiet)),
+            "verbose" | "noisy" => Ok(OutputFormat::Human(NoiseLevel::Verbose)),
+            "json" => Ok(OutputFormat::Json),
+            "json-compact" => Ok(OutputFormat::JsonCompact),
+            "markdown" | "md" => Ok(OutputFormat::Markdown),
+            "silent" => Ok(OutputFormat::Silent),
+            "tsv" => Ok(OutputFormat::Tsv),
+            _ => Err(format!(
+                "{:?} isn't an output format sigi knows. Options include [normal, quiet, verbose, silent, csv, json, json-compact, markdown, tsv]",
+                format
+            )),
+        }
+    }
+}
+
 /// How much noise (verbosity) should be used when printing to standard output.
 #[derive(Clone, Copy, Eq, PartialEq)]
 pub enum NoiseLevel {
@@ -52,10 +85,61 @@ pub enum NoiseLevel {
 }
 
 impl OutputFormat {
-    pub fn format_time(&self, dt: DateTime<Local>) -> String {
-        // TODO: This should be configurable.
-        // TODO: Does this work for all locales?
-        dt.to_rfc2822()
+    /// Write a time as chosen for display (see `TimeDisplay`). Programmatic
+    /// formats always get RFC 3339 (ISO 8601) times, which tools can parse.
+    /// Markdown is read by people, so it gets their times.
+    pub fn format_time(&self, dt: Timestamp) -> String {
+        let display = TimeDisplay::current();
+        match self.is_read_by_people() {
+            true => display.format(dt),
+            false => TimeStyle::Iso.format(display.zone.convert(dt), display.locale),
+        }
+    }
+
+    /// Write a length of time. Programs get a number of seconds; people get
+    /// it in the largest unit that fits, like "3 days".
+    pub fn format_duration(&self, duration: Duration) -> String {
+        match self.is_read_by_people() {
+            true => approximate(duration).unwrap_or_else(|| "under a minute".to_string()),
+            false => duration.num_seconds().to_string(),
+        }
+    }
+
+    /// The day a time falls on, in the zone times are shown in.
+    pub fn day_of(&self, dt: Timestamp) -> NaiveDate {
+        TimeDisplay::current().zone.convert(dt).date_naive()
+    }
+
+    /// Write a day. Programs get an ISO 8601 date; people also get the name
+    /// of the day.
+    pub fn format_day(&self, day: NaiveDate) -> String {
+        match self.is_read_by_people() {
+            true => day
+                .and_time(NaiveTime::MIN)
+                .and_utc()
+                .format_localized("%A %Y-%m-%d", TimeDisplay::current().locale)
+                .to_string(),
+            false => day.format("%Y-%m-%d").to_string(),
+        }
+    }
+
+    /// Whether this is a table format with every column, for other programs
+    /// or documents (csv, json, markdown, tsv).
+    pub fn is_programmatic(&self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Csv
+                | OutputFormat::Json
+                | OutputFormat::JsonCompact
+                | OutputFormat::Markdown
+                | OutputFormat::Tsv
+        )
+    }
+
+    /// Whether times and days should be written for people. Markdown tables
+    /// are for documents people read.
+    fn is_read_by_people(&self) -> bool {
+        !self.is_programmatic() || *self == OutputFormat::Markdown
     }
 
     pub fn is_nonquiet_for_humans(&self) -> bool {
@@ -88,26 +172,30 @@ impl OutputFormat {
 
         match &self {
             OutputFormat::Csv => {
-                let print_csv = join_and_print(",");
+                let print_csv = join_and_print(",", escape_csv_field);
                 print_csv(labels);
                 values.into_iter().for_each(print_csv)
             }
             OutputFormat::Human(noise) => match noise {
                 NoiseLevel::Verbose => {
-                    values.into_iter().for_each(|line| match line.len() {
-                        0 => (),
-                        1 => println!("{}", line.first().unwrap()),
-                        2 => println!("{}: {}", line.first().unwrap(), line.get(1).unwrap()),
-                        _ => println!(
-                            "{}: {} ({})",
-                            line.first().unwrap(),
-                            line.get(1).unwrap(),
-                            line.iter()
-                                .skip(2)
-                                .map(|s| s.to_string())
-                                .collect::<Vec<_>>()
-                                .join(", ")
-                        ),
+                    values.into_iter().for_each(|line| {
+                        // Details that are blank for this line (e.g. no tags) are left out.
+                        let details = line
+                            .iter()
+                            .skip(2)
+                            .filter(|s| !s.is_empty())
+                            .map(|s| s.to_string())
+                            .collect::<Vec<_>>();
+                        match (line.first(), line.get(1)) {
+                            (None, _) => (),
+                            (Some(first), None) => println!("{}", first),
+                            (Some(first), Some(second)) if details.is_empty() => {
+                                println!("{}: {}", first, second)
+                            }
+                            (Some(first), Some(second)) => {
+                                println!("{}: {} ({})", first, second, details.join(", "))
+                            }
+                        }
                     });
                 }
                 NoiseLevel::Normal => {
@@ -148,6 +236,32 @@ impl OutputFormat {
 
                 println!("{}", json::stringify(objs));
             }
+            OutputFormat::Markdown => {
+                // A row of one value, like a heading, stands on its own when
+                // there are more labels. The other rows make up tables.
+                let print_row = |cells: &[&str]| {
+                    let cells = cells.iter().map(|cell| escape_markdown_cell(cell));
+                    println!("| {} |", cells.collect::<Vec<_>>().join(" | "))
+                };
+                let mut in_table = false;
+                for (i, line) in values.into_iter().enumerate() {
+                    let standalone = line.len() == 1 && labels.len() > 1;
+                    if i > 0 && (standalone || !in_table) {
+                        println!();
+                    }
+                    if standalone {
+                        println!("{}", line[0]);
+                        in_table = false;
+                        continue;
+                    }
+                    if !in_table {
+                        print_row(&labels);
+                        println!("|{}", " --- |".repeat(labels.len()));
+                        in_table = true;
+                    }
+                    print_row(&line);
+                }
+            }
             OutputFormat::Silent => {
                 unreachable!("[BUG] Sigi should always exit outputting before this point.")
             }
@@ -155,7 +269,7 @@ impl OutputFormat {
                 unreachable!("[BUG] Sigi should always exit outputting before this point.")
             }
             OutputFormat::Tsv => {
-                let print_tsv = join_and_print("\t");
+                let print_tsv = join_and_print("\t", escape_tsv_field);
                 print_tsv(labels);
                 values.into_iter().for_each(print_tsv)
             }
@@ -174,7 +288,35 @@ fn quiet_print(values: Vec<Vec<&str>>) {
     })
 }
 
-fn join_and_print(sep: &str) -> impl Fn(Vec<&str>) {
+/// Keep a value from breaking out of its table cell.
+fn escape_markdown_cell(cell: &str) -> String {
+    cell.replace('|', "\\|").replace('\n', "<br>")
+}
+
+/// Quote a CSV field that needs it, as RFC 4180 does: in double quotes, with
+/// any double quotes in it doubled.
+fn escape_csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Keep a TSV field on its line and in its column. Tabs and line breaks are
+/// written as `\t`, `\n` and `\r`, and backslashes as `\\`.
+fn escape_tsv_field(field: &str) -> String {
+    field
+        .replace('\\', "\\\\")
+        .replace('\t', "\\t")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+}
+
+fn join_and_print(sep: &str, escape: fn(&str) -> String) -> impl Fn(Vec<&str>) {
     let sep = sep.to_string();
-    move |tokens: Vec<&str>| println!("{}", tokens.join(&sep))
+    move |tokens: Vec<&str>| {
+        let fields = tokens.into_iter().map(escape).collect::<Vec<_>>();
+        println!("{}", fields.join(&sep))
+    }
 }
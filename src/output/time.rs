@@ -0,0 +1,215 @@
+//! How times are shown: in which zone, in which style, and in which locale.
+
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{
+    DateTime, Duration, FixedOffset, Local, LocalResult, Locale, NaiveDateTime, SecondsFormat,
+    TimeZone, Utc,
+};
+use chrono_tz::Tz;
+
+use crate::data::{now, Timestamp};
+
+/// How times are shown, chosen once at startup.
+static TIME_DISPLAY: OnceLock<TimeDisplay> = OnceLock::new();
+
+/// Everything about how times are shown to people.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TimeDisplay {
+    pub zone: DisplayZone,
+    pub style: TimeStyle,
+    /// Used for names of days and months, and the "locale" style.
+    pub locale: Locale,
+}
+
+impl TimeDisplay {
+    /// Show all times this way. Only the first choice is used.
+    pub fn use_for_display(self) {
+        let _ = TIME_DISPLAY.set(self);
+    }
+
+    /// How times are being shown.
+    pub fn current() -> &'static TimeDisplay {
+        TIME_DISPLAY.get_or_init(TimeDisplay::default)
+    }
+
+    /// The zone times people type are read in: the one times are shown in,
+    /// once it's been chosen.
+    pub fn input_zone() -> DisplayZone {
+        TIME_DISPLAY
+            .get()
+            .map(|display| display.zone)
+            .unwrap_or_default()
+    }
+
+    pub fn format(&self, dt: Timestamp) -> String {
+        self.style.format(self.zone.convert(dt), self.locale)
+    }
+}
+
+/// Which time zone times are shown in. Times are stored with the UTC offset
+/// they were recorded at, whatever zone they are shown in.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum DisplayZone {
+    /// This machine's time zone.
+    #[default]
+    Local,
+    /// The offset each time was recorded at.
+    Original,
+    Utc,
+    /// A fixed offset from UTC, like +05:30.
+    Offset(FixedOffset),
+    /// A named (IANA) time zone, like America/Chicago.
+    Named(Tz),
+}
+
+impl DisplayZone {
+    /// The same moment, at this zone's offset.
+    pub fn convert(&self, dt: Timestamp) -> Timestamp {
+        match self {
+            DisplayZone::Local => dt.with_timezone(&Local).fixed_offset(),
+            DisplayZone::Original => dt,
+            DisplayZone::Utc => dt.with_timezone(&Utc).fixed_offset(),
+            DisplayZone::Offset(offset) => dt.with_timezone(offset),
+            DisplayZone::Named(tz) => dt.with_timezone(tz).fixed_offset(),
+        }
+    }
+
+    /// The moment a clock in this zone shows this time, if it ever does.
+    /// Times recorded elsewhere have no one zone, so "original" reads them
+    /// in this machine's time zone.
+    pub fn from_wall_clock(&self, naive: NaiveDateTime) -> Option<Timestamp> {
+        match self {
+            DisplayZone::Local | DisplayZone::Original => fixed(Local.from_local_datetime(&naive)),
+            DisplayZone::Utc => fixed(Utc.from_local_datetime(&naive)),
+            DisplayZone::Offset(offset) => fixed(offset.from_local_datetime(&naive)),
+            DisplayZone::Named(tz) => fixed(tz.from_local_datetime(&naive)),
+        }
+    }
+}
+
+fn fixed<Z: TimeZone>(dt: LocalResult<DateTime<Z>>) -> Option<Timestamp> {
+    dt.earliest().map(|dt| dt.fixed_offset())
+}
+
+impl FromStr for DisplayZone {
+    type Err = String;
+
+    fn from_str(zone: &str) -> Result<Self, Self::Err> {
+        match zone.trim().to_ascii_lowercase().as_str() {
+            "local" => return Ok(DisplayZone::Local),
+            "original" => return Ok(DisplayZone::Original),
+            "utc" | "z" => return Ok(DisplayZone::Utc),
+            _ => (),
+        }
+        if let Ok(offset) = zone.trim().parse::<FixedOffset>() {
+            return Ok(DisplayZone::Offset(offset));
+        }
+        zone.trim().parse::<Tz>().map(DisplayZone::Named).map_err(|_| {
+            format!(
+                "{:?} isn't a time zone sigi knows. Try e.g. local, original, utc, +05:30 or America/Chicago",
+                zone
+            )
+        })
+    }
+}
+
+/// How times are written for people.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum TimeStyle {
+    /// Like "Sat, 1 Jun 2024 09:30:00 +0200".
+    #[default]
+    Rfc2822,
+    /// Like "2024-06-01T09:30:00+02:00".
+    Iso,
+    /// Like "3 days ago" or "in 2 hours".
+    Relative,
+    /// The locale's own way of writing dates and times.
+    Locale,
+    /// A strftime-style format, like "%Y-%m-%d %H:%M".
+    Custom(String),
+}
+
+impl TimeStyle {
+    pub fn format(&self, dt: Timestamp, locale: Locale) -> String {
+        match self {
+            TimeStyle::Rfc2822 => dt.to_rfc2822(),
+            TimeStyle::Iso => dt.to_rfc3339_opts(SecondsFormat::Secs, false),
+            TimeStyle::Relative => relative(dt, now()),
+            TimeStyle::Locale => dt.format_localized("%c", locale).to_string(),
+            TimeStyle::Custom(format) => dt.format_localized(format, locale).to_string(),
+        }
+    }
+}
+
+impl FromStr for TimeStyle {
+    type Err = String;
+
+    fn from_str(style: &str) -> Result<Self, Self::Err> {
+        match style.trim().to_ascii_lowercase().as_str() {
+            "rfc2822" => Ok(TimeStyle::Rfc2822),
+            "iso" | "iso8601" | "rfc3339" => Ok(TimeStyle::Iso),
+            "relative" => Ok(TimeStyle::Relative),
+            "locale" => Ok(TimeStyle::Locale),
+            _ if style.contains('%') => {
+                // Catch mistakes now; chrono can only report them by panicking later.
+                match StrftimeItems::new(style).any(|item| item == Item::Error) {
+                    true => Err(format!("{:?} has an unknown %-specifier", style)),
+                    false => Ok(TimeStyle::Custom(style.to_string())),
+                }
+            }
+            _ => Err(format!(
+                "{:?} isn't a time format sigi knows. Try e.g. rfc2822, iso, relative, locale or \"%Y-%m-%d %H:%M\"",
+                style
+            )),
+        }
+    }
+}
+
+/// Read a locale like "fr_FR", or one as given in LANG like "fr_FR.UTF-8".
+pub fn parse_locale(locale: &str) -> Result<Locale, String> {
+    let name = locale.split(['.', '@']).next().unwrap_or_default();
+    match name {
+        "C" | "POSIX" | "" => Ok(Locale::POSIX),
+        _ => name.parse().map_err(|_| {
+            format!(
+                "{:?} isn't a locale sigi knows. Try e.g. en_US or fr_FR",
+                locale
+            )
+        }),
+    }
+}
+
+/// Describe how long ago (or how far off) a time is, like "3 days ago".
+fn relative(dt: Timestamp, now: Timestamp) -> String {
+    let seconds = (dt - now).num_seconds();
+    match approximate(dt - now) {
+        None => "just now".to_string(),
+        Some(amount) if seconds < 0 => format!("{} ago", amount),
+        Some(amount) => format!("in {}", amount),
+    }
+}
+
+/// Describe a length of time in the largest unit that fits, like "3 days".
+/// `None` if it's under a minute. The sign is ignored.
+pub fn approximate(duration: Duration) -> Option<String> {
+    const UNITS: [(&str, i64); 6] = [
+        ("year", 365 * 24 * 60 * 60),
+        ("month", 30 * 24 * 60 * 60),
+        ("week", 7 * 24 * 60 * 60),
+        ("day", 24 * 60 * 60),
+        ("hour", 60 * 60),
+        ("minute", 60),
+    ];
+
+    let seconds = duration.num_seconds().abs();
+    UNITS
+        .iter()
+        .find(|(_, size)| seconds >= *size)
+        .map(|(unit, size)| match (seconds + size / 2) / size {
+            1 => format!("1 {}", unit),
+            n => format!("{} {}s", n, unit),
+        })
+}
@@ -0,0 +1,206 @@
+mod run_sigi;
+
+use run_sigi::{dir_with_files, fresh_dir, piping, sigi};
+
+/// A "work" stack with a few days of history, written out directly so the
+/// times are known.
+const STACKS: [(&str, &str); 3] = [
+    (
+        "work.json",
+        r#"[
+            {"id": "c1", "contents": "chores", "tags": ["home"], "history": [
+                {"event": "created", "time": "2024-06-01T07:00:00+00:00"},
+                {"event": "lowered", "time": "2024-06-01T07:30:00+00:00"},
+                {"event": "tagged", "tags": ["home"], "time": "2024-06-03T10:00:00+00:00"}
+            ]},
+            {"id": "f1", "contents": "fix the bug, properly", "history": [
+                {"event": "created", "time": "2024-06-02T10:00:00+00:00"},
+                {"event": "edited", "field": "headline", "before": "fix the bug",
+                 "time": "2024-06-04T10:00:00+00:00"}
+            ]}
+        ]"#,
+    ),
+    (
+        "work_history.json",
+        r#"[
+            {"id": "r1", "contents": "write report", "history": [
+                {"event": "created", "time": "2024-06-01T09:00:00+00:00"},
+                {"event": "completed", "time": "2024-06-03T17:00:00+00:00"}
+            ]}
+        ]"#,
+    ),
+    (
+        "later.json",
+        r#"[
+            {"id": "p1", "contents": "plan", "history": [
+                {"event": "created", "time": "2024-06-01T08:00:00+00:00"},
+                {"event": "moved", "from": "work", "to": "later",
+                 "time": "2024-06-03T12:00:00+00:00"}
+            ]}
+        ]"#,
+    ),
+];
+
+#[test]
+fn sigi_list_as_of_a_past_time() {
+    let dir = dir_with_files("sigi_integ_as_of_past", &STACKS);
+
+    let res = sigi("work", &["-d", &dir, "list"]);
+    res.assert_stdout_eq("Now: fix the bug, properly\n  1: chores\n");
+
+    // Completed and moved items are back, and the edit and tag are undone.
+    let res = sigi(
+        "work",
+        &["-d", &dir, "list", "--as-of", "2024-06-02T23:00:00+00:00"],
+    );
+    res.assert_success();
+    res.assert_stdout_eq("Now: fix the bug\n  1: write report\n  2: plan\n  3: chores\n");
+
+    let res = sigi(
+        "work",
+        &["-d", &dir, "list", "--as-of", "2024-06-01T08:30:00+00:00"],
+    );
+    res.assert_stdout_eq("Now: plan\n  1: chores\n");
+
+    let res = sigi(
+        "work",
+        &["-d", &dir, "list", "--as-of", "2024-05-01T00:00:00+00:00"],
+    );
+    res.assert_stdout_eq("Now: NOTHING\n");
+
+    // History stacks can be looked at too.
+    let res = sigi(
+        "work_history",
+        &[
+            "-d",
+            &dir,
+            "list",
+            "--as-of",
+            "2024-06-03T23:00:00+00:00",
+            "-q",
+        ],
+    );
+    res.assert_stdout_eq("write report\n");
+
+    let res = sigi("work", &["-d", &dir, "list", "--as-of", "someday"]);
+    res.assert_exit_code(2);
+}
+
+#[test]
+fn sigi_list_as_of_filters_and_formats() {
+    let dir = dir_with_files("sigi_integ_as_of_formats", &STACKS);
+
+    let res = sigi(
+        "work",
+        &[
+            "-d",
+            &dir,
+            "list",
+            "--as-of",
+            "2024-06-02T23:00:00+00:00",
+            "--tag",
+            "home",
+        ],
+    );
+    res.assert_stdout_eq("Now: NOTHING\n");
+    let res = sigi(
+        "work",
+        &[
+            "-d",
+            &dir,
+            "list",
+            "--as-of",
+            "2024-06-03T23:00:00+00:00",
+            "--tag",
+            "home",
+        ],
+    );
+    res.assert_stdout_eq("  1: chores\n");
+
+    let res = sigi(
+        "work",
+        &[
+            "-d",
+            &dir,
+            "list",
+            "--as-of",
+            "2024-06-02T23:00:00+00:00",
+            "-f",
+            "csv",
+        ],
+    );
+    let lines = res.stdout().lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 5, "{:?}", lines);
+    assert!(
+        lines[0].starts_with("position,item,created,id"),
+        "{}",
+        lines[0]
+    );
+    assert!(
+        lines[1].starts_with("0,fix the bug,2024-06-02"),
+        "{}",
+        lines[1]
+    );
+}
+
+#[test]
+fn sigi_list_as_of_now_keeps_reordering() {
+    let dir = fresh_dir("sigi_integ_as_of_reordering");
+    let stack = "_integ::as_of";
+
+    for item in ["a", "b", "c", "d"] {
+        sigi(stack, &["-d", &dir, "push", item]).assert_success();
+    }
+    for reorder in [&["swap"][..], &["rot"], &["next"], &["pick", "2"]] {
+        let args = [&["-d", dir.as_str()][..], reorder].concat();
+        sigi(stack, &args).assert_success();
+
+        let now = sigi(stack, &["-d", &dir, "list", "-q"]);
+        let as_of = sigi(stack, &["-d", &dir, "list", "-q", "--as-of", "now"]);
+        assert_eq!(now.stdout(), as_of.stdout(), "after {:?}", reorder);
+    }
+}
+
+#[test]
+fn sigi_list_as_of_in_interactive_mode() {
+    let dir = dir_with_files("sigi_integ_as_of_interactive", &STACKS);
+
+    let res = piping(&["list --as-of 2024-06-01T08:30:00+00:00", "ls --as-of"])
+        .into_sigi("work", &["-d", &dir, "interactive"]);
+    res.assert_stdout_line_starts_with("Now: plan");
+    res.assert_stdout_line_starts_with("--as-of needs a time");
+}
+
+#[test]
+fn sigi_list_as_of_before_a_copy() {
+    let dir = dir_with_files(
+        "sigi_integ_as_of_copy",
+        &[
+            (
+                "home.json",
+                r#"[{"id": "m1", "contents": "buy milk", "history": [
+                    {"event": "created", "time": "2024-06-01T12:00:00+00:00"}
+                ]}]"#,
+            ),
+            (
+                "shop.json",
+                r#"[{"id": "m2", "contents": "buy milk", "history": [
+                    {"event": "created", "time": "2024-06-01T12:00:00+00:00"},
+                    {"event": "copied", "from": "home", "to": "shop", "id": "m1",
+                     "time": "2024-06-05T12:00:00+00:00"}
+                ]}]"#,
+            ),
+        ],
+    );
+
+    // The copy wasn't anywhere before it was copied.
+    for (as_of, home, shop) in [
+        ("2024-06-03T00:00:00+00:00", "buy milk\n", ""),
+        ("2024-06-06T00:00:00+00:00", "buy milk\n", "buy milk\n"),
+    ] {
+        let res = sigi("home", &["-d", &dir, "list", "-q", "--as-of", as_of]);
+        res.assert_stdout_eq(home);
+        let res = sigi("shop", &["-d", &dir, "list", "-q", "--as-of", as_of]);
+        res.assert_stdout_eq(shop);
+    }
+}
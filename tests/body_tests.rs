@@ -0,0 +1,159 @@
+mod run_sigi;
+
+use run_sigi::{fresh_dir, piping, sigi};
+
+/// An "editor" that replaces the file it's given with some text.
+fn editor_writing(dir: &str, text: &str) -> String {
+    let source = format!("{}/body.txt", dir);
+    std::fs::write(&source, text).unwrap();
+    format!("cp {}", source)
+}
+
+#[test]
+fn sigi_item_body() {
+    let dir = fresh_dir("sigi_integ_body");
+    let stack = "_integ::body";
+
+    sigi(stack, &["-d", &dir, "push", "older"]).assert_success();
+    sigi(stack, &["-d", &dir, "push", "fix the login bug"]).assert_success();
+
+    let editor = editor_writing(&dir, "steps:\n1. log in\n2. crash\n");
+    let res = sigi(stack, &["-d", &dir, "edit", "--body", "-e", &editor]);
+    res.assert_success();
+    res.assert_stdout_eq("Edited: fix the login bug\n");
+
+    let res = sigi(stack, &["-d", &dir, "list"]);
+    res.assert_stdout_eq("Now: fix the login bug\n  1: older\n");
+
+    let res = sigi(stack, &["-d", &dir, "peek"]);
+    res.assert_stdout_eq("Now: fix the login bug\n");
+
+    let res = sigi(stack, &["-d", &dir, "show"]);
+    res.assert_stdout_eq("Now: fix the login bug\nsteps:\n1. log in\n2. crash\n");
+
+    let res = sigi(stack, &["-d", &dir, "-v", "list"]);
+    res.assert_stdout_lines_eq(&["*", "    steps:", "    1. log in", "    2. crash", "*"]);
+    res.assert_stdout_line_starts_with("  1: older (");
+
+    let res = sigi(stack, &["-d", &dir, "-f", "json", "show"]);
+    res.assert_stdout_line_eq(r#"    "body": "steps:\n1. log in\n2. crash""#);
+
+    // The headline is edited separately from the body.
+    let editor = editor_writing(&dir, "fix the logout bug\n");
+    let res = sigi(stack, &["-d", &dir, "edit", "-e", &editor]);
+    res.assert_stdout_eq("Edited: fix the logout bug\n");
+    let res = sigi(stack, &["-d", &dir, "-q", "show"]);
+    res.assert_stdout_eq("fix the logout bug\nsteps:\n1. log in\n2. crash\n");
+
+    // An empty body is no body.
+    let editor = editor_writing(&dir, "\n");
+    sigi(stack, &["-d", &dir, "edit", "-b", "-e", &editor]).assert_success();
+    let res = sigi(stack, &["-d", &dir, "show"]);
+    res.assert_stdout_eq("Now: fix the logout bug\n");
+
+    let res = sigi(stack, &["-d", &dir, "show", "1"]);
+    res.assert_stdout_eq("  1: older\n");
+}
+
+#[test]
+fn sigi_show_from_stdin() {
+    let dir = fresh_dir("sigi_integ_body_stdin");
+    let stack = "_integ::body_stdin";
+
+    let res =
+        piping(&["push a", "push b", "show 1", "details"]).into_sigi(stack, &["-d", &dir, "-"]);
+    res.assert_stdout_eq("a\nb\n");
+    res.assert_stderr_empty();
+}
+
+#[test]
+fn sigi_edit_body_from_stdin() {
+    let dir = fresh_dir("sigi_integ_body_edit_stdin");
+    let stack = "_integ::body_edit_stdin";
+    let editor = editor_writing(&dir, "notes\n");
+
+    let lines = [
+        "push a",
+        "push b",
+        "edit 1 --body",
+        "show 1",
+        "edit -b",
+        "show",
+    ];
+    let res = piping(&lines).into_sigi_with_env(stack, &[("VISUAL", &editor)], &["-d", &dir, "-"]);
+    res.assert_stdout_eq("a\nnotes\nb\nnotes\n");
+    res.assert_stderr_empty();
+}
+
+#[test]
+fn sigi_body_in_markdown_table() {
+    let dir = fresh_dir("sigi_integ_body_markdown");
+    let stack = "_integ::body_markdown";
+
+    sigi(stack, &["-d", &dir, "push", "pipes | tables"]).assert_success();
+    let editor = editor_writing(&dir, "line one\nline | two\n");
+    sigi(stack, &["-d", &dir, "edit", "-b", "-e", &editor]).assert_success();
+
+    // Pipes and line breaks stay inside their cells.
+    let res = sigi(stack, &["-d", &dir, "list", "-f", "markdown"]);
+    res.assert_success();
+    let lines = res.stdout().lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3, "{:?}", lines);
+    assert_eq!(
+        lines[0],
+        "| position | item | created | id | tags | due | priority | body |"
+    );
+    assert_eq!(
+        lines[1],
+        "| --- | --- | --- | --- | --- | --- | --- | --- |"
+    );
+    assert!(
+        lines[2].starts_with("| 0 | pipes \\| tables | "),
+        "{}",
+        lines[2]
+    );
+    assert!(
+        lines[2].ends_with(" | line one<br>line \\| two |"),
+        "{}",
+        lines[2]
+    );
+}
+
+#[test]
+fn sigi_body_in_csv_and_tsv() {
+    let dir = fresh_dir("sigi_integ_body_csv");
+    let stack = "_integ::body_csv";
+
+    sigi(stack, &["-d", &dir, "push", "fix it, \"properly\""]).assert_success();
+    let editor = editor_writing(&dir, "steps, in order:\n1. log in\t(as admin)\n2. crash\n");
+    sigi(stack, &["-d", &dir, "edit", "-b", "-e", &editor]).assert_success();
+
+    // Fields with commas, quotes or line breaks are quoted.
+    let res = sigi(stack, &["-d", &dir, "list", "-f", "csv"]);
+    res.assert_success();
+    let csv = res.stdout();
+    assert!(
+        csv.starts_with(
+            "position,item,created,id,tags,due,priority,body\n0,\"fix it, \"\"properly\"\"\","
+        ),
+        "{}",
+        csv
+    );
+    assert!(
+        csv.ends_with(",\"steps, in order:\n1. log in\t(as admin)\n2. crash\"\n"),
+        "{}",
+        csv
+    );
+
+    // Each item stays on one line, with one field per column.
+    let res = sigi(stack, &["-d", &dir, "list", "-f", "tsv"]);
+    let lines = res.stdout().lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2, "{:?}", lines);
+    let fields = lines[1].split('\t').collect::<Vec<_>>();
+    assert_eq!(fields.len(), 8, "{:?}", fields);
+    assert_eq!(fields[1], "fix it, \"properly\"");
+    assert_eq!(
+        fields[7],
+        "steps, in order:\\n1. log in\\t(as admin)\\n2. crash"
+    );
+}
@@ -0,0 +1,123 @@
+mod run_sigi;
+
+use run_sigi::{dir_with_files, fresh_dir, piping, sigi};
+
+/// Histories finished long ago, written out directly so the times are known.
+const STACKS: [(&str, &str); 3] = [
+    (
+        "work_history.json",
+        r#"[
+            {"id": "b1", "contents": "file bug", "history": [
+                {"event": "created", "time": "2024-05-01T09:00:00+00:00"},
+                {"event": "deleted", "time": "2024-05-10T09:00:00+00:00"}
+            ]},
+            {"id": "f1", "contents": "fix the bug", "history": [
+                {"event": "created", "time": "2024-05-20T09:00:00+00:00"},
+                {"event": "completed", "time": "2024-06-02T09:00:00+00:00"}
+            ]},
+            {"id": "r1", "contents": "write report", "history": [
+                {"event": "created", "time": "2024-06-01T09:00:00+00:00"},
+                {"event": "completed", "time": "2024-06-03T17:00:00+00:00"}
+            ]}
+        ]"#,
+    ),
+    (
+        "home_history.json",
+        r#"[{"id": "m1", "contents": "buy milk", "history": [
+            {"event": "created", "time": "2024-06-01T12:00:00+00:00"},
+            {"event": "completed", "time": "2024-06-01T13:00:00+00:00"}
+        ]}]"#,
+    ),
+    (
+        "_archive/old_history.json",
+        r#"[{"id": "o1", "contents": "forgotten", "history": [
+            {"event": "created", "time": "2024-06-02T12:00:00+00:00"},
+            {"event": "completed", "time": "2024-06-02T13:00:00+00:00"}
+        ]}]"#,
+    ),
+];
+
+#[test]
+fn sigi_compact_archives_by_month() {
+    let dir = dir_with_files("sigi_integ_compact_keep", &STACKS);
+
+    let res = sigi("work", &["-d", &dir, "compact", "--keep", "1"]);
+    res.assert_success();
+    res.assert_stdout_eq("work_history: 1 kept, 2 archived\n");
+
+    let res = sigi("work_history", &["-d", &dir, "list", "-q"]);
+    res.assert_stdout_eq("write report\n");
+    let res = sigi("work", &["-d", &dir, "list-stacks", "--archived", "-q"]);
+    res.assert_stdout_eq("_archive/work_history/2024-05\n_archive/work_history/2024-06\n");
+    let res = sigi("_archive/work_history/2024-06", &["-d", &dir, "list", "-q"]);
+    res.assert_stdout_eq("fix the bug\n");
+
+    // Compacting again has nothing more to do, and undo puts it all back.
+    let res = sigi("work", &["-d", &dir, "compact", "--keep", "1"]);
+    res.assert_stdout_eq("work_history: 1 kept, 0 archived\n");
+    sigi("work", &["-d", &dir, "undo"]).assert_success();
+    sigi("work", &["-d", &dir, "undo"]).assert_success();
+    let res = sigi("work_history", &["-d", &dir, "count"]);
+    res.assert_stdout_eq("3\n");
+}
+
+#[test]
+fn sigi_compact_by_age_and_drop() {
+    let dir = dir_with_files("sigi_integ_compact_days", &STACKS);
+    sigi("work", &["-d", &dir, "push", "fresh"]).assert_success();
+    sigi("work", &["-d", &dir, "complete"]).assert_success();
+
+    let res = sigi(
+        "work",
+        &["-d", &dir, "compact", "--keep-days", "30", "--drop"],
+    );
+    res.assert_stdout_eq("work_history: 1 kept, 3 dropped\n");
+    let res = sigi("work_history", &["-d", &dir, "list", "-q"]);
+    res.assert_stdout_eq("fresh\n");
+    let res = sigi("work", &["-d", &dir, "list-stacks", "--archived", "-q"]);
+    res.assert_stdout_eq("");
+
+    // Nothing says what to keep.
+    let res = sigi("work", &["-d", &dir, "compact"]);
+    res.assert_exit_code(78);
+    let res = sigi("work", &["-d", &dir, "compact", "--drop", "--archive"]);
+    res.assert_exit_code(2);
+
+    // Further back than times go.
+    let res = sigi(
+        "work",
+        &["-d", &dir, "compact", "--keep-days", "4000000000"],
+    );
+    res.assert_exit_code(78);
+    res.assert_stderr_starts_with("Error: Invalid keep-days: 4000000000 days is further back");
+}
+
+#[test]
+fn sigi_compact_all_histories() {
+    let dir = dir_with_files("sigi_integ_compact_all", &STACKS);
+
+    let res = sigi(
+        "work",
+        &["-d", &dir, "compact", "--all", "--keep", "2", "-f", "csv"],
+    );
+    res.assert_stdout_eq("stack,kept,archived,dropped\nhome_history,1,0,0\nwork_history,2,1,0\n");
+
+    let dir = fresh_dir("sigi_integ_compact_none");
+    let res = sigi("work", &["-d", &dir, "compact", "--all", "--keep", "2"]);
+    res.assert_stdout_eq("Compacted: NOTHING\n");
+}
+
+#[test]
+fn sigi_compact_in_interactive_mode() {
+    let dir = dir_with_files("sigi_integ_compact_interactive", &STACKS);
+
+    let res = piping(&[
+        "compact --keep 2 --drop",
+        "compact --keep",
+        "compact --keep-weeks 2",
+    ])
+    .into_sigi("work", &["-d", &dir, "interactive"]);
+    res.assert_stdout_line_starts_with("work_history: 2 kept, 1 dropped");
+    res.assert_stdout_line_starts_with("--keep needs a number");
+    res.assert_stdout_line_starts_with("\"--keep-weeks\" isn't an option of compact");
+}
@@ -0,0 +1,77 @@
+mod run_sigi;
+
+use std::process::Command;
+
+use run_sigi::{fresh_dir, sigi, SIGI_PATH};
+
+#[test]
+fn sigi_concurrent_pushes_are_not_lost() {
+    let dir = std::env::temp_dir().join("sigi_integ_concurrency");
+    let _ = std::fs::remove_dir_all(&dir);
+    let dir = dir.to_string_lossy().to_string();
+    let stack = "_integ::concurrency";
+    let n = 20;
+
+    let children = (0..n)
+        .map(|i| {
+            Command::new(SIGI_PATH)
+                .args(["--stack", stack, "-d", &dir, "-s", "push"])
+                .arg(i.to_string())
+                .spawn()
+                .expect("Error running process")
+        })
+        .collect::<Vec<_>>();
+
+    for mut child in children {
+        assert!(child.wait().unwrap().success());
+    }
+
+    let res = sigi(stack, &["-d", &dir, "count"]);
+    res.assert_success();
+    res.assert_stdout_eq(&format!("{}\n", n));
+    res.assert_stderr_empty();
+}
+
+/// An "editor" that runs sigi on the stack while it's open, then writes
+/// some text to the file it's given.
+fn editor_running_sigi(dir: &str, stack: &str, args: &str, text: &str) -> String {
+    let script = format!("{}/editor.sh", dir);
+    let contents = format!(
+        "#!/bin/sh\n{:?} --stack {:?} -d {:?} {} || exit 1\nprintf {:?} > \"$1\"\n",
+        SIGI_PATH, stack, dir, args, text
+    );
+    std::fs::write(&script, contents).unwrap();
+    format!("sh {}", script)
+}
+
+#[test]
+fn sigi_edit_leaves_the_store_unlocked_while_editing() {
+    let dir = fresh_dir("sigi_integ_concurrency_edit");
+    let stack = "_integ::concurrency_edit";
+    sigi(stack, &["-d", &dir, "push", "draft"]).assert_success();
+
+    // This would wait forever if the edit held the lock.
+    let editor = editor_running_sigi(&dir, stack, "-s push meanwhile", "final");
+    let res = sigi(stack, &["-d", &dir, "edit", "-e", &editor]);
+    res.assert_success();
+    res.assert_stdout_eq("Edited: final\n");
+
+    let res = sigi(stack, &["-d", &dir, "-q", "list"]);
+    res.assert_stdout_eq("meanwhile\nfinal\n");
+}
+
+#[test]
+fn sigi_edit_fails_if_the_item_changed_meanwhile() {
+    let dir = fresh_dir("sigi_integ_concurrency_edit_conflict");
+    let stack = "_integ::concurrency_edit_conflict";
+    sigi(stack, &["-d", &dir, "push", "draft"]).assert_success();
+
+    let editor = editor_running_sigi(&dir, stack, "-s tag +urgent", "final");
+    let res = sigi(stack, &["-d", &dir, "edit", "-e", &editor]);
+    res.assert_exit_code(75);
+    res.assert_stderr_starts_with("Error: Item @");
+
+    let res = sigi(stack, &["-d", &dir, "-v", "peek"]);
+    res.assert_stdout_line_starts_with("Now: draft (");
+    assert!(res.stdout().contains("+urgent"), "{}", res.stdout());
+}
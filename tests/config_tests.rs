@@ -0,0 +1,175 @@
+mod run_sigi;
+
+use run_sigi::{fresh_dir, sigi_with_env, stack_file, SigiOutput, SIGI_PATH};
+use std::process::Command;
+
+/// Write a config file, and return the path to give as SIGI_CONFIG.
+fn write_config(dir: &str, toml: &str) -> String {
+    let path = format!("{}/config.toml", dir);
+    std::fs::write(&path, toml).unwrap();
+    path
+}
+
+/// Run sigi with a config file, and without a --stack flag.
+fn sigi_configured(config: &str, args: &[&str]) -> SigiOutput {
+    Command::new(SIGI_PATH)
+        .env("SIGI_CONFIG", config)
+        .env_remove("SIGI_HOME")
+        .env_remove("SIGI_TZ")
+        .args(args)
+        .output()
+        .expect("Error running process")
+        .into()
+}
+
+#[test]
+fn sigi_config_sets_defaults() {
+    let dir = fresh_dir("sigi_integ_config_defaults");
+    let config = write_config(
+        &dir,
+        &format!(
+            "stack = \"work\"\nformat = \"quiet\"\ndata-store = \"{}/store\"\nlist-limit = 2\n",
+            dir
+        ),
+    );
+
+    for item in ["a", "b", "c"] {
+        sigi_configured(&config, &["push", item]).assert_success();
+    }
+    assert!(std::path::Path::new(&format!("{}/store/work.json", dir)).exists());
+
+    let res = sigi_configured(&config, &["head"]);
+    res.assert_stdout_eq("c\nb\n");
+
+    // Flags win over the config file.
+    let res = sigi_configured(&config, &["head", "3"]);
+    res.assert_stdout_eq("c\nb\na\n");
+    let res = sigi_configured(&config, &["-f", "csv", "count"]);
+    res.assert_stdout_eq("items\n3\n");
+    let res = sigi_configured(&config, &["-t", "other", "count"]);
+    res.assert_stdout_eq("0\n");
+    let other_store = format!("{}/other_store", dir);
+    sigi_configured(&config, &["-d", &other_store, "push", "elsewhere"]).assert_success();
+    assert!(std::path::Path::new(&format!("{}/work.json", other_store)).exists());
+}
+
+#[test]
+fn sigi_config_environment_wins() {
+    let dir = fresh_dir("sigi_integ_config_env");
+    let config = write_config(
+        &dir,
+        &format!(
+            "data-store = \"{}/config_store\"\ntz = \"utc\"\ntime-format = \"%H:%M %z\"\n",
+            dir
+        ),
+    );
+    let home = format!("{}/home_store", dir);
+    let stack = "_integ::config_env";
+    let env = [
+        ("SIGI_CONFIG", config.as_str()),
+        ("SIGI_HOME", home.as_str()),
+        ("SIGI_TZ", "+05:30"),
+    ];
+
+    let due = "2024-06-01T09:30:00+00:00";
+    let res = sigi_with_env(stack, &env, &["push", "--due", due, "call"]);
+    res.assert_success();
+    assert!(std::path::Path::new(&stack_file(&home, stack)).exists());
+
+    let res = sigi_with_env(stack, &env, &["-v", "peek"]);
+    res.assert_stdout_line_starts_with("Now: call [overdue] (");
+    assert!(res.stdout().contains("due 15:00 +0530"), "{}", res.stdout());
+}
+
+#[test]
+fn sigi_config_aliases() {
+    let dir = fresh_dir("sigi_integ_config_aliases");
+    let config = write_config(
+        &dir,
+        &format!(
+            "stack = \"aliases\"\ndata-store = \"{}\"\n\n[aliases]\ntodo = \"push +todo\"\nlater = \"delete\"\n",
+            dir
+        ),
+    );
+
+    let res = sigi_configured(&config, &["-q", "todo", "write", "tests"]);
+    res.assert_stdout_eq("write tests\n");
+    let res = sigi_configured(&config, &["-f", "tsv", "peek"]);
+    res.assert_stdout_line_starts_with("Now\twrite tests\t");
+    assert!(res.stdout().contains("\ttodo\t"), "{}", res.stdout());
+
+    // Built-in commands can't be replaced: later is still next.
+    sigi_configured(&config, &["push", "other"]).assert_success();
+    sigi_configured(&config, &["later"]).assert_success();
+    sigi_configured(&config, &["-q", "count"]).assert_stdout_eq("2\n");
+}
+
+#[test]
+fn sigi_config_retention() {
+    let dir = fresh_dir("sigi_integ_config_retention");
+    let config = write_config(
+        &dir,
+        &format!(
+            "stack = \"chores\"\ndata-store = \"{}\"\n\n[retention]\nkeep = 2\narchive = false\n",
+            dir
+        ),
+    );
+    for item in ["dishes", "laundry", "sweep"] {
+        sigi_configured(&config, &["push", item]).assert_success();
+        sigi_configured(&config, &["complete"]).assert_success();
+    }
+
+    // Histories are kept to the policy as items go to them.
+    let res = sigi_configured(&config, &["-t", "chores_history", "-q", "list"]);
+    res.assert_stdout_eq("sweep\nlaundry\n");
+    let res = sigi_configured(&config, &["compact"]);
+    res.assert_stdout_eq("chores_history: 2 kept, 0 dropped\n");
+    sigi_configured(&config, &["push", "mop"]).assert_success();
+    sigi_configured(&config, &["delete"]).assert_success();
+    let res = sigi_configured(&config, &["-t", "chores_history", "-q", "list"]);
+    res.assert_stdout_eq("mop\nsweep\n");
+
+    // Flags win over the config file.
+    let res = sigi_configured(&config, &["compact", "--keep", "1", "--archive"]);
+    res.assert_stdout_eq("chores_history: 1 kept, 1 archived\n");
+    let res = sigi_configured(&config, &["-q", "list-stacks", "--archived"]);
+    assert!(
+        res.stdout().starts_with("_archive/chores_history/"),
+        "{}",
+        res.stdout()
+    );
+}
+
+#[test]
+fn sigi_config_errors() {
+    let dir = fresh_dir("sigi_integ_config_errors");
+
+    let config = write_config(&dir, "format = \"loud\"\n");
+    let res = sigi_configured(&config, &["peek"]);
+    res.assert_exit_code(78);
+    res.assert_stderr_starts_with("Error: Invalid config file");
+
+    let config = write_config(&dir, "colour = \"blue\"\n");
+    sigi_configured(&config, &["peek"]).assert_exit_code(78);
+
+    let config = write_config(&dir, "[retention]\nkeep-days = 4000000000\n");
+    let res = sigi_configured(&config, &["peek"]);
+    res.assert_exit_code(78);
+    res.assert_stderr_starts_with("Error: Invalid config file");
+
+    // Help and version still work, to help fix the config file.
+    for args in [
+        &["--help"][..],
+        &["--version"],
+        &["help", "push"],
+        &["push", "-h"],
+    ] {
+        let res = sigi_configured(&config, args);
+        res.assert_success();
+        res.assert_stderr_starts_with("Error: Invalid config file");
+        assert!(res.stdout().contains("sigi"), "{}", res.stdout());
+    }
+
+    let missing = format!("{}/missing.toml", dir);
+    sigi_configured(&missing, &["peek"]).assert_exit_code(74);
+}
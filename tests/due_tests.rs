@@ -0,0 +1,147 @@
+mod run_sigi;
+
+use run_sigi::{fresh_dir, piping, sigi};
+
+#[test]
+fn sigi_snooze() {
+    let dir = fresh_dir("sigi_integ_snooze");
+    let stack = "_integ::snooze";
+
+    for item in ["a", "b", "c"] {
+        sigi(stack, &["-d", &dir, "push", item]).assert_success();
+    }
+
+    let res = sigi(stack, &["-d", &dir, "snooze", "--until", "2h"]);
+    res.assert_success();
+    res.assert_stdout_line_starts_with("Snoozed: c");
+    res.assert_stdout_line_eq("Now: b");
+
+    let res = sigi(stack, &["-d", &dir, "list"]);
+    res.assert_stdout_lines_eq(&["Now: b", "  1: a"]);
+
+    let res = sigi(stack, &["-d", &dir, "count"]);
+    res.assert_stdout_eq("2\n");
+
+    let res = sigi(stack, &["-d", &dir, "list", "--deferred"]);
+    res.assert_stdout_eq("Later: c\n");
+
+    // Positions skip the snoozed item.
+    let res = sigi(stack, &["-d", &dir, "swap"]);
+    res.assert_stdout_lines_eq(&["Now: a", "  1: b"]);
+
+    let res = sigi(stack, &["-d", &dir, "complete", "1"]);
+    res.assert_stdout_lines_eq(&["Completed: b", "Now: a"]);
+
+    // Snoozing until now brings an item back.
+    let res = sigi(stack, &["-d", &dir, "-f", "tsv", "list", "--deferred"]);
+    let id = res
+        .stdout()
+        .lines()
+        .nth(1)
+        .unwrap()
+        .split('\t')
+        .nth(3)
+        .unwrap()
+        .to_string();
+    let res = sigi(
+        stack,
+        &["-d", &dir, "snooze", &format!("@{}", id), "--until", "now"],
+    );
+    res.assert_success();
+    let res = sigi(stack, &["-d", &dir, "-q", "list"]);
+    res.assert_stdout_eq("c\na\n");
+}
+
+#[test]
+fn sigi_due_and_overdue() {
+    let dir = fresh_dir("sigi_integ_due");
+    let stack = "_integ::due";
+    let other = "_integ::due_other";
+
+    let res = sigi(
+        stack,
+        &["-d", &dir, "push", "--due", "2020-01-01", "file taxes"],
+    );
+    res.assert_success();
+    sigi(
+        stack,
+        &["-d", &dir, "push", "--due", "tomorrow", "water plants"],
+    )
+    .assert_success();
+    sigi(other, &["-d", &dir, "push", "--due", "1d ago", "call back"]).assert_success();
+    sigi(other, &["-d", &dir, "push", "no rush"]).assert_success();
+
+    let res = sigi(stack, &["-d", &dir, "list"]);
+    res.assert_stdout_lines_eq(&["Now: water plants", "  1: file taxes [overdue]"]);
+
+    let res = sigi(stack, &["-d", &dir, "-q", "list"]);
+    res.assert_stdout_lines_eq(&["water plants", "file taxes [overdue]"]);
+
+    let res = sigi(stack, &["-d", &dir, "overdue"]);
+    res.assert_stdout_lines_eq(&["_integ::due: file taxes", "_integ::due_other: call back"]);
+
+    let res = sigi(stack, &["-d", &dir, "-f", "csv", "overdue"]);
+    res.assert_stdout_line_eq("stack,item,position,due,id");
+    res.assert_stdout_line_starts_with("_integ::due,file taxes,1,");
+
+    let res = sigi(stack, &["-d", &dir, "due", "1", "--never"]);
+    res.assert_stdout_eq("Due: file taxes\n");
+    let res = sigi(other, &["-d", &dir, "due", "1", "--by", "1w"]);
+    res.assert_success();
+
+    let res = sigi(stack, &["-d", &dir, "overdue"]);
+    res.assert_stdout_eq("Overdue: NOTHING\n");
+
+    let res = sigi(stack, &["-d", &dir, "push", "--due", "someday", "x"]);
+    res.assert_failure();
+    res.assert_stderr_starts_with("error: invalid value 'someday'");
+}
+
+#[test]
+fn sigi_due_and_snooze_from_stdin() {
+    let dir = fresh_dir("sigi_integ_due_stdin");
+    let stack = "_integ::due_stdin";
+
+    let res = piping(&[
+        "push a",
+        "push b",
+        "due 1 yesterday",
+        "snooze 1w",
+        "list",
+        "overdue",
+    ])
+    .into_sigi(stack, &["-d", &dir, "-"]);
+    // Only people are told about overdue items, not pipes.
+    res.assert_stdout_eq("a\na\n");
+    res.assert_stderr_empty();
+
+    // The flags of the command line work too.
+    let dir = fresh_dir("sigi_integ_due_stdin_flags");
+    let res = piping(&[
+        "push c",
+        "push d",
+        "due --by yesterday",
+        "due --never",
+        "due 1 --by=yesterday",
+        "snooze --until 1w",
+        "list",
+        "overdue",
+    ])
+    .into_sigi(stack, &["-d", &dir, "-"]);
+    res.assert_stdout_eq("c\nc\n");
+    res.assert_stderr_empty();
+}
+
+#[test]
+fn sigi_times_too_far_off_are_refused() {
+    let dir = fresh_dir("sigi_integ_due_too_far");
+    let stack = "_integ::due_too_far";
+
+    let res = sigi(stack, &["-d", &dir, "push", "--due", "100000000w", "x"]);
+    res.assert_exit_code(2);
+    res.assert_stderr_starts_with("error: invalid value '100000000w'");
+
+    sigi(stack, &["-d", &dir, "push", "x"]).assert_success();
+    let res = sigi(stack, &["-d", &dir, "snooze", "--until", "100000000w"]);
+    res.assert_exit_code(2);
+}
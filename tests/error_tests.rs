@@ -0,0 +1,32 @@
+mod run_sigi;
+
+use run_sigi::{fresh_dir, sigi, stack_file};
+
+#[test]
+fn sigi_corrupt_stack_is_an_error() {
+    let dir = fresh_dir("sigi_integ_corrupt");
+    let stack = "_integ::corrupt";
+    std::fs::write(stack_file(&dir, stack), "[{not json").unwrap();
+
+    let res = sigi(stack, &["-d", &dir, "peek"]);
+    res.assert_exit_code(65);
+    res.assert_stdout_eq("");
+    res.assert_stderr_starts_with("Error: The data for stack \"_integ::corrupt\" is corrupt");
+}
+
+#[test]
+fn sigi_invalid_index_is_an_error() {
+    let dir = fresh_dir("sigi_integ_invalid_index");
+    let stack = "_integ::invalid_index";
+
+    let res = sigi(stack, &["-d", &dir, "push", "only one"]);
+    res.assert_success();
+
+    let res = sigi(stack, &["-d", &dir, "complete", "3"]);
+    res.assert_exit_code(64);
+    res.assert_stderr_starts_with("Error: Stack \"_integ::invalid_index\" has no item number 3");
+
+    let res = sigi(stack, &["-d", &dir, "count"]);
+    res.assert_success();
+    res.assert_stdout_eq("1\n");
+}
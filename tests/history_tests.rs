@@ -0,0 +1,60 @@
+mod run_sigi;
+
+use run_sigi::{fresh_dir, sigi, stack_file};
+
+fn stored(dir: &str, stack: &str) -> String {
+    std::fs::read_to_string(stack_file(dir, stack)).unwrap()
+}
+
+#[test]
+fn sigi_edits_and_moves_are_recorded() {
+    let dir = fresh_dir("sigi_integ_history_events");
+    let stack = "_integ::history_events";
+    let dest = "_integ::history_events_dest";
+
+    sigi(stack, &["-d", &dir, "push", "first draft"]).assert_success();
+    sigi(stack, &["-d", &dir, "edit", "-e", "sed -i s/first/final/"]).assert_success();
+    sigi(stack, &["-d", &dir, "move", dest]).assert_success();
+
+    let json = stored(&dir, dest);
+    assert!(json.contains(r#""event":"created""#), "{}", json);
+    assert!(json.contains(r#""event":"edited","field":"headline","before":"first draft""#));
+    assert!(json.contains(&format!(
+        r#""event":"moved","from":"{}","to":"{}""#,
+        stack, dest
+    )));
+
+    let res = sigi(dest, &["-d", &dir, "peek"]);
+    res.assert_stdout_eq("Now: final draft\n");
+}
+
+#[test]
+fn sigi_untyped_history_is_migrated() {
+    let dir = fresh_dir("sigi_integ_history_migration");
+    let stack = "_integ::history_migration";
+    let old = r#"[{"contents":"old","history":[["created","2023-01-01T00:00:00+00:00"],["archived","2023-01-02T00:00:00+00:00"]]}]"#;
+    std::fs::write(stack_file(&dir, stack), old).unwrap();
+
+    let res = sigi(stack, &["-d", &dir, "-f", "tsv", "peek"]);
+    res.assert_success();
+    let id = res
+        .stdout()
+        .lines()
+        .nth(1)
+        .unwrap()
+        .split('\t')
+        .nth(3)
+        .unwrap()
+        .to_string();
+
+    // Saving the stack again writes typed events, keeping the unknown one.
+    sigi(stack, &["-d", &dir, "tag", "+old"]).assert_success();
+    let json = stored(&dir, stack);
+    assert!(json.contains(&format!(r#""id":"{}""#, id)), "{}", json);
+    assert!(json.contains(r#""event":"created","time":"#));
+    assert!(json.contains(r#""event":"other","name":"archived","time":"#));
+    assert!(json.contains(r#""event":"tagged","tags":["old"]"#));
+
+    let res = sigi(stack, &["-d", &dir, "-f", "tsv", "peek"]);
+    assert!(res.stdout().contains(&format!("\t{}\t", id)));
+}
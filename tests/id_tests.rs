@@ -0,0 +1,132 @@
+mod run_sigi;
+
+use run_sigi::{fresh_dir, sigi, sigi_with_env, stack_file};
+
+/// Push an item and return its full ID.
+fn push(dir: &str, stack: &str, content: &str) -> String {
+    let res = sigi(stack, &["-d", dir, "-f", "tsv", "push", content]);
+    res.assert_success();
+    let line = res.stdout().lines().nth(1).unwrap().to_string();
+    line.split('\t').nth(2).unwrap().to_string()
+}
+
+#[test]
+fn sigi_items_by_id() {
+    let dir = fresh_dir("sigi_integ_ids");
+    let stack = "_integ::ids";
+
+    let a = push(&dir, stack, "a");
+    let b = push(&dir, stack, "b");
+    push(&dir, stack, "c");
+    assert_eq!(a.len(), 16);
+    assert_ne!(a, b);
+
+    // IDs stay with items as they move around.
+    let res = sigi(stack, &["-d", &dir, "pick", &format!("@{}", &a[..6])]);
+    res.assert_success();
+    let res = sigi(stack, &["-d", &dir, "-q", "list"]);
+    res.assert_stdout_lines_eq(&["a", "c", "b"]);
+
+    let res = sigi(stack, &["-d", &dir, "complete", &format!("@{}", &b[..6])]);
+    res.assert_success();
+    res.assert_stdout_lines_eq(&["Completed: b", "Now: a"]);
+
+    let res = sigi(stack, &["-d", &dir, "-v", "peek"]);
+    res.assert_stdout_line_starts_with("Now: a (");
+    assert!(res.stdout().ends_with(&format!(", @{})\n", &a[..8])));
+
+    let res = sigi(stack, &["-d", &dir, "-f", "csv", "list"]);
+    res.assert_stdout_line_starts_with("position,item,created,id,tags");
+    res.assert_stdout_line_starts_with("0,a,");
+
+    let res = sigi(
+        stack,
+        &["-d", &dir, "move", "_integ::ids_other", &format!("@{}", a)],
+    );
+    res.assert_success();
+    let res = sigi(stack, &["-d", &dir, "-q", "list"]);
+    res.assert_stdout_eq("c\n");
+
+    let res = sigi(stack, &["-d", &dir, "restore", &format!("@{}", &b[..6])]);
+    res.assert_success();
+    res.assert_stdout_lines_eq(&["Restored: b", "Now: b"]);
+}
+
+#[test]
+fn sigi_unknown_id_is_an_error() {
+    let dir = fresh_dir("sigi_integ_unknown_id");
+    let stack = "_integ::unknown_id";
+    let id = push(&dir, stack, "a");
+
+    let other = if id.starts_with('0') { "@1" } else { "@0" };
+    let res = sigi(stack, &["-d", &dir, "delete", other]);
+    res.assert_exit_code(64);
+    res.assert_stderr_starts_with("Error: Stack \"_integ::unknown_id\" has no item with ID");
+
+    let res = sigi(stack, &["-d", &dir, "delete", "@"]);
+    res.assert_failure();
+
+    let res = sigi(stack, &["-d", &dir, "count"]);
+    res.assert_stdout_eq("1\n");
+}
+
+#[test]
+fn sigi_ids_are_backfilled_for_old_files() {
+    let dir = fresh_dir("sigi_integ_backfill_ids");
+    let stack = "_integ::backfill_ids";
+    let old = r#"[{"contents":"old","history":[["created","2023-01-01T00:00:00+00:00"]]}]"#;
+    std::fs::write(stack_file(&dir, stack), old).unwrap();
+
+    let res = sigi(stack, &["-d", &dir, "-f", "tsv", "peek"]);
+    res.assert_success();
+    let id = res
+        .stdout()
+        .lines()
+        .nth(1)
+        .unwrap()
+        .split('\t')
+        .nth(3)
+        .unwrap()
+        .to_string();
+    assert_eq!(id.len(), 16);
+
+    // The same ID every time, even before the file is written again, and
+    // whatever the local time zone.
+    let res = sigi(stack, &["-d", &dir, "-f", "tsv", "peek"]);
+    res.assert_stdout_line_starts_with("Now\told\t");
+    assert!(res.stdout().contains(&format!("\t{}\t", id)));
+    for tz in ["America/Chicago", "Asia/Kolkata"] {
+        let res = sigi_with_env(stack, &[("TZ", tz)], &["-d", &dir, "-f", "tsv", "peek"]);
+        assert!(res.stdout().contains(&format!("\t{}\t", id)), "{}", tz);
+    }
+
+    let res = sigi(
+        stack,
+        &[
+            "-d",
+            &dir,
+            "edit",
+            "-e",
+            "sed -i s/old/new/",
+            &format!("@{}", id),
+        ],
+    );
+    res.assert_success();
+    let res = sigi(stack, &["-d", &dir, "-f", "tsv", "peek"]);
+    res.assert_stdout_line_starts_with("Now\tnew\t");
+    assert!(res.stdout().contains(&format!("\t{}\t", id)));
+}
+
+#[test]
+fn sigi_edit_counts_from_the_top() {
+    let dir = fresh_dir("sigi_integ_edit_position");
+    let stack = "_integ::edit_position";
+    push(&dir, stack, "bottom");
+    push(&dir, stack, "top");
+
+    let res = sigi(stack, &["-d", &dir, "edit", "-e", "sed -i s/top/changed/"]);
+    res.assert_success();
+
+    let res = sigi(stack, &["-d", &dir, "-q", "list"]);
+    res.assert_stdout_lines_eq(&["changed", "bottom"]);
+}
@@ -0,0 +1,133 @@
+mod run_sigi;
+
+use run_sigi::{fresh_dir, piping, sigi};
+
+#[test]
+fn sigi_queue_mode() {
+    let dir = fresh_dir("sigi_integ_mode_queue");
+    let stack = "_integ::queue";
+
+    sigi(stack, &["-d", &dir, "push", "first"]).assert_success();
+    sigi(stack, &["-d", &dir, "push", "second"]).assert_success();
+    let res = sigi(stack, &["-d", &dir, "mode"]);
+    res.assert_stdout_eq("Mode: stack\n");
+
+    // Switching puts the oldest item on top.
+    let res = sigi(stack, &["-d", &dir, "mode", "queue"]);
+    res.assert_success();
+    res.assert_stdout_eq("Mode: queue\nNow: first\n");
+
+    sigi(stack, &["-d", &dir, "push", "third"]).assert_success();
+    let res = sigi(stack, &["-d", &dir, "peek"]);
+    res.assert_stdout_eq("Now: first\n");
+    let res = sigi(stack, &["-d", &dir, "head", "-q", "2"]);
+    res.assert_stdout_eq("first\nsecond\n");
+
+    let res = sigi(stack, &["-d", &dir, "complete"]);
+    res.assert_stdout_eq("Completed: first\nNow: second\n");
+    let res = sigi(stack, &["-d", &dir, "list"]);
+    res.assert_stdout_eq("Now: second\n  1: third\n");
+
+    // Moved items join the back of the queue too.
+    sigi("_integ::other", &["-d", &dir, "push", "moved"]).assert_success();
+    sigi("_integ::other", &["-d", &dir, "move", stack]).assert_success();
+    let res = sigi(stack, &["-d", &dir, "list", "-q"]);
+    res.assert_stdout_eq("second\nthird\nmoved\n");
+    let res = sigi(stack, &["-d", &dir, "list", "-q", "--as-of", "now"]);
+    res.assert_stdout_eq("second\nthird\nmoved\n");
+}
+
+#[test]
+fn sigi_priority_mode() {
+    let dir = fresh_dir("sigi_integ_mode_priority");
+    let stack = "_integ::priority";
+
+    sigi(stack, &["-d", &dir, "push", "whenever"]).assert_success();
+    sigi(stack, &["-d", &dir, "push", "--priority", "2", "soon"]).assert_success();
+    sigi(stack, &["-d", &dir, "push", "newest"]).assert_success();
+    sigi(stack, &["-d", &dir, "mode", "priority"]).assert_success();
+
+    let res = sigi(stack, &["-d", &dir, "list"]);
+    res.assert_stdout_eq("Now: soon\n  1: whenever\n  2: newest\n");
+
+    // Lower numbers go first, and the same priority is first come, first served.
+    sigi(stack, &["-d", &dir, "push", "--priority", "1", "urgent"]).assert_success();
+    sigi(stack, &["-d", &dir, "push", "--priority", "2", "also soon"]).assert_success();
+    let res = sigi(stack, &["-d", &dir, "list", "-q"]);
+    res.assert_stdout_eq("urgent\nsoon\nalso soon\nwhenever\nnewest\n");
+
+    let res = sigi(stack, &["-d", &dir, "priority", "4", "--to", "1"]);
+    res.assert_stdout_eq("Priority: newest\n");
+    let res = sigi(stack, &["-d", &dir, "head", "-q", "2"]);
+    res.assert_stdout_eq("urgent\nnewest\n");
+
+    let res = sigi(stack, &["-d", &dir, "-f", "csv", "peek"]);
+    res.assert_stdout_line_starts_with("position,item,created,id,tags,due,priority,body");
+    assert!(res.stdout().contains(",,,1,\n"), "{}", res.stdout());
+
+    sigi(stack, &["-d", &dir, "complete"]).assert_success();
+    sigi(stack, &["-d", &dir, "priority", "--none"]).assert_success();
+    let res = sigi(stack, &["-d", &dir, "list", "-q"]);
+    res.assert_stdout_eq("soon\nalso soon\nwhenever\nnewest\n");
+}
+
+#[test]
+fn sigi_mode_is_kept_with_the_stack() {
+    let dir = fresh_dir("sigi_integ_mode_kept");
+    let stack = "_integ::kept";
+
+    sigi(stack, &["-d", &dir, "push", "a"]).assert_success();
+    sigi(stack, &["-d", &dir, "mode", "queue"]).assert_success();
+    sigi(stack, &["-d", &dir, "rename-stack", "_integ::renamed"]).assert_success();
+    let res = sigi("_integ::renamed", &["-d", &dir, "mode", "-q"]);
+    res.assert_stdout_eq("queue\n");
+    let res = sigi(stack, &["-d", &dir, "mode", "-q"]);
+    res.assert_stdout_eq("stack\n");
+
+    // Undo puts the mode back along with the items.
+    sigi("_integ::renamed", &["-d", &dir, "mode", "stack"]).assert_success();
+    sigi(stack, &["-d", &dir, "undo"]).assert_success();
+    let res = sigi("_integ::renamed", &["-d", &dir, "mode", "-q"]);
+    res.assert_stdout_eq("queue\n");
+
+    let res = sigi(stack, &["-d", &dir, "mode", "fifo", "-f", "json-compact"]);
+    res.assert_stdout_eq("[{\"action\":\"Mode\",\"mode\":\"queue\",\"stack\":\"_integ::kept\"}]\n");
+    let res = sigi(stack, &["-d", &dir, "mode", "random"]);
+    res.assert_exit_code(2);
+    let res = sigi("_integ::kept_history", &["-d", &dir, "mode", "queue"]);
+    res.assert_exit_code(64);
+
+    // SQLite stores keep modes too.
+    let sqlite = |args: &[&str]| {
+        let args = [&["--data-format", "sqlite", "-d", &dir], args].concat();
+        sigi(stack, &args)
+    };
+    sqlite(&["push", "x"]).assert_success();
+    sqlite(&["push", "y"]).assert_success();
+    sqlite(&["mode", "queue"]).assert_success();
+    sqlite(&["push", "z"]).assert_success();
+    sqlite(&["list", "-q"]).assert_stdout_eq("x\ny\nz\n");
+}
+
+#[test]
+fn sigi_mode_in_interactive_mode() {
+    let dir = fresh_dir("sigi_integ_mode_interactive");
+
+    let res = piping(&[
+        "push later",
+        "push sooner",
+        "mode priority",
+        "priority 1 3",
+        "prioritize 1 2",
+        "peek",
+        "priority 1 high",
+        "mode deque",
+    ])
+    .into_sigi("_integ::mode_interactive", &["-d", &dir, "interactive"]);
+    res.assert_stdout_line_starts_with("Mode: priority");
+    res.assert_stdout_line_starts_with("Priority: sooner");
+    res.assert_stdout_line_starts_with("Priority: later");
+    res.assert_stdout_line_starts_with("Now: later");
+    res.assert_stdout_line_starts_with("\"high\" isn't a priority");
+    res.assert_stdout_line_starts_with("\"deque\" isn't a stack mode");
+}
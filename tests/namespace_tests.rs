@@ -0,0 +1,61 @@
+mod run_sigi;
+
+use run_sigi::{fresh_dir, sigi};
+
+#[test]
+fn sigi_nested_stacks_are_stored_in_subdirectories() {
+    let dir = fresh_dir("sigi_integ_namespace_storage");
+
+    sigi("work/backend/bugs", &["-d", &dir, "push", "fix it"]).assert_success();
+    assert!(std::path::Path::new(&format!("{}/work/backend/bugs.json", dir)).exists());
+
+    let res = sigi("work/backend/bugs", &["-d", &dir, "peek"]);
+    res.assert_stdout_eq("Now: fix it\n");
+}
+
+#[test]
+fn sigi_list_stacks_as_tree() {
+    let dir = fresh_dir("sigi_integ_namespace_tree");
+
+    for stack in ["work/backend/bugs", "work/frontend", "home", "work"] {
+        sigi(stack, &["-d", &dir, "push", "something"]).assert_success();
+    }
+
+    let res = sigi("home", &["-d", &dir, "list-stacks"]);
+    res.assert_success();
+    res.assert_stdout_eq("home\nwork\n  backend/\n    bugs\n  frontend\n");
+
+    // Scripts get the full names.
+    let res = sigi("home", &["-d", &dir, "list-stacks", "-q"]);
+    res.assert_stdout_eq("home\nwork\nwork/backend/bugs\nwork/frontend\n");
+}
+
+#[test]
+fn sigi_count_and_list_recursively() {
+    let dir = fresh_dir("sigi_integ_namespace_recursive");
+
+    sigi("work", &["-d", &dir, "push", "plan"]).assert_success();
+    sigi("work/bugs", &["-d", &dir, "push", "crash"]).assert_success();
+    sigi("work/bugs", &["-d", &dir, "push", "typo"]).assert_success();
+    sigi("work/bugs", &["-d", &dir, "complete"]).assert_success();
+    sigi("workshop", &["-d", &dir, "push", "not in work"]).assert_success();
+
+    let res = sigi("work", &["-d", &dir, "count"]);
+    res.assert_stdout_eq("1\n");
+    let res = sigi("work", &["-d", &dir, "count", "--recursive"]);
+    res.assert_stdout_eq("2\n");
+
+    let res = sigi("work", &["-d", &dir, "list", "-r"]);
+    res.assert_success();
+    res.assert_stdout_eq("work:\nNow: plan\nwork/bugs:\nNow: crash\n");
+
+    let res = sigi("work", &["-d", &dir, "list", "-r", "-q"]);
+    res.assert_stdout_eq("plan\ncrash\n");
+
+    let res = sigi("work", &["-d", &dir, "list", "-r", "-f", "csv"]);
+    let lines = res.stdout().lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3, "{:?}", lines);
+    assert!(lines[0].starts_with("stack,position,item,"));
+    assert!(lines[1].starts_with("work,0,plan,"));
+    assert!(lines[2].starts_with("work/bugs,0,crash,"));
+}
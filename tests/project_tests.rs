@@ -0,0 +1,55 @@
+mod run_sigi;
+
+use run_sigi::{fresh_dir, SigiOutput, SIGI_PATH};
+use std::process::Command;
+
+/// Run sigi from the given directory, with no other store chosen.
+fn sigi_in(dir: &str, args: &[&str]) -> SigiOutput {
+    Command::new(SIGI_PATH)
+        .current_dir(dir)
+        .env("SIGI_CONFIG", "/dev/null")
+        .env_remove("SIGI_HOME")
+        .args(args)
+        .output()
+        .expect("Error running process")
+        .into()
+}
+
+#[test]
+fn sigi_init_and_discover_project_store() {
+    let dir = fresh_dir("sigi_integ_project");
+    let nested = format!("{}/src/deeply/nested", dir);
+    std::fs::create_dir_all(&nested).unwrap();
+
+    let res = sigi_in(&dir, &["init"]);
+    res.assert_success();
+    res.assert_stdout_eq(&format!("Initialized: {}/.sigi\n", dir));
+
+    let res = sigi_in(&dir, &["init"]);
+    res.assert_stdout_eq(&format!("Already initialized: {}/.sigi\n", dir));
+
+    // Found from anywhere inside the project.
+    sigi_in(&nested, &["-t", "bugs", "push", "fix it"]).assert_success();
+    assert!(std::path::Path::new(&format!("{}/.sigi/bugs.json", dir)).exists());
+
+    let res = sigi_in(&dir, &["-t", "bugs", "peek"]);
+    res.assert_stdout_eq("Now: fix it\n");
+
+    let res = sigi_in(&nested, &["-v", "list-stacks"]);
+    res.assert_stdout_lines_eq(&[&format!("Store: {}/.sigi", dir), "bugs"]);
+
+    // An explicit data store still wins.
+    let other = format!("{}/other", dir);
+    let res = sigi_in(&nested, &["-d", &other, "-t", "bugs", "-q", "count"]);
+    res.assert_stdout_eq("0\n");
+}
+
+#[test]
+fn sigi_init_elsewhere() {
+    let dir = fresh_dir("sigi_integ_project_elsewhere");
+
+    let res = sigi_in(&dir, &["-f", "csv", "init", "sub"]);
+    res.assert_success();
+    res.assert_stdout_eq(&format!("action,store\nInitialized,{}/sub/.sigi\n", dir));
+    assert!(std::path::Path::new(&format!("{}/sub/.sigi", dir)).is_dir());
+}
@@ -0,0 +1,168 @@
+mod run_sigi;
+
+use run_sigi::{dir_with_files, piping, sigi};
+
+/// A few days of work, written out directly so the times are known.
+const STACKS: [(&str, &str); 5] = [
+    (
+        "work.json",
+        r#"[{"id": "f1", "contents": "fix the bug", "history": [
+            {"event": "created", "time": "2024-06-02T10:00:00+00:00"}
+        ]}]"#,
+    ),
+    (
+        "work_history.json",
+        r#"[{"id": "r1", "contents": "write report", "history": [
+            {"event": "created", "time": "2024-06-01T09:00:00+00:00"},
+            {"event": "completed", "time": "2024-06-03T17:00:00+00:00"}
+        ]}]"#,
+    ),
+    (
+        "home_history.json",
+        r#"[{"id": "m1", "contents": "buy milk", "history": [
+            {"event": "created", "time": "2024-06-01T12:00:00+00:00"},
+            {"event": "deleted", "time": "2024-06-01T13:00:00+00:00"}
+        ]}]"#,
+    ),
+    (
+        "later.json",
+        r#"[{"id": "p1", "contents": "plan", "history": [
+            {"event": "created", "time": "2024-06-01T08:00:00+00:00"},
+            {"event": "moved", "from": "work", "to": "later",
+             "time": "2024-06-03T12:00:00+00:00"}
+        ]}]"#,
+    ),
+    (
+        "_archive/old.json",
+        r#"[{"id": "o1", "contents": "forgotten", "history": [
+            {"event": "created", "time": "2024-06-02T12:00:00+00:00"}
+        ]}]"#,
+    ),
+];
+
+/// Show times the same way wherever the tests run.
+fn report_args<'a>(dir: &'a str, args: &[&'a str]) -> Vec<&'a str> {
+    let mut all = vec!["-d", dir, "--tz", "utc", "--locale", "en_US", "report"];
+    all.extend_from_slice(args);
+    all
+}
+
+#[test]
+fn sigi_report_by_day_and_stack() {
+    let dir = dir_with_files("sigi_integ_report_human", &STACKS);
+
+    let res = sigi(
+        "work",
+        &report_args(&dir, &["--since", "2024-06-01T00:00:00+00:00"]),
+    );
+    res.assert_success();
+    res.assert_stdout_eq(
+        "Saturday 2024-06-01\n\
+         \x20 home:\n\
+         \x20   Created: buy milk\n\
+         \x20   Deleted: buy milk\n\
+         \x20 work:\n\
+         \x20   Created: plan\n\
+         \x20   Created: write report\n\
+         Sunday 2024-06-02\n\
+         \x20 work:\n\
+         \x20   Created: fix the bug\n\
+         Monday 2024-06-03\n\
+         \x20 work:\n\
+         \x20   Completed: write report\n",
+    );
+
+    let res = sigi(
+        "work",
+        &report_args(&dir, &["--since", "2024-06-03T00:00:00+00:00", "-q"]),
+    );
+    res.assert_stdout_eq("write report\n");
+
+    let res = sigi("work", &report_args(&dir, &[]));
+    res.assert_stdout_eq("Report: NOTHING\n");
+}
+
+#[test]
+fn sigi_report_for_some_stacks() {
+    let dir = dir_with_files("sigi_integ_report_stacks", &STACKS);
+
+    let res = sigi(
+        "work",
+        &report_args(
+            &dir,
+            &[
+                "--since",
+                "2024-06-02T00:00:00+00:00",
+                "--stacks",
+                "home,_archive",
+            ],
+        ),
+    );
+    res.assert_stdout_eq("Sunday 2024-06-02\n  _archive/old:\n    Created: forgotten\n");
+}
+
+#[test]
+fn sigi_report_as_markdown_and_json() {
+    let dir = dir_with_files("sigi_integ_report_formats", &STACKS);
+
+    let res = sigi(
+        "work",
+        &[
+            "-d",
+            &dir,
+            "--tz",
+            "utc",
+            "--locale",
+            "en_US",
+            "--time-format",
+            "%H:%M",
+            "report",
+            "--since",
+            "2024-06-02T00:00:00+00:00",
+            "-f",
+            "markdown",
+        ],
+    );
+    res.assert_stdout_eq(
+        "## Sunday 2024-06-02\n\
+         \n\
+         ### work\n\
+         \n\
+         | action | item | time | id |\n\
+         | --- | --- | --- | --- |\n\
+         | Created | fix the bug | 10:00 | f1 |\n\
+         \n\
+         ## Monday 2024-06-03\n\
+         \n\
+         ### work\n\
+         \n\
+         | action | item | time | id |\n\
+         | --- | --- | --- | --- |\n\
+         | Completed | write report | 17:00 | r1 |\n",
+    );
+
+    let res = sigi(
+        "work",
+        &report_args(
+            &dir,
+            &["--since", "2024-06-03T00:00:00+00:00", "-f", "json-compact"],
+        ),
+    );
+    res.assert_stdout_eq(
+        "[{\"day\":\"2024-06-03\",\"stack\":\"work\",\"action\":\"Completed\",\
+         \"item\":\"write report\",\"time\":\"2024-06-03T17:00:00+00:00\",\"id\":\"r1\"}]\n",
+    );
+}
+
+#[test]
+fn sigi_report_in_interactive_mode() {
+    let dir = dir_with_files("sigi_integ_report_interactive", &STACKS);
+
+    let res = piping(&[
+        "report --since 2024-06-01T00:00:00+00:00 --stacks home",
+        "standup --sinse yesterday",
+    ])
+    .into_sigi("work", &["-d", &dir, "interactive"]);
+    res.assert_stdout_line_starts_with("    Deleted: buy milk");
+    res.assert_stdout_line_starts_with("\"--sinse\" isn't an option of report");
+}
@@ -0,0 +1,54 @@
+mod run_sigi;
+
+use run_sigi::{fresh_dir, piping, sigi};
+
+#[test]
+fn sigi_restore() {
+    let dir = fresh_dir("sigi_integ_restore");
+    let stack = "_integ::restore";
+
+    sigi(stack, &["-d", &dir, "push", "a"]).assert_success();
+    sigi(stack, &["-d", &dir, "push", "b"]).assert_success();
+    sigi(stack, &["-d", &dir, "complete"]).assert_success();
+    sigi(stack, &["-d", &dir, "delete"]).assert_success();
+
+    let res = sigi(stack, &["-d", &dir, "restore", "1"]);
+    res.assert_success();
+    res.assert_stdout_lines_eq(&["Restored: b", "Now: b"]);
+    res.assert_stderr_empty();
+
+    let res = sigi(stack, &["-d", &dir, "restore"]);
+    res.assert_success();
+    res.assert_stdout_lines_eq(&["Restored: a", "Now: a"]);
+    res.assert_stderr_empty();
+
+    let res = sigi(stack, &["-d", &dir, "list"]);
+    res.assert_stdout_lines_eq(&["Now: a", "  1: b"]);
+
+    let res = sigi(stack, &["-d", &dir, "restore"]);
+    res.assert_success();
+    res.assert_stdout_eq("Now: a\n");
+
+    // Both items are back on the stack, and none are left in the history.
+    let res = sigi(stack, &["-d", &dir, "-f", "json", "list"]);
+    res.assert_success();
+    let items = res
+        .stdout()
+        .lines()
+        .filter(|line| line.trim_start().starts_with("\"item\""))
+        .collect::<Vec<_>>();
+    assert_eq!(items, vec![r#"    "item": "a","#, r#"    "item": "b","#]);
+    let res = sigi(&format!("{}_history", stack), &["-d", &dir, "count"]);
+    res.assert_stdout_eq("0\n");
+}
+
+#[test]
+fn sigi_restore_from_stdin() {
+    let dir = fresh_dir("sigi_integ_restore_stdin");
+    let stack = "_integ::restore_stdin";
+
+    let res =
+        piping(&["push hello", "delete", "restore", "peek"]).into_sigi(stack, &["-d", &dir, "-"]);
+    res.assert_stdout_eq("hello\n");
+    res.assert_stderr_empty();
+}
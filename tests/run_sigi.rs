@@ -1,16 +1,68 @@
-use std::io::{Read, Write};
+//! Helpers for running sigi in integration tests. Each test binary includes
+//! this with `mod run_sigi;` and uses only some of it; the helpers are tested
+//! once, in `run_sigi_tests.rs`.
+#![allow(dead_code)]
+
+use std::io::Write;
 use std::process::{Command, Output, Stdio};
 
 pub const SIGI_PATH: &str = std::env!("CARGO_BIN_EXE_sigi");
 
+/// An empty directory for a test's data store, made afresh each run.
+pub fn fresh_dir(name: &str) -> String {
+    let dir = std::env::temp_dir().join(name);
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir.canonicalize().unwrap().to_string_lossy().to_string()
+}
+
+/// A fresh directory holding these files, given as (path, contents), like
+/// stacks with a made-up history.
+pub fn dir_with_files(name: &str, files: &[(&str, &str)]) -> String {
+    let dir = fresh_dir(name);
+    for (file, contents) in files {
+        let path = std::path::Path::new(&dir).join(file);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, contents).unwrap();
+    }
+    dir
+}
+
+/// The file a stack is stored in, in a JSON data store. Test stack names,
+/// like `_integ::abc`, have colons in them, which file names escape.
+pub fn stack_file(dir: &str, stack: &str) -> String {
+    format!("{}/{}.json", dir, stack.replace(':', "%3A"))
+}
+
+/// An empty config file, so a config file on the machine running the tests
+/// doesn't change what sigi does.
+pub fn empty_config() -> String {
+    let path = std::env::temp_dir().join("sigi_integ_empty_config.toml");
+    std::fs::write(&path, "").unwrap();
+    path.to_string_lossy().to_string()
+}
+
 pub fn sigi(stack: &str, args: &[&str]) -> SigiOutput {
-    return Command::new(SIGI_PATH)
+    Command::new(SIGI_PATH)
+        .env("SIGI_CONFIG", empty_config())
         .arg("--stack")
         .arg(stack)
         .args(args)
         .output()
         .expect("Error running process")
-        .into();
+        .into()
+}
+
+pub fn sigi_with_env(stack: &str, env: &[(&str, &str)], args: &[&str]) -> SigiOutput {
+    Command::new(SIGI_PATH)
+        .env("SIGI_CONFIG", empty_config())
+        .envs(env.iter().copied())
+        .arg("--stack")
+        .arg(stack)
+        .args(args)
+        .output()
+        .expect("Error running process")
+        .into()
 }
 
 pub fn piping(lines: &[&str]) -> SigiInput {
@@ -25,9 +77,20 @@ pub struct SigiInput {
 
 impl SigiInput {
     pub fn into_sigi(self, stack: &str, args: &[&str]) -> SigiOutput {
+        self.into_sigi_with_env(stack, &[], args)
+    }
+
+    pub fn into_sigi_with_env(
+        self,
+        stack: &str,
+        env: &[(&str, &str)],
+        args: &[&str],
+    ) -> SigiOutput {
         let stdin = self.stdin.join("\n");
 
-        let process = Command::new(SIGI_PATH)
+        let mut process = Command::new(SIGI_PATH)
+            .env("SIGI_CONFIG", empty_config())
+            .envs(env.iter().copied())
             .arg("--stack")
             .arg(stack)
             .args(args)
@@ -39,32 +102,31 @@ impl SigiInput {
 
         process
             .stdin
+            .take()
             .expect("Error sending stdin to sigi")
             .write_all(stdin.as_bytes())
             .unwrap();
 
-        let mut stdout = String::new();
-        process.stdout.unwrap().read_to_string(&mut stdout).unwrap();
-
-        let mut stderr = String::new();
-        process.stderr.unwrap().read_to_string(&mut stderr).unwrap();
+        let output = process.wait_with_output().expect("Error running process");
 
         SigiOutput {
             status: SigiStatus::Unknown,
-            stdout,
-            stderr,
+            code: None,
+            stdout: String::from_utf8(output.stdout).expect("Couldn't read stdout"),
+            stderr: String::from_utf8(output.stderr).expect("Couldn't read stderr"),
         }
     }
 }
 
 pub struct SigiOutput {
-    status: SigiStatus,
-    stdout: String,
-    stderr: String,
+    pub(crate) status: SigiStatus,
+    pub(crate) code: Option<i32>,
+    pub(crate) stdout: String,
+    pub(crate) stderr: String,
 }
 
 #[derive(Debug, PartialEq)]
-enum SigiStatus {
+pub(crate) enum SigiStatus {
     Success,
     Failure,
     Unknown,
@@ -88,6 +150,15 @@ impl SigiOutput {
         assert_eq!(self.status, SigiStatus::Failure);
     }
 
+    pub fn assert_exit_code(&self, expected_code: i32) {
+        assert_eq!(
+            self.code,
+            Some(expected_code),
+            "sigi exited with an unexpected code.\n{}",
+            self.stderr_for_errors()
+        );
+    }
+
     pub fn assert_stdout_eq(&self, expected_stdout: &str) {
         assert_eq!(
             &self.stdout,
@@ -140,6 +211,15 @@ impl SigiOutput {
         );
     }
 
+    pub fn assert_stderr_starts_with(&self, expected_prefix: &str) {
+        assert!(
+            self.stderr.starts_with(expected_prefix),
+            "sigi stderr did not start with: {}\n{}",
+            expected_prefix,
+            self.stderr_for_errors()
+        );
+    }
+
     pub fn assert_stderr_empty(&self) {
         assert_eq!(
             &self.stderr,
@@ -149,6 +229,10 @@ impl SigiOutput {
         );
     }
 
+    pub fn stdout(&self) -> &str {
+        &self.stdout
+    }
+
     fn stdout_for_errors(&self) -> String {
         format!(
             "===\nstdout:\n===\n{}\n===\n",
@@ -172,93 +256,9 @@ impl From<Output> for SigiOutput {
             } else {
                 SigiStatus::Failure
             },
+            code: output.status.code(),
             stdout: String::from_utf8(output.stdout).expect("Couldn't read stdout"),
             stderr: String::from_utf8(output.stderr).expect("Couldn't read stderr"),
         }
     }
 }
-
-#[test]
-fn assert_success() {
-    let output = SigiOutput {
-        status: true.into(),
-        stdout: String::new(),
-        stderr: String::new(),
-    };
-
-    output.assert_success();
-}
-
-#[test]
-fn assert_failure() {
-    let output = SigiOutput {
-        status: false.into(),
-        stdout: String::new(),
-        stderr: String::new(),
-    };
-
-    output.assert_failure();
-}
-
-#[test]
-fn assert_stdout_eq() {
-    let output = SigiOutput {
-        status: true.into(),
-        stdout: "hello".to_string(),
-        stderr: String::new(),
-    };
-
-    output.assert_stdout_eq("hello");
-}
-
-#[test]
-fn assert_stdout_line_eq() {
-    let output = SigiOutput {
-        status: true.into(),
-        stdout: "hey\nhello".to_string(),
-        stderr: String::new(),
-    };
-
-    output.assert_stdout_line_eq("hello");
-}
-
-#[test]
-fn assert_stdout_lines_eq() {
-    let output = SigiOutput {
-        status: true.into(),
-        stdout: "hey\nhello there".to_string(),
-        stderr: String::new(),
-    };
-
-    output.assert_stdout_lines_eq(&["hey", "hello there"]);
-}
-
-#[test]
-fn assert_stdout_line_starts_with() {
-    let output = SigiOutput {
-        status: true.into(),
-        stdout: "hey\nhello there".to_string(),
-        stderr: String::new(),
-    };
-
-    output.assert_stdout_line_starts_with("hello");
-}
-
-#[test]
-fn assert_stderr_empty() {
-    let output = SigiOutput {
-        status: true.into(),
-        stdout: "hey\nhello there".to_string(),
-        stderr: String::new(),
-    };
-
-    output.assert_stderr_empty();
-}
-
-#[test]
-fn sigi_piping_basic() {
-    let res = piping(&[]).into_sigi("_integ::basic", &["interactive"]);
-    assert_eq!(res.status, SigiStatus::Unknown);
-    res.assert_stdout_line_starts_with("sigi 3.7");
-    res.assert_stderr_empty();
-}
@@ -0,0 +1,161 @@
+mod run_sigi;
+
+use run_sigi::{dir_with_files, fresh_dir, piping, sigi, sigi_with_env, stack_file};
+use run_sigi::{SigiOutput, SigiStatus};
+
+#[test]
+fn assert_success() {
+    let output = SigiOutput {
+        status: true.into(),
+        code: Some(0),
+        stdout: String::new(),
+        stderr: String::new(),
+    };
+
+    output.assert_success();
+}
+
+#[test]
+fn assert_failure() {
+    let output = SigiOutput {
+        status: false.into(),
+        code: Some(1),
+        stdout: String::new(),
+        stderr: String::new(),
+    };
+
+    output.assert_failure();
+}
+
+#[test]
+fn assert_stdout_eq() {
+    let output = SigiOutput {
+        status: true.into(),
+        code: Some(0),
+        stdout: "hello".to_string(),
+        stderr: String::new(),
+    };
+
+    output.assert_stdout_eq("hello");
+}
+
+#[test]
+fn stdout() {
+    let output = SigiOutput {
+        status: true.into(),
+        code: Some(0),
+        stdout: "hello".to_string(),
+        stderr: String::new(),
+    };
+
+    assert_eq!(output.stdout(), "hello");
+}
+
+#[test]
+fn assert_stdout_line_eq() {
+    let output = SigiOutput {
+        status: true.into(),
+        code: Some(0),
+        stdout: "hey\nhello".to_string(),
+        stderr: String::new(),
+    };
+
+    output.assert_stdout_line_eq("hello");
+}
+
+#[test]
+fn assert_stdout_lines_eq() {
+    let output = SigiOutput {
+        status: true.into(),
+        code: Some(0),
+        stdout: "hey\nhello there".to_string(),
+        stderr: String::new(),
+    };
+
+    output.assert_stdout_lines_eq(&["hey", "hello there"]);
+}
+
+#[test]
+fn assert_stdout_line_starts_with() {
+    let output = SigiOutput {
+        status: true.into(),
+        code: Some(0),
+        stdout: "hey\nhello there".to_string(),
+        stderr: String::new(),
+    };
+
+    output.assert_stdout_line_starts_with("hello");
+}
+
+#[test]
+fn assert_exit_code() {
+    let output = SigiOutput {
+        status: false.into(),
+        code: Some(65),
+        stdout: String::new(),
+        stderr: String::new(),
+    };
+
+    output.assert_exit_code(65);
+}
+
+#[test]
+fn assert_stderr_starts_with() {
+    let output = SigiOutput {
+        status: false.into(),
+        code: Some(1),
+        stdout: String::new(),
+        stderr: "Error: oh no".to_string(),
+    };
+
+    output.assert_stderr_starts_with("Error:");
+}
+
+#[test]
+fn assert_stderr_empty() {
+    let output = SigiOutput {
+        status: true.into(),
+        code: Some(0),
+        stdout: "hey\nhello there".to_string(),
+        stderr: String::new(),
+    };
+
+    output.assert_stderr_empty();
+}
+
+#[test]
+fn sigi_basic() {
+    let res = sigi("_integ::basic", &["--version"]);
+    res.assert_success();
+    res.assert_stdout_line_starts_with("sigi 3.7");
+}
+
+#[test]
+fn sigi_with_env_basic() {
+    let res = sigi_with_env("_integ::basic", &[("SIGI_TZ", "nowhere")], &["peek"]);
+    res.assert_exit_code(78);
+    res.assert_stderr_starts_with("Error: Invalid SIGI_TZ");
+}
+
+#[test]
+fn sigi_piping_basic() {
+    let res = piping(&[]).into_sigi("_integ::basic", &["interactive"]);
+    assert_eq!(res.status, SigiStatus::Unknown);
+    res.assert_stdout_line_starts_with("sigi 3.7");
+    res.assert_stderr_empty();
+}
+
+#[test]
+fn stack_file_basic() {
+    let dir = fresh_dir("sigi_integ_stack_file");
+    sigi("_integ::basic", &["-d", &dir, "push", "a"]).assert_success();
+    assert!(std::path::Path::new(&stack_file(&dir, "_integ::basic")).exists());
+}
+
+#[test]
+fn dir_with_files_basic() {
+    let dir = dir_with_files("sigi_integ_dir_with_files", &[("ns/basic.json", "[]")]);
+    let res = sigi("ns/basic", &["-d", &dir, "count"]);
+    res.assert_success();
+    res.assert_stdout_eq("0\n");
+}
@@ -0,0 +1,87 @@
+mod run_sigi;
+
+use run_sigi::{fresh_dir, piping, sigi};
+
+/// A few stacks to search, with one item done and one with a body.
+fn searchable_dir(name: &str) -> String {
+    let dir = fresh_dir(name);
+    sigi("work", &["-d", &dir, "push", "Fix the login bug"]).assert_success();
+    sigi("work", &["-d", &dir, "push", "Write docs"]).assert_success();
+    sigi("work/ui", &["-d", &dir, "push", "+css", "Tidy styles"]).assert_success();
+    sigi("home", &["-d", &dir, "push", "Buy milk"]).assert_success();
+    sigi("home", &["-d", &dir, "complete"]).assert_success();
+    sigi("home", &["-d", &dir, "push", "Call plumber"]).assert_success();
+    // An "editor" that replaces the body with some text.
+    let source = format!("{}/body.txt", dir);
+    std::fs::write(&source, "check the login page\n").unwrap();
+    let editor = format!("cp {}", source);
+    sigi("home", &["-d", &dir, "edit", "--body", "-e", &editor]).assert_success();
+    dir
+}
+
+#[test]
+fn sigi_search_across_stacks() {
+    let dir = searchable_dir("sigi_integ_search_basic");
+
+    let res = sigi("home", &["-d", &dir, "search", "login"]);
+    res.assert_success();
+    res.assert_stdout_eq("home: Call plumber\nwork: Fix the login bug\n");
+
+    let res = sigi("home", &["-d", &dir, "search", "LOGIN"]);
+    res.assert_stdout_eq("Found: NOTHING\n");
+    let res = sigi(
+        "home",
+        &["-d", &dir, "search", "-i", "LOGIN", "--field", "headline"],
+    );
+    res.assert_stdout_eq("work: Fix the login bug\n");
+
+    // Plain text is plain; regular expressions are asked for.
+    let res = sigi("home", &["-d", &dir, "search", "W.ite"]);
+    res.assert_stdout_eq("Found: NOTHING\n");
+    let res = sigi("home", &["-d", &dir, "search", "-E", "^(W.ite|Tidy)"]);
+    res.assert_stdout_eq("work: Write docs\nwork/ui: Tidy styles\n");
+
+    let res = sigi("home", &["-d", &dir, "search", "-E", "("]);
+    res.assert_exit_code(64);
+    res.assert_stderr_starts_with("Error: Can't search for \"(\":");
+}
+
+#[test]
+fn sigi_search_history_stacks_and_fields() {
+    let dir = searchable_dir("sigi_integ_search_scope");
+
+    let res = sigi("home", &["-d", &dir, "search", "milk"]);
+    res.assert_stdout_eq("Found: NOTHING\n");
+    let res = sigi("home", &["-d", &dir, "search", "--history", "milk"]);
+    res.assert_stdout_eq("home_history: Buy milk\n");
+
+    let res = sigi("home", &["-d", &dir, "search", "--in", "work", "-i", "t"]);
+    res.assert_stdout_eq("work: Write docs\nwork: Fix the login bug\nwork/ui: Tidy styles\n");
+
+    let res = sigi(
+        "home",
+        &["-d", &dir, "search", "--field", "tags", "css", "-f", "csv"],
+    );
+    let lines = res.stdout().lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 2, "{:?}", lines);
+    assert_eq!(lines[0], "stack,item,position,created,id");
+    assert!(
+        lines[1].starts_with("work/ui,Tidy styles,0,"),
+        "{}",
+        lines[1]
+    );
+}
+
+#[test]
+fn sigi_search_in_interactive_mode() {
+    let dir = searchable_dir("sigi_integ_search_interactive");
+
+    let res = piping(&[
+        "search -i --in work LOGIN",
+        "find --field nowhere x",
+        "grep",
+    ])
+    .into_sigi("home", &["-d", &dir, "interactive"]);
+    res.assert_stdout_line_starts_with("work: Fix the login bug");
+    res.assert_stdout_line_starts_with("\"nowhere\" isn't a field sigi can search");
+}
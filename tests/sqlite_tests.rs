@@ -0,0 +1,43 @@
+mod run_sigi;
+
+use run_sigi::sigi;
+
+#[test]
+fn sigi_sqlite_basic_ops() {
+    let dir = std::env::temp_dir().join("sigi_integ_sqlite");
+    let _ = std::fs::remove_dir_all(&dir);
+    let dir = dir.to_string_lossy().to_string();
+    let stack = "_integ::sqlite";
+    let sqlite = |args: &[&str]| {
+        let args = [&["--data-format", "sqlite", "-d", &dir], args].concat();
+        sigi(stack, &args)
+    };
+
+    let res = sqlite(&["push", "a"]);
+    res.assert_success();
+    res.assert_stdout_eq("Created: a\n");
+    res.assert_stderr_empty();
+
+    let res = sqlite(&["push", "b"]);
+    res.assert_success();
+
+    let res = sqlite(&["list"]);
+    res.assert_success();
+    res.assert_stdout_lines_eq(&["Now: b", "  1: a"]);
+    res.assert_stderr_empty();
+
+    let res = sqlite(&["complete"]);
+    res.assert_success();
+    res.assert_stdout_lines_eq(&["Completed: b", "Now: a"]);
+    res.assert_stderr_empty();
+
+    let res = sqlite(&["list-stacks", "--history", "-q"]);
+    res.assert_success();
+    res.assert_stdout_lines_eq(&["_integ::sqlite", "_integ::sqlite_history"]);
+    res.assert_stderr_empty();
+
+    let res = sqlite(&["count"]);
+    res.assert_success();
+    res.assert_stdout_eq("1\n");
+    res.assert_stderr_empty();
+}
@@ -0,0 +1,189 @@
+mod run_sigi;
+
+use run_sigi::{fresh_dir, piping, sigi, stack_file};
+
+#[test]
+fn sigi_rename_stack_with_history() {
+    let dir = fresh_dir("sigi_integ_rename_stack");
+    let stack = "_integ::rename";
+    let dest = "_integ::renamed";
+
+    sigi(stack, &["-d", &dir, "push", "done"]).assert_success();
+    sigi(stack, &["-d", &dir, "complete"]).assert_success();
+    sigi(stack, &["-d", &dir, "push", "to do"]).assert_success();
+
+    let res = sigi(stack, &["-d", &dir, "rename-stack", dest]);
+    res.assert_success();
+    res.assert_stdout_eq("Renamed: _integ::renamed\n");
+
+    let res = sigi(stack, &["-d", &dir, "list-stacks", "--history", "-q"]);
+    res.assert_stdout_eq("_integ::renamed\n_integ::renamed_history\n");
+    let res = sigi(dest, &["-d", &dir, "peek"]);
+    res.assert_stdout_eq("Now: to do\n");
+
+    // Renaming again, or onto a stack that has items, is an error.
+    let res = sigi(stack, &["-d", &dir, "rename-stack", dest]);
+    res.assert_exit_code(66);
+    sigi(stack, &["-d", &dir, "push", "new"]).assert_success();
+    let res = sigi(stack, &["-d", &dir, "rename-stack", dest]);
+    res.assert_exit_code(73);
+    res.assert_stderr_starts_with("Error: A stack named \"_integ::renamed\" already exists");
+}
+
+#[test]
+fn sigi_copy_stack() {
+    let dir = fresh_dir("sigi_integ_copy_stack");
+    let stack = "_integ::copy";
+    let dest = "_integ::copied";
+
+    sigi(stack, &["-d", &dir, "push", "shared"]).assert_success();
+    let res = sigi(stack, &["-d", &dir, "copy-stack", dest]);
+    res.assert_success();
+    res.assert_stdout_eq("Copied: _integ::copied\n");
+
+    let mut ids = vec![];
+    for stack in [stack, dest] {
+        let res = sigi(stack, &["-d", &dir, "peek"]);
+        res.assert_stdout_eq("Now: shared\n");
+        let res = sigi(stack, &["-d", &dir, "-f", "tsv", "list"]);
+        let id = res.stdout().lines().nth(1).unwrap().split('\t').nth(3);
+        ids.push(id.unwrap().to_string());
+    }
+
+    // Copies get IDs of their own, and remember the item they were copied from.
+    assert_ne!(ids[0], ids[1]);
+    let copy = std::fs::read_to_string(stack_file(&dir, dest)).unwrap();
+    assert!(copy.contains("\"copied\""), "{}", copy);
+    assert!(copy.contains(&format!("\"{}\"", ids[0])), "{}", copy);
+}
+
+#[test]
+fn sigi_delete_stack_and_undo() {
+    let dir = fresh_dir("sigi_integ_delete_stack");
+    let stack = "work/old";
+
+    sigi(stack, &["-d", &dir, "push", "done"]).assert_success();
+    sigi(stack, &["-d", &dir, "complete"]).assert_success();
+    sigi(stack, &["-d", &dir, "push", "forgotten"]).assert_success();
+
+    let res = sigi(stack, &["-d", &dir, "delete-stack"]);
+    res.assert_success();
+    res.assert_stdout_eq("Deleted stack: work/old\n");
+    let res = sigi(stack, &["-d", &dir, "list-stacks"]);
+    res.assert_stdout_eq("");
+    // The emptied namespace goes too.
+    assert!(!std::path::Path::new(&format!("{}/work", dir)).exists());
+
+    let res = sigi(stack, &["-d", &dir, "delete-stack"]);
+    res.assert_exit_code(66);
+
+    sigi(stack, &["-d", &dir, "undo"]).assert_success();
+    let res = sigi(stack, &["-d", &dir, "peek"]);
+    res.assert_stdout_eq("Now: forgotten\n");
+}
+
+#[test]
+fn sigi_archive_stack() {
+    let dir = fresh_dir("sigi_integ_archive_stack");
+
+    sigi("current", &["-d", &dir, "push", "keep"]).assert_success();
+    sigi("stale", &["-d", &dir, "push", "someday"]).assert_success();
+
+    let res = sigi("stale", &["-d", &dir, "archive-stack"]);
+    res.assert_success();
+    res.assert_stdout_eq("Archived: stale\n");
+
+    let res = sigi("current", &["-d", &dir, "list-stacks"]);
+    res.assert_stdout_eq("current\n");
+    let res = sigi("current", &["-d", &dir, "list-stacks", "--archived", "-q"]);
+    res.assert_stdout_eq("_archive/stale\n");
+
+    // Nothing is lost; an archived stack can be renamed back.
+    sigi("_archive/stale", &["-d", &dir, "rename-stack", "stale"]).assert_success();
+    let res = sigi("stale", &["-d", &dir, "peek"]);
+    res.assert_stdout_eq("Now: someday\n");
+}
+
+#[test]
+fn sigi_stack_management_in_interactive_mode() {
+    let dir = fresh_dir("sigi_integ_stack_management_interactive");
+    let stack = "_integ::manage";
+
+    let res = piping(&[
+        "push hello",
+        "copy-stack _integ::manage2",
+        "archive-stack",
+        "use _integ::manage2",
+        "rename-stack _integ::manage3",
+        "use _integ::manage3",
+        "delete-stack",
+        "list-stacks --archived",
+    ])
+    .into_sigi(stack, &["-d", &dir, "interactive"]);
+    res.assert_stderr_empty();
+    res.assert_stdout_line_starts_with("Copied: _integ::manage2");
+    res.assert_stdout_line_starts_with("Archived: _integ::manage");
+    res.assert_stdout_line_starts_with("Renamed: _integ::manage3");
+    res.assert_stdout_line_starts_with("Deleted stack: _integ::manage3");
+    res.assert_stdout_line_starts_with("_archive/");
+}
+
+#[test]
+fn sigi_list_stacks_hides_history() {
+    let dir = fresh_dir("sigi_integ_list_stacks_history");
+
+    for stack in ["work", "work/bugs"] {
+        sigi(stack, &["-d", &dir, "push", "done"]).assert_success();
+        sigi(stack, &["-d", &dir, "complete"]).assert_success();
+    }
+    sigi("work-later", &["-d", &dir, "push", "someday"]).assert_success();
+
+    let res = sigi("work", &["-d", &dir, "list-stacks"]);
+    res.assert_stdout_eq("work\n  bugs\nwork-later\n");
+
+    // Each history is grouped with the stack it belongs to.
+    let res = sigi("work", &["-d", &dir, "list-stacks", "--history"]);
+    res.assert_stdout_eq("work\n  work_history\n  bugs\n    bugs_history\nwork-later\n");
+    let res = sigi("work", &["-d", &dir, "list-stacks", "--history", "-q"]);
+    res.assert_stdout_eq("work\nwork_history\nwork/bugs\nwork/bugs_history\nwork-later\n");
+}
+
+#[test]
+fn sigi_list_stacks_summary() {
+    let dir = fresh_dir("sigi_integ_list_stacks_summary");
+
+    sigi("chores", &["-d", &dir, "push", "dishes"]).assert_success();
+    sigi("chores", &["-d", &dir, "push", "laundry"]).assert_success();
+    sigi("chores", &["-d", &dir, "complete"]).assert_success();
+    sigi("chores", &["-d", &dir, "push", "sweep"]).assert_success();
+    sigi("empty", &["-d", &dir, "delete-all"]).assert_success();
+
+    // A time format with no times in it keeps the output the same every run.
+    let res = sigi(
+        "chores",
+        &["-d", &dir, "--time-format=%%", "list-stacks", "--summary"],
+    );
+    res.assert_success();
+    res.assert_stdout_eq(
+        "chores: 2 items, now: sweep, newest %, oldest %, 1 completed in 7 days\n\
+         empty: 0 items, 0 completed in 7 days\n",
+    );
+
+    let res = sigi(
+        "chores",
+        &["-d", &dir, "list-stacks", "--summary=30", "-f", "csv"],
+    );
+    let lines = res.stdout().lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3, "{:?}", lines);
+    assert_eq!(lines[0], "stack,items,top,newest,oldest,completed");
+    assert!(lines[1].starts_with("chores,2,sweep,"), "{}", lines[1]);
+    assert!(lines[1].ends_with(",1"), "{}", lines[1]);
+    assert_eq!(lines[2], "empty,0,,,,0");
+
+    let res = sigi(
+        "chores",
+        &["-d", &dir, "list-stacks", "--summary=4000000000"],
+    );
+    res.assert_exit_code(78);
+    res.assert_stderr_starts_with("Error: Invalid summary: 4000000000 days is further back");
+}
@@ -0,0 +1,104 @@
+mod run_sigi;
+
+use run_sigi::{fresh_dir, sigi};
+
+#[test]
+fn sigi_rejects_stack_names_outside_the_data_store() {
+    let parent = fresh_dir("sigi_integ_stack_name_traversal");
+    let dir = format!("{}/store", parent);
+
+    let res = sigi("../escaped", &["-d", &dir, "push", "nope"]);
+    res.assert_exit_code(64);
+    res.assert_stderr_starts_with("Error: Can't use \"../escaped\" as a stack name:");
+    assert!(!std::path::Path::new(&format!("{}/escaped.json", parent)).exists());
+
+    let res = sigi("work//bugs", &["-d", &dir, "list"]);
+    res.assert_exit_code(64);
+
+    let res = sigi("con", &["-d", &dir, "push", "nope"]);
+    res.assert_exit_code(64);
+}
+
+#[test]
+fn sigi_rejects_pushing_to_history_stacks() {
+    let dir = fresh_dir("sigi_integ_stack_name_history");
+
+    let res = sigi("_integ::names_history", &["-d", &dir, "push", "nope"]);
+    res.assert_exit_code(64);
+    res.assert_stderr_starts_with(
+        "Error: Can't use \"_integ::names_history\" as a stack name: names ending in \"_history\"",
+    );
+
+    // Reading and clearing history is still fine.
+    sigi("_integ::names", &["-d", &dir, "push", "done soon"]).assert_success();
+    sigi("_integ::names", &["-d", &dir, "complete"]).assert_success();
+    let res = sigi("_integ::names_history", &["-d", &dir, "count"]);
+    res.assert_stdout_eq("1\n");
+}
+
+#[test]
+fn sigi_unusual_stack_names_round_trip() {
+    let dir = fresh_dir("sigi_integ_stack_name_escaping");
+    let names = [
+        ".hidden",
+        "100% done",
+        "back\\slash",
+        "café au lait",
+        "work: <later>",
+        "what? *really* \"now\"|soon",
+        "etc.",
+        "trailing ",
+    ];
+
+    for name in names {
+        sigi(name, &["-d", &dir, "push", "something"]).assert_success();
+        let res = sigi(name, &["-d", &dir, "peek", "-q"]);
+        res.assert_stdout_eq("something\n");
+    }
+    assert!(std::path::Path::new(&format!("{}/%2Ehidden.json", dir)).exists());
+    assert!(std::path::Path::new(&format!("{}/100%25 done.json", dir)).exists());
+    assert!(std::path::Path::new(&format!("{}/work%3A %3Clater%3E.json", dir)).exists());
+    assert!(std::path::Path::new(&format!("{}/etc%2E.json", dir)).exists());
+    assert!(std::path::Path::new(&format!("{}/trailing%20.json", dir)).exists());
+
+    let res = sigi(".hidden", &["-d", &dir, "list-stacks", "-q"]);
+    let mut listed = res.stdout().lines().collect::<Vec<_>>();
+    listed.sort();
+    let mut expected = names.to_vec();
+    expected.sort();
+    assert_eq!(listed, expected);
+}
+
+#[test]
+fn sigi_stacks_stored_by_older_versions_stay_put() {
+    let dir = fresh_dir("sigi_integ_stack_name_legacy");
+    let stack = "_integ::legacy";
+    let legacy = format!("{}/{}.json", dir, stack);
+    std::fs::write(&legacy, "[]").unwrap();
+
+    sigi(stack, &["-d", &dir, "push", "still here"]).assert_success();
+    let res = sigi(stack, &["-d", &dir, "peek", "-q"]);
+    res.assert_stdout_eq("still here\n");
+    assert!(std::fs::read_to_string(&legacy)
+        .unwrap()
+        .contains("still here"));
+
+    // Including names with characters that are escaped now.
+    let percent = "50%off";
+    let legacy = format!("{}/{}.json", dir, percent);
+    std::fs::write(&legacy, "[]").unwrap();
+    sigi(percent, &["-d", &dir, "push", "sale"]).assert_success();
+    let res = sigi(percent, &["-d", &dir, "list", "-q"]);
+    res.assert_stdout_eq("sale\n");
+    assert!(std::fs::read_to_string(&legacy).unwrap().contains("sale"));
+    assert!(!std::path::Path::new(&format!("{}/50%25off.json", dir)).exists());
+
+    // New stacks get the new escaping.
+    sigi("_integ::new", &["-d", &dir, "push", "fresh"]).assert_success();
+    assert!(std::path::Path::new(&format!("{}/_integ%3A%3Anew.json", dir)).exists());
+
+    let res = sigi(stack, &["-d", &dir, "list-stacks", "-q"]);
+    let mut listed = res.stdout().lines().collect::<Vec<_>>();
+    listed.sort();
+    assert_eq!(listed, ["50%off", "_integ::legacy", "_integ::new"]);
+}
@@ -0,0 +1,164 @@
+mod run_sigi;
+
+use run_sigi::{dir_with_files, fresh_dir, piping, sigi};
+
+/// Stacks with some history, written out directly so the times are known.
+const STACKS: [(&str, &str); 4] = [
+    (
+        "work.json",
+        r#"[
+            {"id": "o1", "contents": "old", "history": [
+                {"event": "created", "time": "2024-05-01T00:00:00+00:00"}
+            ]},
+            {"id": "n1", "contents": "new", "history": [
+                {"event": "created", "time": "2024-06-05T00:00:00+00:00"}
+            ]}
+        ]"#,
+    ),
+    (
+        "work_history.json",
+        r#"[
+            {"id": "c1", "contents": "quick", "history": [
+                {"event": "created", "time": "2024-06-01T09:00:00+00:00"},
+                {"event": "completed", "time": "2024-06-01T11:00:00+00:00"}
+            ]},
+            {"id": "c2", "contents": "slow", "history": [
+                {"event": "created", "time": "2024-06-01T09:00:00+00:00"},
+                {"event": "completed", "time": "2024-06-04T09:00:00+00:00"}
+            ]},
+            {"id": "c3", "contents": "a day", "history": [
+                {"event": "created", "time": "2024-06-01T00:00:00+00:00"},
+                {"event": "completed", "time": "2024-06-02T00:00:00+00:00"}
+            ]},
+            {"id": "d1", "contents": "dropped", "history": [
+                {"event": "created", "time": "2024-06-02T00:00:00+00:00"},
+                {"event": "deleted", "time": "2024-06-03T00:00:00+00:00"}
+            ]}
+        ]"#,
+    ),
+    (
+        "home_history.json",
+        r#"[{"id": "h1", "contents": "buy milk", "history": [
+            {"event": "created", "time": "2024-06-01T12:00:00+00:00"},
+            {"event": "deleted", "time": "2024-06-01T13:00:00+00:00"}
+        ]}]"#,
+    ),
+    (
+        "_archive/old.json",
+        r#"[{"id": "a1", "contents": "forgotten", "history": [
+            {"event": "created", "time": "2024-01-01T00:00:00+00:00"}
+        ]}]"#,
+    ),
+];
+
+#[test]
+fn sigi_stats_per_stack_and_overall() {
+    let dir = dir_with_files("sigi_integ_stats_human", &STACKS);
+
+    let res = sigi(
+        "work",
+        &[
+            "-d",
+            &dir,
+            "--tz",
+            "utc",
+            "--time-format",
+            "%Y-%m-%d",
+            "stats",
+        ],
+    );
+    res.assert_success();
+    res.assert_stdout_eq(
+        "home: 0 open, 0 completed, 1 deleted (0% completed), \
+         0.0 completed a week over 4 weeks (latest first: 0, 0, 0, 0)\n\
+         work: 2 open, 3 completed, 1 deleted (75% completed), \
+         0.0 completed a week over 4 weeks (latest first: 0, 0, 0, 0), median 1 day to complete, \
+         oldest: old (2024-05-01), new (2024-06-05)\n\
+         Total: 2 open, 3 completed, 2 deleted (60% completed), \
+         0.0 completed a week over 4 weeks (latest first: 0, 0, 0, 0), median 1 day to complete, \
+         oldest: old (2024-05-01), new (2024-06-05)\n",
+    );
+}
+
+#[test]
+fn sigi_stats_for_programs() {
+    let dir = dir_with_files("sigi_integ_stats_csv", &STACKS);
+
+    let res = sigi(
+        "work",
+        &[
+            "-d", &dir, "--tz", "utc", "stats", "--stacks", "work", "--oldest", "1", "-f", "csv",
+        ],
+    );
+    res.assert_stdout_eq(
+        "stack,open,completed,deleted,completion-rate,per-week,by-week,median-time-to-complete,\
+         oldest-1,oldest-1-created\n\
+         work,2,3,1,75,0.0,0 0 0 0,86400,old,2024-05-01T00:00:00+00:00\n\
+         *,2,3,1,75,0.0,0 0 0 0,86400,old,2024-05-01T00:00:00+00:00\n",
+    );
+}
+
+#[test]
+fn sigi_stats_throughput() {
+    let dir = fresh_dir("sigi_integ_stats_throughput");
+    let stack = "_integ::stats";
+
+    for item in ["a", "b", "c"] {
+        sigi(stack, &["-d", &dir, "push", item]).assert_success();
+    }
+    sigi(stack, &["-d", &dir, "complete"]).assert_success();
+    sigi(stack, &["-d", &dir, "complete"]).assert_success();
+
+    let res = sigi(stack, &["-d", &dir, "stats", "--weeks", "2", "-f", "tsv"]);
+    let lines = res.stdout().lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), 3, "{:?}", lines);
+    assert!(
+        lines[1].starts_with("_integ::stats\t1\t2\t0\t100\t1.0\t2 0\t"),
+        "{}",
+        lines[1]
+    );
+
+    // Items completed a week and a half ago count for the week before.
+    let ago = (chrono::Utc::now() - chrono::Duration::days(10)).to_rfc3339();
+    let history = format!(
+        r#"[{{"id": "o1", "contents": "older", "history": [
+            {{"event": "created", "time": "{ago}"}},
+            {{"event": "completed", "time": "{ago}"}}
+        ]}}]"#
+    );
+    let older = dir_with_files(
+        "sigi_integ_stats_by_week",
+        &[("work_history.json", &history)],
+    );
+    let res = sigi(
+        "work",
+        &["-d", &older, "stats", "--weeks", "3", "-f", "tsv"],
+    );
+    res.assert_stdout_line_starts_with("work\t0\t1\t0\t100\t0.3\t0 1 0\t");
+
+    let res = sigi(stack, &["-d", &dir, "stats", "--weeks", "0"]);
+    res.assert_exit_code(2);
+
+    let res = sigi(stack, &["-d", &dir, "stats", "--weeks", "4000000000"]);
+    res.assert_exit_code(78);
+    res.assert_stderr_starts_with(
+        "Error: Invalid number of weeks: 4000000000 weeks is further back",
+    );
+}
+
+#[test]
+fn sigi_stats_in_interactive_mode() {
+    let dir = dir_with_files("sigi_integ_stats_interactive", &STACKS);
+
+    let res = piping(&[
+        "stats --stacks home --weeks 1 --oldest 1",
+        "statistics --weeks none",
+        "stats --oldest all",
+    ])
+    .into_sigi("work", &["-d", &dir, "interactive"]);
+    res.assert_stdout_line_starts_with(
+        "home: 0 open, 0 completed, 1 deleted (0% completed), 0 completed in the last week",
+    );
+    res.assert_stdout_line_starts_with("\"none\" isn't a number of weeks");
+    res.assert_stdout_line_starts_with("\"all\" isn't a number of items");
+}
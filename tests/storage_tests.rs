@@ -0,0 +1,87 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+use sigi::data::{Stack, Storage};
+use sigi::effects::{ItemRef, StackEffect};
+use sigi::error::SigiError;
+use sigi::output::OutputFormat;
+
+#[derive(Default)]
+struct MemoryStore {
+    stacks: RefCell<HashMap<String, Stack>>,
+}
+
+impl Storage for MemoryStore {
+    fn load(&self, stack_name: &str) -> Result<Stack, SigiError> {
+        Ok(self
+            .stacks
+            .borrow()
+            .get(stack_name)
+            .cloned()
+            .unwrap_or_default())
+    }
+
+    fn save(&self, stack_name: &str, items: Stack) -> Result<(), SigiError> {
+        self.stacks
+            .borrow_mut()
+            .insert(stack_name.to_string(), items);
+        Ok(())
+    }
+
+    fn list_stacks(&self) -> Result<Vec<String>, SigiError> {
+        Ok(self.stacks.borrow().keys().cloned().collect())
+    }
+
+    fn delete(&self, stack_name: &str) -> Result<(), SigiError> {
+        self.stacks.borrow_mut().remove(stack_name);
+        Ok(())
+    }
+}
+
+#[test]
+fn effects_run_against_custom_storage() {
+    let store = MemoryStore::default();
+    let silent = OutputFormat::Silent;
+    let stack = "memory".to_string();
+
+    for content in ["a", "b", "c"] {
+        let content = content.to_string();
+        let stack = stack.clone();
+        StackEffect::Push {
+            stack,
+            content,
+            tags: Default::default(),
+            due: None,
+            priority: None,
+        }
+        .run(&store, &silent)
+        .unwrap();
+    }
+    StackEffect::Complete {
+        stack: stack.clone(),
+        index: ItemRef::Position(0),
+        retention: None,
+    }
+    .run(&store, &silent)
+    .unwrap();
+    StackEffect::Swap {
+        stack: stack.clone(),
+    }
+    .run(&store, &silent)
+    .unwrap();
+
+    let items = store.load(&stack).unwrap();
+    let contents = items
+        .iter()
+        .map(|i| i.contents.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(contents, vec!["b", "a"]);
+
+    let history = store.load("memory_history").unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].contents, "c");
+
+    let mut stacks = store.list_stacks().unwrap();
+    stacks.sort();
+    assert_eq!(stacks, vec!["memory", "memory_history"]);
+}
@@ -0,0 +1,84 @@
+mod run_sigi;
+
+use run_sigi::{fresh_dir, piping, sigi};
+
+#[test]
+fn sigi_tags() {
+    let dir = fresh_dir("sigi_integ_tags");
+    let stack = "_integ::tags";
+
+    let res = sigi(
+        stack,
+        &[
+            "-d", &dir, "push", "+work", "+urgent", "fix", "the", "build",
+        ],
+    );
+    res.assert_success();
+    res.assert_stdout_eq("Created: fix the build\n");
+
+    sigi(stack, &["-d", &dir, "push", "+home", "water plants"]).assert_success();
+    sigi(stack, &["-d", &dir, "push", "call +1 555 0100"]).assert_success();
+
+    let res = sigi(stack, &["-d", &dir, "list", "--tag", "work"]);
+    res.assert_stdout_eq("  2: fix the build\n");
+
+    let res = sigi(
+        stack,
+        &["-d", &dir, "count", "--tag", "+work", "--tag", "urgent"],
+    );
+    res.assert_stdout_eq("1\n");
+
+    let res = sigi(
+        stack,
+        &["-d", &dir, "count", "--tag", "work", "--tag", "home"],
+    );
+    res.assert_stdout_eq("0\n");
+
+    let res = sigi(stack, &["-d", &dir, "tag", "1", "+work"]);
+    res.assert_success();
+    res.assert_stdout_eq("Tagged: water plants\n");
+
+    let res = sigi(stack, &["-d", &dir, "head", "1", "--tag", "work"]);
+    res.assert_stdout_eq("  1: water plants\n");
+
+    let res = sigi(stack, &["-d", &dir, "tail", "1", "--tag", "work"]);
+    res.assert_stdout_eq("  2: fix the build\n");
+
+    let res = sigi(stack, &["-d", &dir, "untag", "2", "urgent"]);
+    res.assert_success();
+
+    let res = sigi(stack, &["-d", &dir, "-f", "tsv", "list", "--tag", "work"]);
+    res.assert_stdout_line_starts_with("position\titem\tcreated\tid\ttags");
+    res.assert_stdout_line_starts_with("1\twater plants\t");
+    let tags = res
+        .stdout()
+        .lines()
+        .map(|line| line.split('\t').nth(4).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(tags, vec!["tags", "home work", "work"]);
+
+    let res = sigi(stack, &["-d", &dir, "-v", "list"]);
+    res.assert_stdout_line_starts_with("Now: call +1 555 0100 (");
+    let lines = res.stdout().lines().collect::<Vec<_>>();
+    assert!(!lines[0].contains(", +"));
+    assert!(lines[1].ends_with(", +home +work)"));
+}
+
+#[test]
+fn sigi_tags_from_stdin() {
+    let dir = fresh_dir("sigi_integ_tags_stdin");
+    let stack = "_integ::tags_stdin";
+
+    let res = piping(&[
+        "push +a one",
+        "push two",
+        "tag +b",
+        "tag 1 b",
+        "list +b",
+        "untag 0 b",
+        "count +b",
+    ])
+    .into_sigi(stack, &["-d", &dir, "-"]);
+    res.assert_stdout_eq("two\none\n1\n");
+    res.assert_stderr_empty();
+}
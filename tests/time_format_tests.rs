@@ -0,0 +1,96 @@
+mod run_sigi;
+
+use run_sigi::{fresh_dir, sigi, sigi_with_env};
+
+#[test]
+fn sigi_time_format_styles() {
+    let dir = fresh_dir("sigi_integ_time_format");
+    let stack = "_integ::time_format";
+    let due = "2024-06-01T09:30:00+02:00";
+    sigi(stack, &["-d", &dir, "push", "--due", due, "call"]).assert_success();
+
+    let show_due = |args: &[&str]| {
+        let mut all = vec!["-d", &dir, "-v", "--tz", "original"];
+        all.extend(args);
+        all.extend(["due", "--by", due]);
+        sigi(stack, &all)
+    };
+
+    show_due(&[]).assert_stdout_eq("Due: call (Sat, 1 Jun 2024 09:30:00 +0200)\n");
+    show_due(&["--time-format", "iso"]).assert_stdout_eq("Due: call (2024-06-01T09:30:00+02:00)\n");
+    show_due(&["--time-format", "%Y-%m-%d %H:%M"])
+        .assert_stdout_eq("Due: call (2024-06-01 09:30)\n");
+    show_due(&["--time-format", "%A %d %B", "--locale", "fr_FR"])
+        .assert_stdout_eq("Due: call (samedi 01 juin)\n");
+}
+
+#[test]
+fn sigi_time_format_relative() {
+    let dir = fresh_dir("sigi_integ_time_format_relative");
+    let stack = "_integ::time_format_relative";
+
+    sigi(stack, &["-d", &dir, "push", "--due", "3d", "soon"]).assert_success();
+    sigi(stack, &["-d", &dir, "push", "--due", "2h ago", "late"]).assert_success();
+
+    let res = sigi(
+        stack,
+        &["-d", &dir, "--time-format", "relative", "-v", "list"],
+    );
+    let lines = res.stdout().lines().collect::<Vec<_>>();
+    assert!(lines[0].starts_with("Now: late [overdue] (just now, @"));
+    assert!(lines[0].ends_with(", due 2 hours ago)"));
+    assert!(lines[1].starts_with("  1: soon (just now, @"));
+    assert!(lines[1].ends_with(", due in 3 days)"));
+
+    // Programmatic formats are always ISO 8601.
+    let res = sigi(
+        stack,
+        &["-d", &dir, "--time-format", "relative", "-f", "csv", "peek"],
+    );
+    assert!(!res.stdout().contains("ago"), "{}", res.stdout());
+}
+
+#[test]
+fn sigi_time_format_locale_from_environment() {
+    let dir = fresh_dir("sigi_integ_time_format_lang");
+    let stack = "_integ::time_format_lang";
+    let due = "2024-06-01T09:30:00+02:00";
+    sigi(stack, &["-d", &dir, "push", "--due", due, "call"]).assert_success();
+
+    let args = [
+        "-d",
+        &dir,
+        "-v",
+        "--tz",
+        "original",
+        "--time-format",
+        "%B",
+        "due",
+        "--by",
+        due,
+    ];
+    let res = sigi_with_env(
+        stack,
+        &[("LC_ALL", ""), ("LC_TIME", ""), ("LANG", "de_DE.UTF-8")],
+        &args,
+    );
+    res.assert_stdout_eq("Due: call (Juni)\n");
+
+    // Locales sigi doesn't know don't get in the way.
+    let res = sigi_with_env(
+        stack,
+        &[("LC_ALL", ""), ("LC_TIME", ""), ("LANG", "C.UTF-8")],
+        &args,
+    );
+    res.assert_stdout_eq("Due: call (June)\n");
+}
+
+#[test]
+fn sigi_time_format_rejects_bad_formats() {
+    let dir = fresh_dir("sigi_integ_time_format_bad");
+    let stack = "_integ::time_format_bad";
+
+    sigi(stack, &["-d", &dir, "--time-format", "%Q", "peek"]).assert_failure();
+    sigi(stack, &["-d", &dir, "--time-format", "sometime", "peek"]).assert_failure();
+    sigi(stack, &["-d", &dir, "--locale", "xx_YY", "peek"]).assert_failure();
+}
@@ -0,0 +1,114 @@
+mod run_sigi;
+
+use run_sigi::{fresh_dir, sigi, sigi_with_env, stack_file};
+
+/// The "due" column of a TSV peek.
+fn peek_due(res: &run_sigi::SigiOutput) -> String {
+    res.stdout()
+        .lines()
+        .nth(1)
+        .unwrap()
+        .split('\t')
+        .nth(5)
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn sigi_tz_changes_how_times_are_shown() {
+    let dir = fresh_dir("sigi_integ_tz");
+    let stack = "_integ::tz";
+
+    let due = "2024-06-01T09:30:00+02:00";
+    sigi(stack, &["-d", &dir, "push", "--due", due, "call"]).assert_success();
+
+    let res = sigi(
+        stack,
+        &["-d", &dir, "--tz", "original", "-f", "tsv", "peek"],
+    );
+    assert_eq!(peek_due(&res), "2024-06-01T09:30:00+02:00");
+
+    let res = sigi(stack, &["-d", &dir, "--tz", "utc", "-f", "tsv", "peek"]);
+    assert_eq!(peek_due(&res), "2024-06-01T07:30:00+00:00");
+
+    let res = sigi(stack, &["-d", &dir, "--tz=-05:00", "-f", "tsv", "peek"]);
+    assert_eq!(peek_due(&res), "2024-06-01T02:30:00-05:00");
+
+    let res = sigi(
+        stack,
+        &["-d", &dir, "--tz", "Asia/Tokyo", "-f", "tsv", "peek"],
+    );
+    assert_eq!(peek_due(&res), "2024-06-01T16:30:00+09:00");
+
+    let env = [("SIGI_TZ", "Asia/Tokyo")];
+    let res = sigi_with_env(stack, &env, &["-d", &dir, "-f", "tsv", "peek"]);
+    assert_eq!(peek_due(&res), "2024-06-01T16:30:00+09:00");
+
+    // The flag wins over the environment.
+    let res = sigi_with_env(
+        stack,
+        &env,
+        &["-d", &dir, "--tz", "utc", "-f", "tsv", "peek"],
+    );
+    assert_eq!(peek_due(&res), "2024-06-01T07:30:00+00:00");
+
+    let stored = std::fs::read_to_string(stack_file(&dir, stack)).unwrap();
+    assert!(stored.contains(due), "{}", stored);
+}
+
+#[test]
+fn sigi_tz_rejects_unknown_zones() {
+    let dir = fresh_dir("sigi_integ_tz_unknown");
+    let stack = "_integ::tz_unknown";
+
+    let res = sigi(stack, &["-d", &dir, "--tz", "Mars/Olympus_Mons", "peek"]);
+    res.assert_failure();
+
+    let res = sigi_with_env(
+        stack,
+        &[("SIGI_TZ", "Mars/Olympus_Mons")],
+        &["-d", &dir, "peek"],
+    );
+    res.assert_exit_code(78);
+    res.assert_stderr_starts_with("Error: Invalid SIGI_TZ: \"Mars/Olympus_Mons\"");
+}
+
+#[test]
+fn sigi_v1_times_keep_their_offsets() {
+    let dir = fresh_dir("sigi_integ_tz_v1");
+    let stack = "_integ::tz_v1";
+    let v1 =
+        r#"[{"name":"old","created":"2020-01-01T10:00:00+05:00","succeeded":null,"failed":null}]"#;
+    std::fs::write(stack_file(&dir, stack), v1).unwrap();
+
+    let res = sigi(
+        stack,
+        &["-d", &dir, "--tz", "original", "-f", "tsv", "peek"],
+    );
+    res.assert_stdout_line_starts_with("Now\told\t2020-01-01T10:00:00+05:00\t");
+
+    // Saving in the modern format keeps the offset too.
+    sigi(stack, &["-d", &dir, "tag", "+old"]).assert_success();
+    let stored = std::fs::read_to_string(stack_file(&dir, stack)).unwrap();
+    assert!(stored.contains("2020-01-01T10:00:00+05:00"), "{}", stored);
+}
+
+#[test]
+fn sigi_tz_reads_typed_times_in_the_same_zone() {
+    let dir = fresh_dir("sigi_integ_tz_input");
+    let stack = "_integ::tz_input";
+
+    let args = ["-d", &dir, "--tz", "Asia/Tokyo"];
+    sigi(
+        stack,
+        &[&args[..], &["push", "--due", "2024-06-01 09:30", "call"]].concat(),
+    )
+    .assert_success();
+    let res = sigi(stack, &[&args[..], &["-f", "tsv", "peek"]].concat());
+    assert_eq!(peek_due(&res), "2024-06-01T09:30:00+09:00");
+
+    let env = [("SIGI_TZ", "-05:00"), ("TZ", "Asia/Kolkata")];
+    sigi_with_env(stack, &env, &["-d", &dir, "due", "--by", "2024-06-02"]).assert_success();
+    let res = sigi_with_env(stack, &env, &["-d", &dir, "-f", "tsv", "peek"]);
+    assert_eq!(peek_due(&res), "2024-06-02T23:59:59-05:00");
+}
@@ -0,0 +1,168 @@
+mod run_sigi;
+
+use run_sigi::{fresh_dir, piping, sigi, stack_file};
+
+#[test]
+fn sigi_undo_redo() {
+    let dir = fresh_dir("sigi_integ_undo");
+    let stack = "_integ::undo";
+    let sigi = |args: &[&str]| sigi(stack, &[&["-d", &dir], args].concat());
+
+    let res = sigi(&["undo"]);
+    res.assert_success();
+    res.assert_stdout_eq("Undone: NOTHING\n");
+
+    sigi(&["push", "a"]).assert_success();
+    sigi(&["push", "b"]).assert_success();
+    sigi(&["push", "c"]).assert_success();
+
+    let res = sigi(&["rot"]);
+    res.assert_success();
+    res.assert_stdout_lines_eq(&["Now: b", "  1: a", "  2: c"]);
+
+    let res = sigi(&["undo"]);
+    res.assert_success();
+    res.assert_stdout_eq("Undone: rot\n");
+    res.assert_stderr_empty();
+
+    let res = sigi(&["list"]);
+    res.assert_stdout_lines_eq(&["Now: c", "  1: b", "  2: a"]);
+
+    let res = sigi(&["complete", "1"]);
+    res.assert_stdout_lines_eq(&["Completed: b", "Now: c"]);
+
+    let res = sigi(&["undo"]);
+    res.assert_stdout_eq("Undone: complete\n");
+
+    let res = sigi(&["list"]);
+    res.assert_stdout_lines_eq(&["Now: c", "  1: b", "  2: a"]);
+    let res = sigi_history_count(&dir, stack);
+    res.assert_stdout_eq("0\n");
+
+    let res = sigi(&["redo"]);
+    res.assert_success();
+    res.assert_stdout_eq("Redone: complete\n");
+
+    let res = sigi(&["list"]);
+    res.assert_stdout_lines_eq(&["Now: c", "  1: a"]);
+    let res = sigi_history_count(&dir, stack);
+    res.assert_stdout_eq("1\n");
+
+    let res = sigi(&["redo"]);
+    res.assert_stdout_eq("Redone: NOTHING\n");
+}
+
+#[test]
+fn sigi_journal_only_records_changes() {
+    for format in ["json", "sqlite"] {
+        let dir = fresh_dir(&format!("sigi_integ_undo_journal_{}", format));
+        let stack = "_integ::undo_journal";
+        let sigi = |args: &[&str]| {
+            let args = [&["--data-format", format, "-d", &dir], args].concat();
+            sigi(stack, &args)
+        };
+
+        for item in ["a", "b", "c"] {
+            sigi(&["push", item]).assert_success();
+        }
+        for read in [
+            &["list"][..],
+            &["peek"],
+            &["count"],
+            &["list-stacks"],
+            &["search", "a"],
+            &["report"],
+            &["stats"],
+        ] {
+            sigi(read).assert_success();
+        }
+
+        sigi(&["undo"]).assert_stdout_eq("Undone: push\n");
+        sigi(&["undo"]).assert_stdout_eq("Undone: push\n");
+        sigi(&["redo"]).assert_stdout_eq("Redone: push\n");
+        sigi(&["-q", "list"]).assert_stdout_eq("b\na\n");
+
+        // Something new can't have anything undone before it redone.
+        sigi(&["push", "d"]).assert_success();
+        sigi(&["redo"]).assert_stdout_eq("Redone: NOTHING\n");
+        for _ in 0..3 {
+            sigi(&["undo"]).assert_stdout_eq("Undone: push\n");
+        }
+        sigi(&["undo"]).assert_stdout_eq("Undone: NOTHING\n");
+        sigi(&["redo"]).assert_stdout_eq("Redone: push\n");
+        sigi(&["-q", "list"]).assert_stdout_eq("a\n");
+
+        if format == "json" {
+            // One line per change: 4 pushes, 5 undos and 2 redos.
+            let journal = std::fs::read_to_string(format!("{}/.journal.jsonl", dir)).unwrap();
+            assert_eq!(journal.lines().count(), 11, "{}", journal);
+        }
+    }
+}
+
+#[test]
+fn sigi_undo_refuses_when_stack_changed_since() {
+    let dir = fresh_dir("sigi_integ_undo_conflict");
+    let stack = "_integ::undo_conflict";
+    let sigi = |args: &[&str]| sigi(stack, &[&["-d", &dir], args].concat());
+
+    sigi(&["push", "a"]).assert_success();
+    sigi(&["push", "b"]).assert_success();
+    sigi(&["swap"]).assert_success();
+
+    // Change the stack behind the journal's back.
+    std::fs::write(stack_file(&dir, stack), "[]").unwrap();
+
+    let res = sigi(&["undo"]);
+    res.assert_exit_code(75);
+    res.assert_stderr_starts_with("Error: Stack \"_integ::undo_conflict\" has changed");
+}
+
+#[test]
+fn sigi_interactive_undo() {
+    let dir = fresh_dir("sigi_integ_undo_interactive");
+    let res = piping(&["push hello; undo; count"])
+        .into_sigi("_integ::undo_interactive", &["-d", &dir, "interactive"]);
+
+    res.assert_stderr_empty();
+    res.assert_stdout_line_eq("Created: hello");
+    res.assert_stdout_line_eq("Undone: push");
+    res.assert_stdout_line_eq("0");
+}
+
+fn sigi_history_count(dir: &str, stack: &str) -> run_sigi::SigiOutput {
+    let history = format!("{}_history", stack);
+    sigi(&history, &["-d", dir, "count"])
+}
+
+#[test]
+fn sigi_journal_is_rewritten_only_when_that_shrinks_it() {
+    // A journal just short of 1 MiB, of entries with long names.
+    let journal_of = |entries: usize, name_len: usize| {
+        let line = format!(
+            "{{\"record\":{{\"action\":\"{}\",\"time\":\"2024-06-01T00:00:00+00:00\",\"changes\":[]}}}}\n",
+            "x".repeat(name_len)
+        );
+        let mut journal = line.repeat(entries);
+        journal.push_str(&"\n".repeat((1 << 20) - 1 - journal.len()));
+        journal
+    };
+
+    for (name, entries, name_len, lines_after, rewritten) in [
+        // Most of it is forgotten entries, so it's rewritten to the 100 kept.
+        ("sigi_integ_journal_rewrite", 1000, 900, 100, true),
+        // It's all kept entries, so it's left as it is.
+        ("sigi_integ_journal_no_rewrite", 50, 20000, 51, false),
+    ] {
+        let dir = fresh_dir(name);
+        let path = format!("{}/.journal.jsonl", dir);
+        std::fs::write(&path, journal_of(entries, name_len)).unwrap();
+
+        sigi("_integ::journal", &["-d", &dir, "push", "a"]).assert_success();
+        let journal = std::fs::read_to_string(&path).unwrap();
+        let lines = journal.lines().filter(|line| !line.is_empty()).count();
+        assert_eq!(lines, lines_after, "{}", name);
+        assert_eq!(journal.len() < 1 << 20, rewritten, "{}", name);
+        sigi("_integ::journal", &["-d", &dir, "undo"]).assert_stdout_eq("Undone: push\n");
+    }
+}